@@ -1,12 +1,42 @@
-use crate::{DateTime, Msg, Store, StoreRow};
+use crate::unit::{Money, Unit};
+use crate::{DateTime, Msg, NewItem, Store, StoreRow};
 use gtk::prelude::*;
 use relm4::gtk;
 use relm4::{ComponentParts, ComponentSender, SimpleComponent};
 
 #[derive(Debug)]
 pub(crate) enum WarningOrigin {
-    Receipt { store: StoreRow, date: DateTime },
-    Store { name: String, location: String },
+    Receipt {
+        store: StoreRow,
+        date: DateTime,
+        /// The date as it is displayed
+        date_label: String,
+        currency: Option<Unit>,
+        go_to_items: bool,
+    },
+    Store {
+        name: String,
+        location: String,
+        city: String,
+        country: String,
+    },
+    Item {
+        item: NewItem,
+        /// The item of the receipt with the same name
+        existing_id: i64,
+        /// Whether the existing item has the same price and unit, so the quantities can be added
+        mergeable: bool,
+    },
+    LargeAmount {
+        item: NewItem,
+        /// The configured amount the line exceeds
+        threshold: Money,
+    },
+    Currency {
+        item: NewItem,
+        /// The unit the items of the receipt are expected to have
+        currency: Unit,
+    },
 }
 
 pub(crate) struct Dialog {
@@ -18,6 +48,7 @@ pub(crate) struct Dialog {
 pub(crate) enum DialogMsg {
     Show(WarningOrigin),
     Accept,
+    Merge,
     Cancel,
 }
 
@@ -38,34 +69,69 @@ impl SimpleComponent for Dialog {
             set_visible: !model.hidden,
             #[track(!model.hidden)]
             set_text: Some(&match &model.origin {
-                WarningOrigin::Receipt{ store, date } => {
-                    format!("A receipt for {} ({}) on {} already exists.", store.name, store.location, date.format("%F").unwrap().as_str())
+                WarningOrigin::Receipt{ store, date_label, .. } => {
+                    format!("A receipt for {} ({}) on {} already exists.", store.name, store.location, date_label)
                 }
-                WarningOrigin::Store{name, location} => {
+                WarningOrigin::Store{name, location, ..} => {
                     format!("A store {} at {} already exists.", name, location)
                 }
+                WarningOrigin::Item{ item, .. } => {
+                    format!("The receipt already has an item {}.", item.name)
+                }
+                WarningOrigin::LargeAmount{ item, .. } => {
+                    format!("{} × {} costs {:#}.", item.quantity, item.name, item.price.times(item.quantity))
+                }
+                WarningOrigin::Currency{ item, currency } => {
+                    format!("{} is in {}, the receipt is in {currency}.", item.name, item.price.unit)
+                }
             }),
             #[track(!model.hidden)]
-            set_secondary_text: match &model.origin {
+            set_secondary_text: Some(&match &model.origin {
                 WarningOrigin::Receipt{ .. } => {
-                    Some("It is uncommon to have two receipts for the same store on the same day. Do you really want to add this receipt?")
+                    "It is uncommon to have two receipts for the same store on the same day. Do you really want to add this receipt?".to_string()
                 }
                 WarningOrigin::Store{ .. } => {
-                    Some("It is uncommon to have two stores with the same name at the same location. Do you really want to add this store?")
+                    "It is uncommon to have two stores with the same name at the same location. Do you really want to add this store?".to_string()
                 }
-            },
+                WarningOrigin::Item{ mergeable: true, .. } => {
+                    "Adding an item twice is often a mistake. Do you want to add it anyway, or add its quantity to the existing item?".to_string()
+                }
+                WarningOrigin::Item{ mergeable: false, .. } => {
+                    "Adding an item twice is often a mistake. Do you really want to add this item?".to_string()
+                }
+                WarningOrigin::LargeAmount{ threshold, .. } => {
+                    format!("That is more than {threshold:#}, which is often a typo in the price or quantity. Do you really want to add this item?")
+                }
+                WarningOrigin::Currency{ .. } => {
+                    "Receipts are rarely paid in two currencies, so the unit is probably a mistake. Do you really want to add this item?".to_string()
+                }
+            }),
             add_button: ("Add", gtk::ResponseType::Accept),
+            add_button: ("Merge Quantities", gtk::ResponseType::Apply),
             add_button: ("Cancel", gtk::ResponseType::Cancel),
             connect_response[sender] => move |_, resp| {
-                sender.input(if resp == gtk::ResponseType::Accept {
-                    DialogMsg::Accept
-                } else {
-                    DialogMsg::Cancel
+                sender.input(match resp {
+                    gtk::ResponseType::Accept => DialogMsg::Accept,
+                    gtk::ResponseType::Apply => DialogMsg::Merge,
+                    _ => DialogMsg::Cancel,
                 });
             }
         }
     }
 
+    fn post_view() {
+        let model: &Dialog = model;
+        if let Some(merge_button) = dialog.widget_for_response(gtk::ResponseType::Apply) {
+            merge_button.set_visible(matches!(
+                model.origin,
+                WarningOrigin::Item {
+                    mergeable: true,
+                    ..
+                }
+            ));
+        }
+    }
+
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
             DialogMsg::Show(origin) => {
@@ -75,15 +141,54 @@ impl SimpleComponent for Dialog {
             DialogMsg::Accept => {
                 self.hidden = true;
                 match &self.origin {
-                    WarningOrigin::Receipt { store, date } => {
-                        sender.output(Msg::ForceAddReceipt(store.id, date.format("%F").unwrap()));
+                    WarningOrigin::Receipt {
+                        store,
+                        date,
+                        currency,
+                        go_to_items,
+                        ..
+                    } => {
+                        sender.output(Msg::ForceAddReceipt {
+                            store_id: store.id,
+                            date: date.clone(),
+                            currency: *currency,
+                            go_to_items: *go_to_items,
+                        });
                     }
-                    WarningOrigin::Store { name, location } => {
+                    WarningOrigin::Store {
+                        name,
+                        location,
+                        city,
+                        country,
+                    } => {
                         sender.output(Msg::ForceAddStore(Store {
                             name: name.as_str().into(),
                             location: location.as_str().into(),
+                            city: city.as_str().into(),
+                            country: country.as_str().into(),
                         }));
                     }
+                    WarningOrigin::Item { item, .. } => {
+                        sender.output(Msg::ForceAddItem(item.clone()));
+                    }
+                    WarningOrigin::LargeAmount { item, .. } => {
+                        sender.output(Msg::ConfirmLargeAmount(item.clone()));
+                    }
+                    WarningOrigin::Currency { item, .. } => {
+                        sender.output(Msg::ConfirmCurrency(item.clone()));
+                    }
+                }
+            }
+            DialogMsg::Merge => {
+                self.hidden = true;
+                if let WarningOrigin::Item {
+                    item, existing_id, ..
+                } = &self.origin
+                {
+                    sender.output(Msg::MergeItem {
+                        existing_id: *existing_id,
+                        item: item.clone(),
+                    });
                 }
             }
             DialogMsg::Cancel => self.hidden = true,
@@ -104,6 +209,9 @@ impl SimpleComponent for Dialog {
                     location: String::new(),
                 },
                 date: DateTime::now_utc().unwrap(),
+                date_label: String::new(),
+                currency: None,
+                go_to_items: false,
             },
         };
 
@@ -1,36 +1,137 @@
+use crate::analysis::attachments::{Attachment, ShowAttachments};
+use crate::analysis::cell_details::CellDetails;
+use crate::analysis::chart::Chart;
 use crate::analysis::edit_query_dialog::QueryDialog;
-use crate::analysis::input_values::{InputValue, InputValueMsg};
+use crate::analysis::import_queries::{Import, Resolution, ShowImport};
+use crate::analysis::input_values::{input_values_file, InputValue, InputValueMsg};
+use crate::analysis::markdown::markdown_table;
+use crate::analysis::query_picker::{matching_queries, ShowQueryPicker};
+use crate::analysis::result_cache::{CachedResult, LastRun};
 use crate::combobox::AppendAll;
+use crate::csv;
+use crate::date::{convert_date, resolve_date, to_iso_date, StorageFormat};
+use crate::dialog_ext::AppendDialog;
+use crate::error::{AppError, Report};
+use crate::unit::{format_amount, Money, Unit};
 use crate::Msg;
+use native_dialog::FileDialog;
 use relm4::gtk;
 use relm4::gtk::glib::{DateTime, GString, Type, Value};
 use relm4::gtk::prelude::*;
 use relm4::{
-    Component, ComponentController, ComponentParts, ComponentSender, Controller, SimpleComponent,
+    Component, ComponentController, ComponentParts, ComponentSender, Controller, RelmWidgetExt,
 };
 use rusqlite::types::ToSqlOutput;
-use rusqlite::{Connection, ToSql};
+use rusqlite::{ffi, Connection, ToSql};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::identity;
+use std::ffi::{CStr, CString};
 use std::fmt::Formatter;
 use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use tap::TapFallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
+mod attachments;
+mod cell_details;
+mod chart;
 mod edit_query_dialog;
+mod import_queries;
 mod input_values;
+mod markdown;
+mod query_picker;
+mod result_cache;
 mod type_component;
 
 #[derive(Debug)]
 pub(crate) enum AnalysisMsg {
     PopulateModel(usize),
     NewQuery(String),
+    /// Creates a query with the name from the definitions of the query at the index.
+    /// Without `keep_sql` only the header and input definitions are copied.
+    NewQueryFrom {
+        name: String,
+        source: usize,
+        keep_sql: bool,
+    },
     EditQuery(usize),
     DeleteQuery(usize),
     EditQueryResult(Query, String, usize),
-    ConnectDb(Rc<Connection>),
+    /// The connection and the path of its database, whose queries are loaded
+    ConnectDb(Rc<Connection>, String),
     QuerySelected(Option<usize>),
+    /// Filters the names of the query picker
+    FilterQueries(String),
+    /// Selects the query at the position of the filtered query picker
+    PickQuery(usize),
     NewQueryNameChanged(GString),
+    CopyTarget(gtk::TreePath, Option<i32>),
+    CopyCell,
+    CopyRow,
+    CsvDelimiter(csv::Delimiter),
+    /// How many inputs and header columns a query needs at least
+    RequiredRows(usize, usize),
+    StorageDateFormat(StorageFormat),
+    /// Shows the money columns of results in major units, e.g. `19.90` instead of `1990`
+    MajorUnits(bool),
+    /// Bound as [`EXCLUDE_REFUNDS_PARAMETER`]
+    ExcludeRefunds(bool),
+    ExportCsv,
+    /// Copies the shown rows and columns of the result as a Markdown table
+    CopyMarkdown,
+    CancelExport,
+    /// Creates a table with the name in the connected database from the current result
+    SaveAsTable(String),
+    /// Asks for a database file and attaches it under the alias
+    Attach(String),
+    /// Detaches the attachment at the index and forgets it
+    Detach(usize),
+    ColumnResized(usize, i32),
+    ColumnVisibility(usize, bool),
+    /// Shows or hides the stored values of the result cells of the type when they are hovered
+    CellDetails(ColumnType, bool),
+    /// Sent by the save timer with the generation it was scheduled for.
+    SaveQueries(u64),
+    CellEdited(gtk::TreePath, usize, String),
+    FilterResults(String),
+    ShowChart(bool),
+    /// Restores the divider position between results and inputs
+    InputPanelPosition(i32),
+    /// Reads the queries of a queries file and asks what happens to the ones whose name is taken
+    ImportQueries,
+    ImportResolution(usize, Resolution),
+    ApplyImport,
+    CancelImport,
+}
+
+/// Bound to whether refunds are left out of totals, if the sql of a query uses it, e.g.
+/// `WHERE NOT :exclude_refunds OR price * quantity >= 0`. An input of the name takes precedence.
+pub(crate) const EXCLUDE_REFUNDS_PARAMETER: &str = "exclude_refunds";
+
+/// How many inputs and header columns a query needs at least, unless the settings change it.
+pub(crate) const DEFAULT_REQUIRED_ROWS: (usize, usize) = (0, 1);
+
+/// Changes within this delay are written to the queries file together.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+/// The CSV export reports its progress and checks for cancellation after this many rows.
+const EXPORT_PROGRESS_ROWS: usize = 1000;
+/// Queries of databases without a queries file of their own, see [`queries_file`].
+const SHARED_QUERIES_FILE: &str = "./sqlbon_queries.json";
+/// Version of the format of the queries files, older files are upgraded when they are read,
+/// see [`upgrade_queries`]. The bare list of queries of the first releases is version 0.
+const QUERIES_VERSION: u64 = 1;
+
+/// Sent by the thread writing a CSV export.
+#[derive(Debug)]
+pub(crate) enum ExportProgress {
+    Rows(usize),
+    /// The number of exported rows, `None` if the export was cancelled
+    Done(Result<Option<usize>, String>),
 }
 
 #[tracker::track]
@@ -39,33 +140,289 @@ pub(crate) struct Analysis {
     analysis: Option<Data>,
     #[tracker::no_eq]
     queries: Vec<(String, Query)>,
+    /// Where the queries are saved, next to the connected database
+    #[tracker::do_not_track]
+    queries_file: PathBuf,
     #[tracker::do_not_track]
     conn: Option<Rc<Connection>>,
     #[tracker::do_not_track]
     new_button_valid: bool,
     selected_query: Option<usize>,
     query_selected: bool,
+    /// Text of the search entry of the query picker
+    query_filter: String,
     #[tracker::do_not_track]
     query_dialog: Controller<edit_query_dialog::QueryDialog>,
     #[tracker::do_not_track]
     input_values: Controller<input_values::InputValue>,
     #[tracker::no_eq]
     query_error: String,
+    /// Row and column of the result table the copy actions operate on
+    #[tracker::do_not_track]
+    copy_target: Option<(gtk::TreePath, Option<i32>)>,
+    #[tracker::do_not_track]
+    csv_delimiter: csv::Delimiter,
+    /// Date inputs and edited dates are written like the receipt dates
+    #[tracker::do_not_track]
+    storage_date_format: StorageFormat,
+    /// Money columns are shown and edited in major units
+    #[tracker::do_not_track]
+    major_units: bool,
+    /// Queries leave out refunds, if they use [`EXCLUDE_REFUNDS_PARAMETER`]
+    #[tracker::do_not_track]
+    exclude_refunds: bool,
+    /// Whether changes of the queries are not written to the file yet
+    #[tracker::do_not_track]
+    save_pending: bool,
+    /// Only the latest scheduled save timer writes the file
+    #[tracker::do_not_track]
+    save_generation: u64,
+    /// Shared with the draw function of the chart area
+    #[tracker::do_not_track]
+    chart: Rc<RefCell<Option<Chart>>>,
+    /// Shared with the tooltip handler of the result table
+    #[tracker::do_not_track]
+    cell_details: Rc<RefCell<CellDetails>>,
+    show_chart: bool,
+    input_panel_position: Option<i32>,
+    /// Of the selected query
+    #[tracker::no_eq]
+    description: String,
+    /// When the shown result was read, if it is not the result of running the query just now
+    #[tracker::no_eq]
+    result_note: String,
+    exporting: bool,
+    #[tracker::no_eq]
+    export_status: String,
+    /// Set to stop the running export
+    #[tracker::do_not_track]
+    export_cancel: Arc<AtomicBool>,
+    /// Of the connected database
+    #[tracker::no_eq]
+    attachments: Vec<Attachment>,
+    /// Imported queries waiting for the import dialog
+    #[tracker::no_eq]
+    pending_import: Option<Import>,
 }
 
 struct Data {
     store: gtk::ListStore,
+    /// The rows of `store` that match `filter_text`
+    filter: gtk::TreeModelFilter,
+    /// The filtered rows in the order of the clicked column header, shown by the result table
+    sorted: gtk::TreeModelSort,
+    /// Shared with the visible function of `filter`
+    filter_text: Rc<RefCell<String>>,
     query_id: usize,
+    /// Whether a result column can be written back, see [`WriteBack`]
+    editable: Vec<bool>,
+    /// The currency of the number columns, see [`unit_column`]
+    unit_column: Option<usize>,
+}
+
+impl Data {
+    /// Shows the rows, which have the types of the header of the query. If the rows have a unit,
+    /// their number columns are shown as amounts of money.
+    fn new(
+        query_id: usize,
+        query: &Query,
+        rows: &[Vec<ColumnTypeValue>],
+        editable: Vec<bool>,
+        major_units: bool,
+    ) -> Self {
+        let unit_column = unit_column(&query.table_header);
+        let mut ctypes: Vec<Type> = query
+            .table_header
+            .0
+            .iter()
+            .map(|row_entry| row_entry.ty.into())
+            .collect();
+        // money columns show a text after the stored values, which they are still sorted by
+        if unit_column.is_some() {
+            ctypes.extend(vec![Type::STRING; query.table_header.0.len()]);
+        }
+        let store = gtk::ListStore::new(ctypes.as_slice());
+        for values in rows {
+            let mut value_refs = Vec::with_capacity(values.len());
+            for (i, value) in values.iter().enumerate() {
+                value_refs.push((i as u32, value as &dyn ToValue));
+            }
+
+            let iter = store.append();
+            store.set(&iter, value_refs.as_slice());
+        }
+
+        let filter_text = Rc::new(RefCell::new(String::new()));
+        let filter = gtk::TreeModelFilter::new(&store, None);
+        let header = query.table_header.clone();
+        let visible_text = Rc::clone(&filter_text);
+        filter.set_visible_func(move |model, iter| {
+            row_matches(&row_values(model, iter, &header), &visible_text.borrow())
+        });
+        let sorted = gtk::TreeModelSort::with_model(&filter);
+        let data = Data {
+            store,
+            filter,
+            sorted,
+            filter_text,
+            query_id,
+            editable,
+            unit_column,
+        };
+        data.show_amounts(&query.table_header, major_units);
+        data
+    }
+
+    /// The column of `store` with the text shown for the result column.
+    fn text_column(&self, column: usize, header: &RowData) -> usize {
+        match (self.unit_column, header.0.get(column)) {
+            (Some(_), Some(row_entry)) if row_entry.ty == ColumnType::Number => {
+                header.0.len() + column
+            }
+            _ => column,
+        }
+    }
+
+    /// Writes the text of the number columns of every row, see [`Data::show_row_amounts`].
+    fn show_amounts(&self, header: &RowData, major_units: bool) {
+        if let Some(iter) = self.store.iter_first() {
+            self.show_row_amounts(&iter, header, major_units);
+            while self.store.iter_next(&iter) {
+                self.show_row_amounts(&iter, header, major_units);
+            }
+        }
+    }
+
+    /// Writes the numbers of the row as amounts of its unit. Numbers of an unknown unit are
+    /// shown as stored.
+    fn show_row_amounts(&self, iter: &gtk::TreeIter, header: &RowData, major_units: bool) {
+        if let Some(unit_column) = self.unit_column {
+            let unit: String = self.store.get(iter, unit_column as i32);
+            for (i, row_entry) in header.0.iter().enumerate() {
+                if row_entry.ty == ColumnType::Number {
+                    let amount: i64 = self.store.get(iter, i as i32);
+                    let text = format_amount(amount, &unit, major_units);
+                    self.store.set_value(
+                        iter,
+                        self.text_column(i, header) as u32,
+                        &text.to_value(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Converts a path of the result table to the path of the row in `store`.
+    fn store_path(&self, path: &gtk::TreePath) -> Option<gtk::TreePath> {
+        self.sorted
+            .convert_path_to_child_path(path)
+            .and_then(|path| self.filter.convert_path_to_child_path(&path))
+    }
+
+    /// The values of every row in `store`, unfiltered and unsorted.
+    fn all_rows(&self, header: &RowData) -> Vec<Vec<ColumnTypeValue>> {
+        model_rows(&self.store, header)
+    }
+
+    /// The values of the rows shown by the result table, filtered and sorted.
+    fn shown_rows(&self, header: &RowData) -> Vec<Vec<ColumnTypeValue>> {
+        model_rows(&self.sorted, header)
+    }
 }
 
 #[relm4::component(pub(crate))]
-impl SimpleComponent for Analysis {
+impl Component for Analysis {
+    type CommandOutput = ExportProgress;
     type Input = AnalysisMsg;
     type Output = Msg;
     type Init = gtk::Window;
     type Widgets = AnalysisWidgets;
 
     view! {
+        #[name(columns_menu)]
+        gtk::Popover {
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                #[name(columns_box)]
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                },
+                gtk::Separator {},
+                gtk::Label {
+                    set_label: "stored values on hover",
+                    set_xalign: 0.0,
+                },
+                gtk::CheckButton {
+                    set_label: Some("numbers"),
+                    #[track(model.changed(Analysis::analysis()))]
+                    set_active: model.shows_cell_details(ColumnType::Number),
+                    connect_toggled[sender] => move |check| {
+                        sender.input(AnalysisMsg::CellDetails(ColumnType::Number, check.is_active()));
+                    },
+                },
+                gtk::CheckButton {
+                    set_label: Some("dates"),
+                    #[track(model.changed(Analysis::analysis()))]
+                    set_active: model.shows_cell_details(ColumnType::Date),
+                    connect_toggled[sender] => move |check| {
+                        sender.input(AnalysisMsg::CellDetails(ColumnType::Date, check.is_active()));
+                    },
+                },
+            },
+        },
+        #[name(copy_menu)]
+        gtk::Popover {
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                gtk::Button {
+                    set_label: "copy cell",
+                    connect_clicked[sender, copy_menu] => move |_| {
+                        copy_menu.popdown();
+                        sender.input(AnalysisMsg::CopyCell);
+                    },
+                },
+                gtk::Button {
+                    set_label: "copy row",
+                    connect_clicked[sender, copy_menu] => move |_| {
+                        copy_menu.popdown();
+                        sender.input(AnalysisMsg::CopyRow);
+                    },
+                },
+            },
+        },
+        #[name(import_dialog)]
+        gtk::Dialog {
+            set_modal: true,
+            set_transient_for: Some(&parent_window),
+            set_title: Some("Import queries"),
+            #[track(model.changed(Analysis::pending_import()))]
+            set_visible: model.pending_import.is_some(),
+            add_button: ("Import", gtk::ResponseType::Accept),
+            add_button: ("Cancel", gtk::ResponseType::Cancel),
+            connect_response[sender] => move |_, resp| {
+                sender.input(if resp == gtk::ResponseType::Accept {
+                    AnalysisMsg::ApplyImport
+                } else {
+                    AnalysisMsg::CancelImport
+                });
+            },
+            append = &gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_margin_all: 5,
+                set_spacing: 5,
+                gtk::Label {
+                    set_xalign: 0.0,
+                    #[track(model.changed(Analysis::pending_import()))]
+                    set_label: &model.pending_import.as_ref().map(Import::summary).unwrap_or_default(),
+                },
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 5,
+                    #[track(model.changed(Analysis::pending_import()))]
+                    show_import: (model.pending_import.as_ref(), &sender),
+                },
+            },
+        },
         #[root]
         #[name(analysis_box)]
         gtk::Box {
@@ -73,13 +430,58 @@ impl SimpleComponent for Analysis {
             gtk::Grid {
                 set_vexpand: true,
                 set_valign: gtk::Align::Center,
-                attach[0, 0, 2, 1]: selected_query = &gtk::ComboBoxText {
-                    #[track(model.changed(Analysis::queries()))]
-                    append_all: model.queries.iter().map(|(n, _)|n).cloned(),
-                    #[track(model.changed(Analysis::selected_query()))]
-                    set_active: model.selected_query.map(|id| id.try_into().unwrap()),
-                    connect_changed[sender] => move |query| {
-                        sender.input(AnalysisMsg::QuerySelected(query.active().map(|id| id as usize)));
+                attach[0, 0, 2, 1] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 5,
+                    #[name(selected_query)]
+                    gtk::ComboBoxText {
+                        set_hexpand: true,
+                        #[track(model.changed(Analysis::queries()))]
+                        append_all: model.queries.iter().map(|(n, _)|n).cloned(),
+                        #[track(model.changed(Analysis::selected_query()))]
+                        set_active: model.selected_query.map(|id| id.try_into().unwrap()),
+                        connect_changed[sender] => move |query| {
+                            sender.input(AnalysisMsg::QuerySelected(query.active().map(|id| id as usize)));
+                        },
+                    },
+                    gtk::MenuButton {
+                        set_icon_name: "system-search-symbolic",
+                        set_tooltip_text: Some("Search the queries by name"),
+                        #[wrap(Some)]
+                        #[name(query_popover)]
+                        set_popover = &gtk::Popover {
+                            connect_show[query_search] => move |_| {
+                                query_search.grab_focus();
+                            },
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_spacing: 5,
+                                #[name(query_search)]
+                                gtk::SearchEntry {
+                                    connect_search_changed[sender] => move |search| {
+                                        sender.input(AnalysisMsg::FilterQueries(search.text().to_string()));
+                                    },
+                                    // enter picks the first match
+                                    connect_activate[sender, query_popover] => move |_| {
+                                        sender.input(AnalysisMsg::PickQuery(0));
+                                        query_popover.popdown();
+                                    },
+                                },
+                                gtk::ScrolledWindow {
+                                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                                    set_min_content_height: 300,
+                                    set_min_content_width: 250,
+                                    gtk::ListBox {
+                                        #[track(model.changed(Analysis::queries()) || model.changed(Analysis::query_filter()))]
+                                        show_queries: (&model.queries, &model.query_filter),
+                                        connect_row_activated[sender, query_popover] => move |_, row| {
+                                            sender.input(AnalysisMsg::PickQuery(row.index() as usize));
+                                            query_popover.popdown();
+                                        },
+                                    },
+                                },
+                            },
+                        },
                     },
                 },
                 attach[0, 1, 1, 1]: name_entry = &gtk::Entry {
@@ -100,6 +502,41 @@ impl SimpleComponent for Analysis {
                         }
                     },
                 },
+                attach[0, 5, 1, 1] = &gtk::Button {
+                    set_label: "duplicate",
+                    #[watch]
+                    set_sensitive: model.new_button_valid && model.query_selected,
+                    connect_clicked[sender, name_entry, selected_query] => move |_| {
+                        let name = name_entry.text();
+                        let name = name.trim();
+                        if let (false, Some(source)) = (name.is_empty(), selected_query.active()) {
+                            name_entry.set_text("");
+                            sender.input(AnalysisMsg::NewQueryFrom {
+                                name: name.to_string(),
+                                source: source as usize,
+                                keep_sql: true,
+                            });
+                        }
+                    },
+                },
+                attach[1, 5, 1, 1] = &gtk::Button {
+                    set_label: "as template",
+                    set_tooltip_text: Some("Creates a query with the header and input definitions of the selected query, but without its SQL."),
+                    #[watch]
+                    set_sensitive: model.new_button_valid && model.query_selected,
+                    connect_clicked[sender, name_entry, selected_query] => move |_| {
+                        let name = name_entry.text();
+                        let name = name.trim();
+                        if let (false, Some(source)) = (name.is_empty(), selected_query.active()) {
+                            name_entry.set_text("");
+                            sender.input(AnalysisMsg::NewQueryFrom {
+                                name: name.to_string(),
+                                source: source as usize,
+                                keep_sql: false,
+                            });
+                        }
+                    },
+                },
                 attach[0, 2, 1, 1] = &gtk::Button {
                     set_label: "edit",
                     #[track]
@@ -130,29 +567,230 @@ impl SimpleComponent for Analysis {
                         }
                     },
                 },
+                attach[0, 4, 1, 1] = &gtk::Button {
+                    set_label: "export csv",
+                    #[track(model.changed(Analysis::analysis()) || model.changed(Analysis::exporting()))]
+                    set_sensitive: model.analysis.is_some() && !model.exporting,
+                    connect_clicked[sender] => move |_| {
+                        sender.input(AnalysisMsg::ExportCsv);
+                    },
+                },
+                attach[1, 4, 1, 1] = &gtk::Button {
+                    set_label: "copy as markdown",
+                    set_tooltip_text: Some("Copies the shown rows and columns as a Markdown table, e.g. for notes."),
+                    #[track(model.changed(Analysis::analysis()))]
+                    set_sensitive: model.analysis.is_some(),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(AnalysisMsg::CopyMarkdown);
+                    },
+                },
+                attach[0, 7, 2, 1] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 5,
+                    #[track(model.changed(Analysis::export_status()))]
+                    set_visible: !model.export_status.is_empty(),
+                    gtk::Spinner {
+                        #[track(model.changed(Analysis::exporting()))]
+                        set_spinning: model.exporting,
+                        #[track(model.changed(Analysis::exporting()))]
+                        set_visible: model.exporting,
+                    },
+                    gtk::Label {
+                        set_hexpand: true,
+                        set_xalign: 0.0,
+                        set_wrap: true,
+                        #[track(model.changed(Analysis::export_status()))]
+                        set_text: &model.export_status,
+                    },
+                    gtk::Button {
+                        set_label: "cancel",
+                        #[track(model.changed(Analysis::exporting()))]
+                        set_visible: model.exporting,
+                        connect_clicked[sender] => move |_| {
+                            sender.input(AnalysisMsg::CancelExport);
+                        },
+                    },
+                },
+                attach[0, 6, 2, 1] = &gtk::ToggleButton {
+                    set_label: "chart",
+                    set_tooltip_text: Some("Shows results with one String or Date column followed by one Number column as a chart."),
+                    connect_toggled[sender] => move |toggle| {
+                        sender.input(AnalysisMsg::ShowChart(toggle.is_active()));
+                    },
+                },
+                attach[0, 9, 2, 1] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 5,
+                    #[name(table_name)]
+                    gtk::Entry {
+                        set_hexpand: true,
+                        set_placeholder_text: Some("table name"),
+                        set_tooltip_text: Some("Saves all rows of the result as a new table of the connected database."),
+                    },
+                    gtk::Button {
+                        set_label: "save as table",
+                        #[track(model.changed(Analysis::analysis()))]
+                        set_sensitive: model.analysis.is_some(),
+                        connect_clicked[sender, table_name] => move |_| {
+                            sender.input(AnalysisMsg::SaveAsTable(table_name.text().trim().to_string()));
+                        },
+                    },
+                },
+                attach[0, 10, 2, 1] = &gtk::Button {
+                    set_label: "import queries",
+                    set_tooltip_text: Some("Adds the queries of another queries file, e.g. one exported by another installation."),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(AnalysisMsg::ImportQueries);
+                    },
+                },
+                attach[0, 8, 2, 1] = &gtk::Expander {
+                    set_label: Some("attached databases"),
+                    #[wrap(Some)]
+                    set_child = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_spacing: 5,
+                            #[track(model.changed(Analysis::attachments()))]
+                            show_attachments: (&model.attachments, &sender),
+                        },
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_spacing: 5,
+                            #[name(attachment_alias)]
+                            gtk::Entry {
+                                set_hexpand: true,
+                                set_placeholder_text: Some("alias, e.g. other"),
+                                set_tooltip_text: Some("Queries use the tables of the attached database as e.g. other.Item."),
+                            },
+                            gtk::Button {
+                                set_label: "attach",
+                                #[watch]
+                                set_sensitive: model.conn.is_some(),
+                                connect_clicked[sender, attachment_alias] => move |_| {
+                                    sender.input(AnalysisMsg::Attach(attachment_alias.text().trim().to_string()));
+                                },
+                            },
+                        },
+                    },
+                },
             },
-            gtk::Box {
-                set_orientation: gtk::Orientation::Vertical,
-                set_vexpand: true,
-                gtk::ScrolledWindow {
+            #[name(input_paned)]
+            gtk::Paned {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_hexpand: true,
+                // the inputs keep their width when the window is resized
+                set_resize_end_child: false,
+                set_shrink_end_child: false,
+                #[wrap(Some)]
+                set_start_child = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
                     set_vexpand: true,
-                    #[name(list)]
-                    gtk::TreeView {
-                        set_hexpand: true,
+                    gtk::Label {
+                        set_wrap: true,
+                        set_xalign: 0.0,
+                        set_margin_start: 5,
+                        #[track(model.changed(Analysis::description()))]
+                        set_text: &model.description,
+                        #[track(model.changed(Analysis::description()))]
+                        set_visible: !model.description.is_empty(),
+                    },
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_margin_start: 5,
+                        add_css_class: "dim-label",
+                        #[track(model.changed(Analysis::result_note()))]
+                        set_text: &model.result_note,
+                        #[track(model.changed(Analysis::result_note()))]
+                        set_visible: !model.result_note.is_empty(),
+                    },
+                    gtk::SearchEntry {
+                        set_placeholder_text: Some("filter results, e.g. milk or 100..500"),
+                        set_tooltip_text: Some("Text and date columns contain the text, number columns equal the number or are in the range."),
+                        // new results are shown unfiltered
+                        #[track(model.changed(Analysis::analysis()))]
+                        set_text: "",
+                        connect_search_changed[sender] => move |entry| {
+                            sender.input(AnalysisMsg::FilterResults(entry.text().to_string()));
+                        },
+                    },
+                    gtk::Overlay {
                         set_vexpand: true,
+                        #[wrap(Some)]
+                        set_child = &gtk::ScrolledWindow {
+                            set_vexpand: true,
+                            #[name(list)]
+                            gtk::TreeView {
+                                set_hexpand: true,
+                                set_vexpand: true,
+                                set_has_tooltip: true,
+                                add_controller = &gtk::GestureClick {
+                                    set_button: 3,
+                                    connect_pressed[sender, list, copy_menu, columns_menu, columns_box] => move |_, _, x, y| {
+                                        let (bx, by) = list.convert_widget_to_bin_window_coords(x as i32, y as i32);
+                                        if by < 0 {
+                                            // the column headers are above the bin window
+                                            fill_columns_menu(&list, &columns_box, &sender);
+                                            columns_menu.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                                            columns_menu.popup();
+                                        } else if let Some((Some(path), column, _, _)) = list.path_at_pos(bx, by) {
+                                            list.selection().select_path(&path);
+                                            let column = column.map(|column| column.sort_column_id());
+                                            sender.input(AnalysisMsg::CopyTarget(path, column));
+                                            copy_menu.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                                            copy_menu.popup();
+                                        }
+                                    },
+                                },
+                                add_controller = &gtk::EventControllerKey {
+                                    connect_key_pressed[sender, list] => move |_, key, _, modifier| {
+                                        if modifier.contains(gtk::gdk::ModifierType::CONTROL_MASK) && key == gtk::gdk::Key::c {
+                                            if let Some((model, iter)) = list.selection().selected() {
+                                                sender.input(AnalysisMsg::CopyTarget(model.path(&iter), None));
+                                                sender.input(AnalysisMsg::CopyRow);
+                                                return gtk::Inhibit(true);
+                                            }
+                                        }
+                                        gtk::Inhibit(false)
+                                    },
+                                },
+                            },
+                        },
+                        add_overlay: no_rows_label = &gtk::Label {
+                            set_halign: gtk::Align::Center,
+                            set_valign: gtk::Align::Center,
+                            set_can_target: false,
+                            set_visible: false,
+                            add_css_class: "dim-label",
+                            set_label: "The query ran, but returned no rows.",
+                        },
                     },
+                    #[name(chart_area)]
+                    gtk::DrawingArea {
+                        set_content_height: 200,
+                        set_hexpand: true,
+                        set_visible: false,
+                    },
+                    gtk::Label {
+                        #[track]
+                        set_text: &model.query_error,
+                        set_vexpand: false,
+                    }
+                },
+                #[wrap(Some)]
+                set_end_child = &gtk::ScrolledWindow {
+                    set_child: Some(model.input_values.widget()),
+                    set_hexpand: false,
+                    set_vexpand: true,
+                    set_policy: (gtk::PolicyType::Never, gtk::PolicyType::Automatic),
+                },
+                connect_position_notify[sender] => move |paned| {
+                    // only positions chosen by dragging the divider are persisted
+                    if paned.is_position_set() {
+                        sender.output(Msg::InputPanelPosition(paned.position()));
+                    }
                 },
-                gtk::Label {
-                    #[track]
-                    set_text: &model.query_error,
-                    set_vexpand: false,
-                }
-            },
-            gtk::ScrolledWindow {
-                set_child: Some(model.input_values.widget()),
-                set_hexpand: false,
-                set_vexpand: true,
-                set_policy: (gtk::PolicyType::Never, gtk::PolicyType::Automatic),
             },
         }
     }
@@ -163,27 +801,79 @@ impl SimpleComponent for Analysis {
             if let Some(data) = &model.analysis {
                 if let Some((_, q)) = model.queries.get(data.query_id) {
                     for (i, row_entry) in q.table_header.0.iter().enumerate() {
+                        let layout = q
+                            .column_layout
+                            .get(&row_entry.name)
+                            .cloned()
+                            .unwrap_or_default();
                         let i: i32 = i.try_into().unwrap();
-                        if let Some(column) = list.column(i) {
-                            column.set_title(&row_entry.name);
+                        let column = if let Some(column) = list.column(i) {
+                            column
                         } else {
                             let cell = gtk::CellRendererText::new();
                             let column = gtk::TreeViewColumn::new();
-                            column.set_title(&row_entry.name);
                             column.pack_start(&cell, true);
-                            column.set_attributes(&cell, &[("text", i)]);
                             column.set_sort_column_id(i);
                             column.set_resizable(true);
+                            let cell_sender = sender.clone();
+                            cell.connect_edited(move |_, path, text| {
+                                cell_sender.input(AnalysisMsg::CellEdited(
+                                    path,
+                                    i as usize,
+                                    text.to_string(),
+                                ));
+                            });
+                            // resizing a column by dragging its header sets the fixed width
+                            let sender = sender.clone();
+                            column.connect_fixed_width_notify(move |column| {
+                                sender.input(AnalysisMsg::ColumnResized(
+                                    i as usize,
+                                    column.fixed_width(),
+                                ));
+                            });
 
                             list.append_column(&column);
+                            column
+                        };
+                        column.set_title(&row_entry.name);
+                        column.set_fixed_width(layout.width.unwrap_or(-1));
+                        column.set_visible(!layout.hidden);
+                        let editable = data.editable.get(i as usize).copied().unwrap_or(false);
+                        let text_column = data.text_column(i as usize, &q.table_header) as i32;
+                        for cell in column.cells() {
+                            // a reused column may show a money column of the previous result
+                            column.set_attributes(&cell, &[("text", text_column)]);
+                            if let Some(cell) = cell.downcast_ref::<gtk::CellRendererText>() {
+                                cell.set_editable(editable);
+                            }
                         }
                     }
                     let i: i32 = q.table_header.0.len().try_into().unwrap();
                     while let Some(column) = list.column(i) {
                         list.remove_column(&column);
                     }
-                    list.set_model(Some(&data.store));
+                    list.set_model(Some(&data.sorted));
+                    // the headers alone look like a query that was not run yet
+                    no_rows_label.set_visible(data.store.iter_first().is_none());
+                    *model.chart.borrow_mut() = Chart::new(&data.store, &q.table_header);
+                    *model.cell_details.borrow_mut() =
+                        CellDetails::new(&q.table_header, &q.cell_details);
                 }
+            } else {
+                list.set_model(None::<&gtk::ListStore>);
+                no_rows_label.set_visible(false);
+                *model.chart.borrow_mut() = None;
+                *model.cell_details.borrow_mut() = CellDetails::default();
+            }
+            chart_area.queue_draw();
+        }
+        if model.changed(Analysis::analysis()) || model.changed(Analysis::show_chart()) {
+            // results that do not fit a chart are only shown as a table
+            chart_area.set_visible(model.show_chart && model.chart.borrow().is_some());
+        }
+        if model.changed(Analysis::input_panel_position()) {
+            if let Some(position) = model.input_panel_position {
+                input_paned.set_position(position);
             }
         }
     }
@@ -198,78 +888,215 @@ impl SimpleComponent for Analysis {
             .forward(sender.input_sender(), identity);
 
         let input_values = InputValue::builder().launch(()).detach();
+        let queries_file = PathBuf::from(SHARED_QUERIES_FILE);
+        let (queries, queries_status) = load_queries(&queries_file);
 
         let model = Analysis {
             analysis: None,
-            queries: read_queries()
-                .tap_err(|err| println!("[read queries]{err:#?}"))
-                .ok()
-                .unwrap_or_default(),
+            queries,
+            queries_file,
             conn: None,
             new_button_valid: false,
             selected_query: None,
             query_selected: false,
+            query_filter: String::new(),
             query_dialog,
             input_values,
-            query_error: String::new(),
+            query_error: queries_status,
+            copy_target: None,
+            csv_delimiter: csv::Delimiter::default(),
+            storage_date_format: StorageFormat::default(),
+            major_units: false,
+            exclude_refunds: false,
+            save_pending: false,
+            save_generation: 0,
+            chart: Rc::new(RefCell::new(None)),
+            cell_details: Rc::new(RefCell::new(CellDetails::default())),
+            show_chart: false,
+            input_panel_position: None,
+            description: String::new(),
+            result_note: String::new(),
+            exporting: false,
+            export_status: String::new(),
+            export_cancel: Arc::new(AtomicBool::new(false)),
+            attachments: Vec::new(),
+            pending_import: None,
             tracker: 0,
         };
 
         let widgets = view_output!();
+        let chart = Rc::clone(&model.chart);
+        widgets
+            .chart_area
+            .set_draw_func(move |_, cr, width, height| {
+                if let Some(chart) = &*chart.borrow() {
+                    chart.draw(cr, width, height);
+                }
+            });
+        let cell_details = Rc::clone(&model.cell_details);
+        widgets
+            .list
+            .connect_query_tooltip(move |list, x, y, _, tooltip| {
+                let (bx, by) = list.convert_widget_to_bin_window_coords(x, y);
+                let text = list.path_at_pos(bx, by).and_then(|(path, column, _, _)| {
+                    let model = list.model()?;
+                    let iter = model.iter(&path?)?;
+                    let column = column?.sort_column_id();
+                    cell_details
+                        .borrow()
+                        .tooltip(&model, &iter, column as usize)
+                });
+                tooltip.set_text(text.as_deref());
+                text.is_some()
+            });
+        widgets.copy_menu.set_parent(&widgets.list);
+        widgets.columns_menu.set_parent(&widgets.list);
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         self.update(message, sender);
     }
+
+    fn update_cmd(&mut self, message: Self::CommandOutput, _sender: ComponentSender<Self>) {
+        self.reset();
+        match message {
+            ExportProgress::Rows(rows) => self.set_export_status(format!("exported {rows} rows")),
+            ExportProgress::Done(result) => {
+                self.set_exporting(false);
+                match result {
+                    Ok(Some(rows)) => self.set_export_status(format!("Exported {rows} rows.")),
+                    Ok(None) => self.set_export_status(
+                        "Export cancelled, the partial file was removed.".to_string(),
+                    ),
+                    Err(err) => {
+                        self.set_export_status(String::new());
+                        self.report("export the result", err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Report for Analysis {
+    fn show_error(&mut self, message: String) {
+        self.set_query_error(message);
+    }
 }
 
 impl Analysis {
-    fn update(&mut self, message: AnalysisMsg, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: AnalysisMsg, sender: ComponentSender<Self>) {
         self.reset();
         match message {
             AnalysisMsg::PopulateModel(id) => {
                 if let (Some(conn), Some((_, query))) = (&self.conn, self.queries.get(id)) {
                     let values = self.input_values.state().get().model.get_input_values();
+                    self.input_values.emit(InputValueMsg::Persist);
 
-                    match Analysis::exec_query(conn, id, query, values) {
+                    match Analysis::exec_query(
+                        conn,
+                        id,
+                        query,
+                        values,
+                        self.storage_date_format,
+                        self.major_units,
+                        self.exclude_refunds,
+                    ) {
                         Ok(data) => {
+                            let cache = (query.cache_result && query.query_input.0.is_empty())
+                                .then(|| {
+                                    CachedResult::new(
+                                        query,
+                                        conn,
+                                        data.all_rows(&query.table_header),
+                                    )
+                                })
+                                .flatten();
+                            // no track update, the last run is shown once the query is selected
+                            if let Some((_, query)) = self.queries.get_mut(id) {
+                                query.last_run = Some(LastRun {
+                                    at: DateTime::now_local()
+                                        .unwrap()
+                                        .format("%F %T")
+                                        .unwrap()
+                                        .to_string(),
+                                    cache,
+                                });
+                            }
+                            self.schedule_save(&sender);
                             self.set_analysis(Some(data));
                             self.set_query_error(String::new());
+                            self.set_result_note(String::new());
                         }
-                        Err(err_str) => {
-                            self.set_query_error(err_str);
-                        }
+                        Err(err) => self.report("run the query", err),
                     }
                 }
             }
-            AnalysisMsg::ConnectDb(db) => self.conn = Some(db),
-            AnalysisMsg::EditQueryResult(query, name, id) => {
+            AnalysisMsg::ConnectDb(db, db_path) => {
+                self.query_dialog
+                    .emit(edit_query_dialog::QueryDialogMsg::ConnectDb(Rc::clone(&db)));
+                if let Some(previous) = &self.conn {
+                    attachments::detach_all(previous, &self.attachments);
+                }
+                let (attachments, mut problems) = attachments::attach_stored(&db);
+                self.set_attachments(attachments);
+                // results of the previous database must not be shown for the new one
+                self.conn = Some(db);
+                self.set_analysis(None);
+                // the changes to the queries of the previous database go to its file
+                self.save_now();
+                self.save_pending = false;
+                self.queries_file = queries_file(&db_path);
+                self.input_values
+                    .emit(InputValueMsg::Load(input_values_file(&db_path)));
+                let (queries, queries_status) = load_queries(&self.queries_file);
+                problems.extend((!queries_status.is_empty()).then_some(queries_status));
+                self.set_queries(queries);
+                self.set_selected_query(None);
+                sender.input(AnalysisMsg::QuerySelected(None));
+                self.set_query_error(problems.join("\n"));
+            }
+            AnalysisMsg::EditQueryResult(mut query, name, id) => {
                 // no track update, because name should already be in the map
                 self.update_queries(|q| {
                     if let Some((n, q)) = q.get_mut(id) {
+                        query.column_layout = std::mem::take(&mut q.column_layout);
+                        query.last_run = q.last_run.take().map(|mut last_run| {
+                            if !query.cache_result || query.sql != q.sql {
+                                last_run.cache = None;
+                            }
+                            last_run
+                        });
+                        query.cell_details = std::mem::take(&mut q.cell_details);
                         *q = query;
                         *n = name;
                     }
                 });
                 // force change
                 self.update_selected_query(|sq| *sq = Some(id));
-                save_queries(&self.queries).unwrap();
+                self.schedule_save(&sender);
             }
-            AnalysisMsg::NewQuery(name) => {
-                if !self.queries.iter().map(|(n, _)| n).any(|n| n == &name) {
-                    self.update_queries(move |q| {
-                        q.push((name, Query::new()));
-                    });
-                    let id = self.queries.len() - 1;
-                    self.set_selected_query(Some(id));
-                    self.query_dialog
-                        .emit(edit_query_dialog::QueryDialogMsg::Open {
-                            query: Query::new(),
-                            id,
-                            names: self.queries.iter().map(|(n, _)| n).cloned().collect(),
-                            ok_button_name: "add".to_string(),
-                        });
+            AnalysisMsg::NewQuery(name) => self.add_query(name, Query::new()),
+            AnalysisMsg::NewQueryFrom {
+                name,
+                source,
+                keep_sql,
+            } => {
+                if let Some((_, source)) = self.queries.get(source) {
+                    let mut query = source.clone();
+                    if !keep_sql {
+                        query.sql.clear();
+                        query.auto_run = false;
+                        query.column_layout.clear();
+                        query.write_back = None;
+                        query.description.clear();
+                    }
+                    // the new query was never run
+                    query.last_run = None;
+                    query.table_header.reindex();
+                    query.query_input.reindex();
+                    self.add_query(name, query);
                 }
             }
             AnalysisMsg::EditQuery(id) => {
@@ -282,15 +1109,37 @@ impl Analysis {
                         ok_button_name: "edit".to_string(),
                     });
             }
-            AnalysisMsg::DeleteQuery(name) => {
+            AnalysisMsg::DeleteQuery(id) => {
                 self.update_queries(|q| {
-                    q.remove(name);
+                    q.remove(id);
                 });
-                save_queries(&self.queries).unwrap();
+                // the result and the selection refer to the queries by index
+                let query_id = self.analysis.as_ref().map(|data| data.query_id);
+                match query_id.map(|query_id| index_after_removal(query_id, id)) {
+                    Some(Some(query_id)) => {
+                        if let Some(data) = &mut self.analysis {
+                            data.query_id = query_id;
+                        }
+                    }
+                    Some(None) => self.set_analysis(None),
+                    None => {}
+                }
+                let selected = self
+                    .selected_query
+                    .and_then(|selected| index_after_removal(selected, id));
+                // force change, the query list was filled again
+                self.update_selected_query(|sq| *sq = selected);
+                self.schedule_save(&sender);
             }
             AnalysisMsg::QuerySelected(active) => {
                 self.selected_query = active;
                 self.set_query_selected(active.is_some());
+                self.set_description(
+                    active
+                        .and_then(|active| self.queries.get(active))
+                        .map(|(_, q)| q.description.clone())
+                        .unwrap_or_default(),
+                );
 
                 if let Some(active) = active {
                     if let Some((name, q)) = self.queries.get(active) {
@@ -298,23 +1147,851 @@ impl Analysis {
                             name.clone(),
                             q.query_input.clone(),
                         ));
+                        if q.auto_run && q.query_input.0.is_empty() {
+                            sender.input(AnalysisMsg::PopulateModel(active));
+                        } else {
+                            self.show_last_run(active, &sender);
+                        }
                     }
                 }
             }
+            AnalysisMsg::FilterQueries(filter) => self.set_query_filter(filter),
+            AnalysisMsg::PickQuery(position) => {
+                if let Some(id) = matching_queries(&self.queries, &self.query_filter).get(position)
+                {
+                    // the combobox shows the query and reports it as selected
+                    self.set_selected_query(Some(*id));
+                }
+            }
             AnalysisMsg::NewQueryNameChanged(name) => {
                 let name = name.trim();
                 self.new_button_valid =
                     !name.is_empty() && !self.queries.iter().map(|(n, _)| n).any(|n| n == name);
             }
+            AnalysisMsg::CopyTarget(path, column) => self.copy_target = Some((path, column)),
+            AnalysisMsg::CopyCell => self.copy_to_clipboard(false),
+            AnalysisMsg::CopyRow => self.copy_to_clipboard(true),
+            AnalysisMsg::CsvDelimiter(delimiter) => self.csv_delimiter = delimiter,
+            AnalysisMsg::RequiredRows(inputs, outputs) => {
+                self.query_dialog
+                    .emit(edit_query_dialog::QueryDialogMsg::RequiredRows(
+                        inputs, outputs,
+                    ))
+            }
+            AnalysisMsg::StorageDateFormat(format) => self.storage_date_format = format,
+            AnalysisMsg::ExcludeRefunds(exclude) => self.exclude_refunds = exclude,
+            AnalysisMsg::MajorUnits(major_units) => {
+                self.major_units = major_units;
+                if let Some((data, (_, q))) = self
+                    .analysis
+                    .as_ref()
+                    .and_then(|data| Some((data, self.queries.get(data.query_id)?)))
+                {
+                    data.show_amounts(&q.table_header, major_units);
+                }
+            }
+            AnalysisMsg::ColumnResized(column, width) => {
+                let width = Some(width).filter(|width| *width > 0);
+                if self.update_column_layout(column, |layout| layout.width = width) {
+                    self.schedule_save(&sender);
+                }
+            }
+            AnalysisMsg::ColumnVisibility(column, visible) => {
+                if self.update_column_layout(column, |layout| layout.hidden = !visible) {
+                    self.schedule_save(&sender);
+                }
+            }
+            AnalysisMsg::CellDetails(ty, show) => {
+                if let Some(data) = &self.analysis {
+                    // no track update, the check button already shows the change
+                    if let Some((_, q)) = self.queries.get_mut(data.query_id) {
+                        if q.cell_details.contains(&ty) != show {
+                            q.cell_details.retain(|other| *other != ty);
+                            if show {
+                                q.cell_details.push(ty);
+                            }
+                            *self.cell_details.borrow_mut() =
+                                CellDetails::new(&q.table_header, &q.cell_details);
+                            self.schedule_save(&sender);
+                        }
+                    }
+                }
+            }
+            AnalysisMsg::ShowChart(show_chart) => self.set_show_chart(show_chart),
+            AnalysisMsg::InputPanelPosition(position) => {
+                self.set_input_panel_position(Some(position))
+            }
+            AnalysisMsg::FilterResults(text) => {
+                // the rows are already fetched, so no tracked change is needed
+                if let Some(data) = &self.analysis {
+                    *data.filter_text.borrow_mut() = text;
+                    data.filter.refilter();
+                }
+            }
+            AnalysisMsg::CellEdited(path, column, text) => {
+                if let Err(err) = self.write_back(&path, column, &text) {
+                    self.report("change the cell", err);
+                }
+            }
+            AnalysisMsg::SaveQueries(generation) => {
+                if self.save_pending && generation == self.save_generation {
+                    self.save_pending = false;
+                    self.save_queries();
+                }
+            }
+            AnalysisMsg::ExportCsv => {
+                let path = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .show_save_single_file()
+                    .unwrap_or_else(|err| self.dialog_failed(err));
+                if let Some(path) = path {
+                    self.export_csv(path, &sender);
+                }
+            }
+            AnalysisMsg::CopyMarkdown => self.copy_markdown(),
+            AnalysisMsg::CancelExport => self.export_cancel.store(true, Ordering::Relaxed),
+            AnalysisMsg::SaveAsTable(table) => {
+                if let (Some(conn), Some(data)) = (&self.conn, &self.analysis) {
+                    if let Some((_, q)) = self.queries.get(data.query_id) {
+                        let rows = data.all_rows(&q.table_header);
+                        match save_as_table(conn, &table, &q.table_header, &rows) {
+                            Ok(rows) => {
+                                self.set_export_status(format!(
+                                    "Saved {rows} rows as the table '{table}'."
+                                ));
+                                self.set_query_error(String::new());
+                            }
+                            Err(err) => self.report("save the result as a table", err),
+                        }
+                    }
+                }
+            }
+            AnalysisMsg::ImportQueries => {
+                let path = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .show_open_single_file()
+                    .unwrap_or_else(|err| self.dialog_failed(err));
+                if let Some(path) = path {
+                    let parsed = std::fs::read_to_string(path)
+                        .map_err(AppError::from)
+                        .and_then(|text| parse_queries(&text).map_err(AppError::from));
+                    match parsed {
+                        Ok((imported, problems)) => {
+                            self.set_query_error(problems.join("\n"));
+                            let import = Import::new(imported, &self.queries, self.conn.as_deref());
+                            if import.has_conflicts() {
+                                self.set_pending_import(Some(import));
+                            } else {
+                                self.apply_import(import, &sender);
+                            }
+                        }
+                        Err(err) => self.report("import the queries", err),
+                    }
+                }
+            }
+            AnalysisMsg::ImportResolution(idx, resolution) => {
+                // no track update, the dialog already shows the choice
+                if let Some(import) = &mut self.pending_import {
+                    import.resolve(idx, resolution);
+                }
+            }
+            AnalysisMsg::ApplyImport => {
+                if let Some(import) = self.pending_import.take() {
+                    self.set_pending_import(None);
+                    self.apply_import(import, &sender);
+                }
+            }
+            AnalysisMsg::CancelImport => self.set_pending_import(None),
+            AnalysisMsg::Attach(alias) => {
+                if self.conn.is_some() {
+                    let path = FileDialog::new()
+                        .add_filter("SQLite", &["db", "sqlite", "sqlite3"])
+                        .show_open_single_file()
+                        .unwrap_or_else(|err| self.dialog_failed(err));
+                    if let (Some(conn), Some(path)) = (&self.conn, path) {
+                        let path = path.to_string_lossy();
+                        match attachments::add(conn, &alias, &path, &self.attachments) {
+                            Ok(attachment) => {
+                                self.update_attachments(|attachments| attachments.push(attachment));
+                                self.set_query_error(String::new());
+                            }
+                            Err(err) => self.report("attach the database", err),
+                        }
+                    }
+                }
+            }
+            AnalysisMsg::Detach(idx) => {
+                if let (Some(conn), Some(attachment)) = (&self.conn, self.attachments.get(idx)) {
+                    match attachments::remove(conn, attachment) {
+                        Ok(()) => self.update_attachments(|attachments| {
+                            attachments.remove(idx);
+                        }),
+                        Err(err) => {
+                            let action = format!("detach '{}'", attachment.alias);
+                            self.report(&action, err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates the table row of the edited result cell and, if that succeeded, the cell itself.
+    fn write_back(&self, path: &gtk::TreePath, column: usize, text: &str) -> Result<(), String> {
+        if let (Some(conn), Some(data)) = (&self.conn, &self.analysis) {
+            if let Some((_, q)) = self.queries.get(data.query_id) {
+                let iter = data
+                    .store_path(path)
+                    .and_then(|path| data.store.iter(&path));
+                let (write_back, iter) = match (&q.write_back, iter) {
+                    (Some(write_back), Some(iter)) => (write_back, iter),
+                    _ => return Ok(()),
+                };
+                if !data.editable.get(column).copied().unwrap_or(false) {
+                    return Err("This column is not editable.".to_string());
+                }
+                let id_idx = q
+                    .table_header
+                    .0
+                    .iter()
+                    .position(|row_entry| row_entry.name == write_back.id_column)
+                    .ok_or_else(|| format!("There is no id column '{}'.", write_back.id_column))?;
+                let values = row_values(&data.store, &iter, &q.table_header);
+                let row_entry = &q.table_header.0[column];
+                let unit = data.unit_column.and_then(|unit| {
+                    Unit::from_str(&data.store.get::<String>(&iter, unit as i32)).ok()
+                });
+                let value = match (row_entry.ty, unit) {
+                    (ColumnType::Number, Some(unit)) if self.major_units => {
+                        ColumnTypeValue::Number(Money::parse(text, unit)?.minor)
+                    }
+                    (ColumnType::Number, _) => ColumnTypeValue::Number(
+                        text.trim()
+                            .parse()
+                            .map_err(|_| format!("'{text}' is not a number."))?,
+                    ),
+                    (ColumnType::Date, _) => ColumnTypeValue::Date(
+                        convert_date(text, self.storage_date_format).ok_or_else(|| {
+                            format!("'{text}' is not a YYYY-MM-DD or DD.MM.YYYY date.")
+                        })?,
+                    ),
+                    (ColumnType::String | ColumnType::Period, _) => {
+                        ColumnTypeValue::String(text.to_string())
+                    }
+                };
+                // the tables of the app note when a row was changed, other tables may not
+                let touch = row_entry.name != "updated_at"
+                    && has_column(conn, &write_back.table, "updated_at")
+                        .map_err(|err| err.to_string())?;
+                let sql = format!(
+                    "UPDATE {} SET {} = ?1{} WHERE {} == ?2;",
+                    quote_identifier(&write_back.table),
+                    quote_identifier(&row_entry.name),
+                    if touch {
+                        ", updated_at = CURRENT_TIMESTAMP"
+                    } else {
+                        ""
+                    },
+                    quote_identifier(&write_back.id_column),
+                );
+                let tx = conn
+                    .unchecked_transaction()
+                    .map_err(|err| err.to_string())?;
+                let updated = tx
+                    .execute(&sql, [&value as &dyn ToSql, &values[id_idx]])
+                    .map_err(|err| err.to_string())?;
+                // an id that is not unique would change more than the edited row
+                if updated != 1 {
+                    return Err(format!(
+                        "The edit would change {updated} rows instead of one, so it was discarded."
+                    ));
+                }
+                tx.commit().map_err(|err| err.to_string())?;
+                data.store
+                    .set_value(&iter, column as u32, &value.to_value());
+                data.show_row_amounts(&iter, &q.table_header, self.major_units);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the query and opens it in the edit dialog.
+    fn add_query(&mut self, name: String, query: Query) {
+        if !self.queries.iter().map(|(n, _)| n).any(|n| n == &name) {
+            let dialog_query = query.clone();
+            self.update_queries(move |q| {
+                q.push((name, query));
+            });
+            let id = self.queries.len() - 1;
+            self.set_selected_query(Some(id));
+            self.query_dialog
+                .emit(edit_query_dialog::QueryDialogMsg::Open {
+                    query: dialog_query,
+                    id,
+                    names: self.queries.iter().map(|(n, _)| n).cloned().collect(),
+                    ok_button_name: "add".to_string(),
+                });
+        }
+    }
+
+    /// Adds the imported queries all at once and saves them right away.
+    fn apply_import(&mut self, import: Import, sender: &ComponentSender<Self>) {
+        let (queries, summary) = import.apply(&self.queries);
+        // the result of an overwritten query no longer matches its header
+        if let Some(data) = &self.analysis {
+            if summary.overwritten.contains(&data.query_id) {
+                self.set_analysis(None);
+            }
+        }
+        let selected = self.selected_query;
+        self.set_queries(queries);
+        // force change, the query list was filled again
+        self.update_selected_query(|sq| *sq = selected);
+        self.save_queries();
+        self.set_export_status(format!("Imported queries: {summary}."));
+        // shows the inputs and description of an overwritten selected query
+        if let Some(selected) = selected.filter(|selected| summary.overwritten.contains(selected)) {
+            sender.input(AnalysisMsg::QuerySelected(Some(selected)));
+        }
+    }
+
+    /// Shows the cached result of the query, if it is still valid, and when the query was run
+    /// the last time. Invalid results are dropped.
+    fn show_last_run(&mut self, id: usize, sender: &ComponentSender<Self>) {
+        let (note, data, invalid_cache) = match self.queries.get(id) {
+            Some((_, query)) => match &query.last_run {
+                Some(last_run) => {
+                    let cache = last_run.cache.as_ref().filter(|cache| {
+                        self.conn
+                            .as_ref()
+                            .map_or(false, |conn| cache.is_valid(query, conn))
+                    });
+                    match cache {
+                        Some(cache) => (
+                            format!(
+                                "Cached result of {}, it may be stale. Run the query to refresh it.",
+                                last_run.at
+                            ),
+                            Some(Data::new(
+                                id,
+                                query,
+                                &cache.rows,
+                                vec![false; query.table_header.0.len()],
+                                self.major_units,
+                            )),
+                            false,
+                        ),
+                        None => (
+                            format!("Last run at {}.", last_run.at),
+                            None,
+                            last_run.cache.is_some(),
+                        ),
+                    }
+                }
+                None => (String::new(), None, false),
+            },
+            None => return,
+        };
+        self.set_result_note(note);
+        if let Some(data) = data {
+            self.set_analysis(Some(data));
+            self.set_query_error(String::new());
+        }
+        if invalid_cache {
+            // no track update, nothing shows the cache
+            if let Some((_, query)) = self.queries.get_mut(id) {
+                if let Some(last_run) = &mut query.last_run {
+                    last_run.cache = None;
+                }
+            }
+            self.schedule_save(sender);
+        }
+    }
+
+    /// Whether the result cells of the type show their stored value when hovered.
+    fn shows_cell_details(&self, ty: ColumnType) -> bool {
+        self.analysis
+            .as_ref()
+            .and_then(|data| self.queries.get(data.query_id))
+            .map_or(false, |(_, q)| q.cell_details.contains(&ty))
+    }
+
+    /// Changes the layout of a column of the current result. Returns whether it changed.
+    fn update_column_layout(&mut self, column: usize, f: impl FnOnce(&mut ColumnLayout)) -> bool {
+        let mut changed = false;
+        if let Some(data) = &self.analysis {
+            // no track update, the view already shows the new layout
+            if let Some((_, q)) = self.queries.get_mut(data.query_id) {
+                if let Some(row_entry) = q.table_header.0.get(column) {
+                    let layout = q.column_layout.entry(row_entry.name.clone()).or_default();
+                    let old_layout = layout.clone();
+                    f(layout);
+                    changed = *layout != old_layout;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Saves the queries after [`SAVE_DELAY`], unless another change restarts the delay.
+    fn schedule_save(&mut self, sender: &ComponentSender<Self>) {
+        self.save_pending = true;
+        self.save_generation += 1;
+        let generation = self.save_generation;
+        let sender = sender.clone();
+        gtk::glib::timeout_add_local_once(SAVE_DELAY, move || {
+            sender.input(AnalysisMsg::SaveQueries(generation));
+        });
+    }
+
+    /// Writes pending changes and releases the connection when the app is closed.
+    pub(crate) fn disconnect(&mut self) {
+        self.save_now();
+        self.save_pending = false;
+        if let Some(conn) = &self.conn {
+            attachments::detach_all(conn, &self.attachments);
+        }
+        self.set_attachments(Vec::new());
+        self.conn = None;
+        self.set_analysis(None);
+        self.query_dialog
+            .emit(edit_query_dialog::QueryDialogMsg::DisconnectDb);
+    }
+
+    /// Writes a pending save immediately, e.g. when the app is closed before the save timer fired.
+    pub(crate) fn save_now(&self) {
+        if self.save_pending {
+            if let Err(err) = save_queries(&self.queries_file, &self.queries) {
+                eprintln!("[save queries]{err:#?}");
+            }
+        }
+    }
+
+    /// The queries stay usable in memory if they can't be written, e.g. in a read-only directory.
+    fn save_queries(&mut self) {
+        if let Err(err) = save_queries(&self.queries_file, &self.queries) {
+            let action = format!("write {}", self.queries_file.display());
+            self.report(&action, err);
+        }
+    }
+
+    /// Writes the header and all rows of the current result in a background thread, which
+    /// reports its progress as [`ExportProgress`]. The rows are formatted beforehand, as the
+    /// result may change while the file is written.
+    fn export_csv(&mut self, path: PathBuf, sender: &ComponentSender<Self>) {
+        if let Some(data) = &self.analysis {
+            if let Some((_, q)) = self.queries.get(data.query_id) {
+                let header = q.table_header.0.iter().map(|row_entry| &row_entry.name);
+                let header = csv::write_record(header, self.csv_delimiter);
+                let rows: Vec<_> = data
+                    .all_rows(&q.table_header)
+                    .iter()
+                    .map(|values| {
+                        let values = values.iter().map(ToString::to_string);
+                        csv::write_record(values, self.csv_delimiter)
+                    })
+                    .collect();
+
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.export_cancel = Arc::clone(&cancel);
+                self.set_exporting(true);
+                self.set_export_status("exported 0 rows".to_string());
+                sender.spawn_command(move |out| {
+                    let result = write_export(&path, &header, &rows, &cancel, |rows| {
+                        out.send(ExportProgress::Rows(rows));
+                    });
+                    out.send(ExportProgress::Done(result.map_err(|err| err.to_string())));
+                });
+            }
+        }
+    }
+
+    /// Copies the rows of the result table as a Markdown table, without the hidden columns.
+    fn copy_markdown(&mut self) {
+        let mut copied = None;
+        if let Some(data) = &self.analysis {
+            if let Some((_, q)) = self.queries.get(data.query_id) {
+                let shown: Vec<bool> = q
+                    .table_header
+                    .0
+                    .iter()
+                    .map(|row_entry| {
+                        !q.column_layout
+                            .get(&row_entry.name)
+                            .map_or(false, |layout| layout.hidden)
+                    })
+                    .collect();
+                let header = q
+                    .table_header
+                    .0
+                    .iter()
+                    .zip(&shown)
+                    .filter(|(_, shown)| **shown)
+                    .map(|(row_entry, _)| row_entry.name.as_str());
+                let rows: Vec<Vec<String>> = data
+                    .shown_rows(&q.table_header)
+                    .iter()
+                    .map(|values| {
+                        values
+                            .iter()
+                            .zip(&shown)
+                            .filter(|(_, shown)| **shown)
+                            .map(|(value, _)| value.to_string())
+                            .collect()
+                    })
+                    .collect();
+                if let Some(display) = gtk::gdk::Display::default() {
+                    display.clipboard().set_text(&markdown_table(header, &rows));
+                    copied = Some(rows.len());
+                }
+            }
+        }
+        if let Some(rows) = copied {
+            self.set_export_status(format!("Copied {rows} rows as a Markdown table."));
+        }
+    }
+
+    /// Copies the cell or the tab separated row at [`Analysis::copy_target`].
+    fn copy_to_clipboard(&self, whole_row: bool) {
+        if let (Some(data), Some((path, column))) = (&self.analysis, &self.copy_target) {
+            let iter = data
+                .store_path(path)
+                .and_then(|path| data.store.iter(&path));
+            if let (Some((_, q)), Some(iter)) = (self.queries.get(data.query_id), iter) {
+                let values = row_values(&data.store, &iter, &q.table_header);
+                let text = match column {
+                    Some(column) if !whole_row => values
+                        .get(*column as usize)
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                    _ => values
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\t"),
+                };
+                if let Some(display) = gtk::gdk::Display::default() {
+                    display.clipboard().set_text(&text);
+                }
+            }
         }
     }
 }
 
+/// Lists a check button for every result column that toggles its visibility.
+fn fill_columns_menu(
+    list: &gtk::TreeView,
+    columns_box: &gtk::Box,
+    sender: &ComponentSender<Analysis>,
+) {
+    while let Some(child) = columns_box.first_child() {
+        columns_box.remove(&child);
+    }
+    for (i, column) in list.columns().into_iter().enumerate() {
+        let check = gtk::CheckButton::with_label(&column.title());
+        check.set_active(column.is_visible());
+        let sender = sender.clone();
+        check.connect_toggled(move |check| {
+            column.set_visible(check.is_active());
+            sender.input(AnalysisMsg::ColumnVisibility(i, check.is_active()));
+        });
+        columns_box.append(&check);
+    }
+}
+
+/// The index of a query after the query at `removed` was deleted, `None` for the deleted query.
+fn index_after_removal(index: usize, removed: usize) -> Option<usize> {
+    if index < removed {
+        Some(index)
+    } else if index == removed {
+        None
+    } else {
+        Some(index - 1)
+    }
+}
+
+/// Marks the result columns that can be written back. The id column and columns that do not
+/// come from the column of the table with their header name, e.g. computed, renamed or joined
+/// ones, are never editable. Neither is any column, if the id does not come from the table.
+fn editable_columns(conn: &Connection, query: &Query) -> rusqlite::Result<Vec<bool>> {
+    let header = &query.table_header.0;
+    let mut editable = vec![false; header.len()];
+    if let Some(write_back) = &query.write_back {
+        let origins = column_origins(conn, &query.sql)?;
+        // the result columns are read by position, like the header
+        let from_table = |idx: usize, name: &str| {
+            origins.get(idx).map_or(false, |origin| match origin {
+                Some((table, column)) => {
+                    table.eq_ignore_ascii_case(&write_back.table)
+                        && column.eq_ignore_ascii_case(name)
+                }
+                None => false,
+            })
+        };
+        let id_from_table = header.iter().enumerate().any(|(idx, row_entry)| {
+            row_entry.name == write_back.id_column && from_table(idx, &row_entry.name)
+        });
+        if id_from_table {
+            for (idx, (editable, row_entry)) in editable.iter_mut().zip(header).enumerate() {
+                *editable = row_entry.name != write_back.id_column
+                    && write_back.columns.contains(&row_entry.name)
+                    && from_table(idx, &row_entry.name);
+            }
+        }
+    }
+    Ok(editable)
+}
+
+/// The table and the column of the table each result column of the sql is read from, `None` for
+/// computed columns. rusqlite has no access to this column metadata of SQLite, which the bundled
+/// SQLite is built with.
+fn column_origins(conn: &Connection, sql: &str) -> rusqlite::Result<Vec<Option<(String, String)>>> {
+    // reports an invalid sql like every other statement does
+    let column_count = conn.prepare(sql)?.column_count();
+    let sql = CString::new(sql)?;
+    let text = |ptr: *const std::os::raw::c_char| {
+        // SAFETY: a non-null pointer of SQLite is a valid string until the statement is finalized
+        (!ptr.is_null()).then(|| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    };
+    let mut stmt = std::ptr::null_mut();
+    // SAFETY: the statement is prepared on the open connection and finalized before it is dropped
+    unsafe {
+        let rc = ffi::sqlite3_prepare_v2(
+            conn.handle(),
+            sql.as_ptr(),
+            -1,
+            &mut stmt,
+            std::ptr::null_mut(),
+        );
+        if rc == ffi::SQLITE_OK {
+            let origins = (0..column_count as i32)
+                .map(|idx| {
+                    text(ffi::sqlite3_column_table_name(stmt, idx))
+                        .zip(text(ffi::sqlite3_column_origin_name(stmt, idx)))
+                })
+                .collect();
+            ffi::sqlite3_finalize(stmt);
+            Ok(origins)
+        } else {
+            Err(rusqlite::Error::SqliteFailure(ffi::Error::new(rc), None))
+        }
+    }
+}
+
+/// Whether the table has a column of the name.
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name == ?2 COLLATE NOCASE;",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Whether a result row is shown for the filter. Text and date columns have to contain the filter,
+/// ignoring case. Number columns have to equal a number (`5`) or be in an inclusive range
+/// (`5..10`, `..10`, `5..`).
+fn row_matches(values: &[ColumnTypeValue], filter: &str) -> bool {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return true;
+    }
+    let range = number_range(filter);
+    let needle = filter.to_lowercase();
+    values.iter().any(|value| match value {
+        ColumnTypeValue::Number(number) => range.map_or(false, |(from, to)| {
+            from.map_or(true, |from| *number >= from) && to.map_or(true, |to| *number <= to)
+        }),
+        value => value.to_string().to_lowercase().contains(&needle),
+    })
+}
+
+/// `None` if the filter is neither a number nor a range of numbers.
+fn number_range(filter: &str) -> Option<(Option<i64>, Option<i64>)> {
+    let bound = |bound: &str| -> Option<Option<i64>> {
+        let bound = bound.trim();
+        if bound.is_empty() {
+            Some(None)
+        } else {
+            bound.parse().ok().map(Some)
+        }
+    };
+    match filter.split_once("..") {
+        Some((from, to)) => match (bound(from)?, bound(to)?) {
+            (None, None) => None,
+            range => Some(range),
+        },
+        None => filter
+            .parse()
+            .ok()
+            .map(|number| (Some(number), Some(number))),
+    }
+}
+
+/// Writes the header and rows to a new file at `path` and calls `progress` with the number of
+/// written rows every [`EXPORT_PROGRESS_ROWS`] rows. If `cancel` is set in between or writing
+/// fails, the partial file is removed. Returns the number of rows, `None` if it was cancelled.
+fn write_export(
+    path: &Path,
+    header: &str,
+    rows: &[String],
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> std::io::Result<Option<usize>> {
+    let file = File::create(path)?;
+    let result = (|| {
+        let mut file = std::io::BufWriter::new(file);
+        file.write_all(header.as_bytes())?;
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 && i % EXPORT_PROGRESS_ROWS == 0 {
+                if cancel.load(Ordering::Relaxed) {
+                    return Ok(None);
+                }
+                progress(i);
+            }
+            file.write_all(row.as_bytes())?;
+        }
+        file.flush()?;
+        Ok(Some(rows.len()))
+    })();
+    if !matches!(result, Ok(Some(_))) {
+        if let Err(err) = std::fs::remove_file(path) {
+            eprintln!("[export csv]{err:#?}");
+        }
+    }
+    result
+}
+
+/// Creates the table with a column per header entry and inserts the rows. Either the whole
+/// result is saved or nothing. An existing table or view with the name is never replaced.
+/// Returns the number of saved rows.
+fn save_as_table(
+    conn: &Connection,
+    table: &str,
+    header: &RowData,
+    rows: &[Vec<ColumnTypeValue>],
+) -> Result<usize, String> {
+    if table.is_empty() {
+        return Err("Enter a name for the table.".to_string());
+    }
+    let exists = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name == ?1 COLLATE NOCASE;",
+            [table],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|err| err.to_string())?;
+    if exists > 0 {
+        return Err(format!("The database already has a table '{table}'."));
+    }
+    let columns = header
+        .0
+        .iter()
+        .map(|row_entry| {
+            format!(
+                "{} {}",
+                quote_identifier(&row_entry.name),
+                row_entry.ty.sql_type()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = (1..=header.0.len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let table = quote_identifier(table);
+    crate::with_transaction(conn, |tx| {
+        tx.execute(&format!("CREATE TABLE {table} ({columns});"), [])?;
+        let mut insert = tx.prepare(&format!("INSERT INTO {table} VALUES ({placeholders});"))?;
+        for values in rows {
+            insert.execute(rusqlite::params_from_iter(values))?;
+        }
+        Ok(rows.len())
+    })
+    .map_err(|err: rusqlite::Error| format!("Could not save the table: {err}"))
+}
+
+/// The values of every row of the model, in its order.
+fn model_rows(model: &impl IsA<gtk::TreeModel>, header: &RowData) -> Vec<Vec<ColumnTypeValue>> {
+    let mut rows = Vec::new();
+    if let Some(iter) = model.iter_first() {
+        loop {
+            rows.push(row_values(model, &iter, header));
+            if !model.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+    rows
+}
+
+/// A string column named `unit`, whose value is the currency of the numbers of its row.
+pub(crate) fn unit_column(header: &RowData) -> Option<usize> {
+    header
+        .0
+        .iter()
+        .position(|entry| entry.ty == ColumnType::String && entry.name.eq_ignore_ascii_case("unit"))
+}
+
+/// Reads the values of a result row back from the model.
+fn row_values(
+    model: &impl IsA<gtk::TreeModel>,
+    iter: &gtk::TreeIter,
+    header: &RowData,
+) -> Vec<ColumnTypeValue> {
+    header
+        .0
+        .iter()
+        .enumerate()
+        .map(|(i, row_entry)| {
+            let i = i as i32;
+            match row_entry.ty {
+                ColumnType::String | ColumnType::Period => {
+                    ColumnTypeValue::String(model.get(iter, i))
+                }
+                ColumnType::Number => ColumnTypeValue::Number(model.get(iter, i)),
+                ColumnType::Date => ColumnTypeValue::Date(model.get(iter, i)),
+            }
+        })
+        .collect()
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub(crate) enum ColumnType {
     String,
     Number,
     Date,
+    /// Only available for query inputs. Binds a `strftime` format for the selected granularity.
+    Period,
+}
+
+impl ColumnType {
+    pub(crate) const OUTPUT: [ColumnType; 3] =
+        [ColumnType::String, ColumnType::Number, ColumnType::Date];
+    pub(crate) const INPUT: [ColumnType; 4] = [
+        ColumnType::String,
+        ColumnType::Number,
+        ColumnType::Date,
+        ColumnType::Period,
+    ];
+
+    /// Declared type of a table column with values of this type. Dates are declared like the
+    /// date of a receipt.
+    fn sql_type(&self) -> &'static str {
+        match self {
+            ColumnType::String | ColumnType::Period => "TEXT",
+            ColumnType::Number => "INTEGER",
+            ColumnType::Date => "DATE",
+        }
+    }
 }
 
 impl std::fmt::Display for ColumnType {
@@ -323,15 +2000,64 @@ impl std::fmt::Display for ColumnType {
             ColumnType::String => f.write_str("String"),
             ColumnType::Number => f.write_str("Number"),
             ColumnType::Date => f.write_str("Date"),
+            ColumnType::Period => f.write_str("Period"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Granularity {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Granularity {
+    pub(crate) const ALL: [Granularity; 4] = [
+        Granularity::Day,
+        Granularity::Week,
+        Granularity::Month,
+        Granularity::Year,
+    ];
+
+    /// Weeks are counted like sqlite's `%W`: starting on monday, the first monday of the year starts week 01.
+    pub(crate) fn strftime_format(&self) -> &'static str {
+        match self {
+            Granularity::Day => "%Y-%m-%d",
+            Granularity::Week => "%Y-W%W",
+            Granularity::Month => "%Y-%m",
+            Granularity::Year => "%Y",
+        }
+    }
+}
+
+impl std::fmt::Display for Granularity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Granularity::Day => f.write_str("Day"),
+            Granularity::Week => f.write_str("Week"),
+            Granularity::Month => f.write_str("Month"),
+            Granularity::Year => f.write_str("Year"),
+        }
+    }
+}
+
+/// A period input named `name` binds `:name` to the `strftime` format of the granularity
+/// and, if the query uses them, `:name_from` and `:name_to` to the range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Period {
+    pub(crate) granularity: Granularity,
+    pub(crate) from: String,
+    pub(crate) to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum ColumnTypeValue {
     String(String),
     Number(i64),
     Date(String),
+    Period(Period),
 }
 
 impl ToSql for ColumnTypeValue {
@@ -344,16 +2070,40 @@ impl ToSql for ColumnTypeValue {
             ColumnTypeValue::Date(d) => {
                 ToSqlOutput::Borrowed(rusqlite::types::ValueRef::Text(d.as_bytes()))
             }
+            ColumnTypeValue::Period(p) => ToSqlOutput::Borrowed(rusqlite::types::ValueRef::Text(
+                p.granularity.strftime_format().as_bytes(),
+            )),
         })
     }
 }
 
+impl std::fmt::Display for ColumnTypeValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnTypeValue::String(s) => f.write_str(s),
+            ColumnTypeValue::Number(n) => write!(f, "{n}"),
+            ColumnTypeValue::Date(d) => f.write_str(d),
+            ColumnTypeValue::Period(p) => write!(f, "{} ({} - {})", p.granularity, p.from, p.to),
+        }
+    }
+}
+
 impl ColumnTypeValue {
+    fn column_type(&self) -> ColumnType {
+        match self {
+            ColumnTypeValue::String(_) => ColumnType::String,
+            ColumnTypeValue::Number(_) => ColumnType::Number,
+            ColumnTypeValue::Date(_) => ColumnType::Date,
+            ColumnTypeValue::Period(_) => ColumnType::Period,
+        }
+    }
+
     fn is_column_type(&self, ty: ColumnType) -> bool {
         match self {
             ColumnTypeValue::String(_) => ty == ColumnType::String,
             ColumnTypeValue::Number(_) => ty == ColumnType::Number,
             ColumnTypeValue::Date(_) => ty == ColumnType::Date,
+            ColumnTypeValue::Period(_) => ty == ColumnType::Period,
         }
     }
 }
@@ -364,6 +2114,7 @@ impl ToValue for ColumnTypeValue {
             ColumnTypeValue::String(s) => s.to_value(),
             ColumnTypeValue::Number(n) => n.to_value(),
             ColumnTypeValue::Date(d) => d.to_value(),
+            ColumnTypeValue::Period(p) => p.granularity.strftime_format().to_value(),
         }
     }
 
@@ -372,6 +2123,7 @@ impl ToValue for ColumnTypeValue {
             ColumnTypeValue::String(s) => s.value_type(),
             ColumnTypeValue::Number(n) => n.value_type(),
             ColumnTypeValue::Date(d) => d.value_type(),
+            ColumnTypeValue::Period(_) => Type::STRING,
         }
     }
 }
@@ -382,6 +2134,7 @@ impl From<ColumnType> for u32 {
             ColumnType::String => 0,
             ColumnType::Number => 1,
             ColumnType::Date => 2,
+            ColumnType::Period => 3,
         }
     }
 }
@@ -391,7 +2144,7 @@ impl From<ColumnType> for Type {
         match ct {
             ColumnType::String => Type::STRING,
             ColumnType::Number => Type::I64,
-            ColumnType::Date => Type::STRING,
+            ColumnType::Date | ColumnType::Period => Type::STRING,
         }
     }
 }
@@ -407,6 +2160,7 @@ impl TryFrom<u32> for ColumnType {
             0 => Ok(ColumnType::String),
             1 => Ok(ColumnType::Number),
             2 => Ok(ColumnType::Date),
+            3 => Ok(ColumnType::Period),
             other => Err(NumberOutOfRange(other)),
         }
     }
@@ -417,13 +2171,17 @@ impl From<ColumnType> for ColumnTypeValue {
         match ty {
             ColumnType::String => ColumnTypeValue::String(String::new()),
             ColumnType::Number => ColumnTypeValue::Number(0),
-            ColumnType::Date => ColumnTypeValue::Date(
-                DateTime::now_local()
-                    .unwrap()
-                    .format("%F")
-                    .unwrap()
-                    .to_string(),
-            ),
+            ColumnType::Date => {
+                ColumnTypeValue::Date(to_iso_date(&DateTime::now_local().unwrap()).to_string())
+            }
+            ColumnType::Period => {
+                let today = to_iso_date(&DateTime::now_local().unwrap()).to_string();
+                ColumnTypeValue::Period(Period {
+                    granularity: Granularity::Month,
+                    from: today.clone(),
+                    to: today,
+                })
+            }
         }
     }
 }
@@ -443,6 +2201,13 @@ impl RowData {
     pub(crate) fn new() -> Self {
         RowData(Vec::new())
     }
+
+    /// Numbers the entries consecutively, as the type components expect.
+    fn reindex(&mut self) {
+        for (id, row) in self.0.iter_mut().enumerate() {
+            row.id = id;
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -450,6 +2215,74 @@ pub(crate) struct Query {
     sql: String,
     table_header: RowData,
     query_input: RowData,
+    /// Execute the query as soon as it is selected. Only honored for queries without inputs.
+    #[serde(default)]
+    auto_run: bool,
+    /// Width and visibility of the result columns by header name
+    #[serde(default)]
+    column_layout: HashMap<String, ColumnLayout>,
+    #[serde(default)]
+    write_back: Option<WriteBack>,
+    /// Notes about the query, e.g. its assumptions, shown above the results
+    #[serde(default)]
+    description: String,
+    /// Column types whose result cells show their stored value when hovered
+    #[serde(default)]
+    cell_details: Vec<ColumnType>,
+    /// Keep the last result, to show it when the query is selected again. Only honored for queries
+    /// without inputs, their result depends on the input values.
+    #[serde(default)]
+    cache_result: bool,
+    #[serde(default)]
+    last_run: Option<LastRun>,
+}
+
+/// Allows editing result cells, which updates the row of `table` with the id of the result row.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct WriteBack {
+    table: String,
+    /// Header name of the column that holds the primary key
+    id_column: String,
+    /// Header names of the editable columns. Each has to be a column of the table.
+    columns: Vec<String>,
+}
+
+impl WriteBack {
+    /// `None` if no table is given. The columns are separated by commas.
+    pub(crate) fn new(table: &str, id_column: &str, columns: &str) -> Option<Self> {
+        let table = table.trim();
+        (!table.is_empty()).then(|| WriteBack {
+            table: table.to_string(),
+            id_column: id_column.trim().to_string(),
+            columns: columns
+                .split(',')
+                .map(str::trim)
+                .filter(|column| !column.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        })
+    }
+
+    /// Table, id column and comma separated columns, as they are entered.
+    pub(crate) fn to_entries(write_back: &Option<Self>) -> (String, String, String) {
+        write_back
+            .as_ref()
+            .map(|write_back| {
+                (
+                    write_back.table.clone(),
+                    write_back.id_column.clone(),
+                    write_back.columns.join(", "),
+                )
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct ColumnLayout {
+    /// `None` sizes the column automatically.
+    width: Option<i32>,
+    hidden: bool,
 }
 
 impl Query {
@@ -458,11 +2291,18 @@ impl Query {
             sql: String::new(),
             table_header: RowData::new(),
             query_input: RowData::new(),
+            auto_run: false,
+            column_layout: HashMap::new(),
+            write_back: None,
+            description: String::new(),
+            cell_details: Vec::new(),
+            cache_result: false,
+            last_run: None,
         }
     }
 }
 
-impl From<ExecQueryErrConv> for String {
+impl From<ExecQueryErrConv> for AppError {
     fn from(err: ExecQueryErrConv) -> Self {
         let conversion_failure = |column_idx: usize,
                                   expected_type: rusqlite::types::Type,
@@ -478,29 +2318,45 @@ impl From<ExecQueryErrConv> for String {
         };
         match err.err {
             rusqlite::Error::FromSqlConversionFailure(column_idx, expected_type, _) => {
-                conversion_failure(column_idx, expected_type, "<unknown>".to_string())
+                conversion_failure(column_idx, expected_type, "<unknown>".to_string()).into()
             }
             rusqlite::Error::InvalidColumnType(column_idx, sql_column_name, expected_type) => {
-                conversion_failure(column_idx, expected_type, sql_column_name)
+                conversion_failure(column_idx, expected_type, sql_column_name).into()
             }
             rusqlite::Error::InvalidParameterName(param) => {
-                format!("The query did not expected to receive a parameter '{param}'.")
+                format!("The query did not expected to receive a parameter '{param}'.").into()
             }
             rusqlite::Error::InvalidColumnIndex(_) => {
-                "The query has less columns than the amount of given output types.".to_string()
+                "The query has less columns than the amount of given output types.".into()
             }
-            rusqlite::Error::InvalidQuery => "The Query is invalid.".to_string(),
+            rusqlite::Error::InvalidQuery => "The Query is invalid.".into(),
             rusqlite::Error::MultipleStatement => {
-                "The query contains multiple statements. Only one is allowed".to_string()
+                "The query contains multiple statements. Only one is allowed".into()
             }
-            err => {
-                eprintln!("[execute query]{err:#?}");
-                "Unknown error".to_string()
+            rusqlite::Error::SqliteFailure(_, Some(msg))
+            | rusqlite::Error::SqlInputError { msg, .. }
+                if msg.starts_with("no such ") =>
+            {
+                missing_schema_object(&msg).into()
             }
+            // logged when it is reported
+            err => AppError::Db(err),
         }
     }
 }
 
+/// Explains a `no such table: X` or `no such column: X` error of SQLite.
+fn missing_schema_object(msg: &str) -> String {
+    let (kind, name) = msg
+        .trim_start_matches("no such ")
+        .split_once(": ")
+        .unwrap_or(("object", msg));
+    format!(
+        "The database has no {kind} '{name}'. The query may be written for a newer version of the database: \
+        reconnect the database to apply pending migrations, or adapt the query to this database."
+    )
+}
+
 struct ExecQueryErrConv {
     err: rusqlite::Error,
     given_type: ColumnType,
@@ -532,27 +2388,67 @@ impl Analysis {
         conn: &Connection,
         query_id: usize,
         query: &Query,
-        mut input_data: Vec<(String, ColumnTypeValue)>,
-    ) -> Result<Data, String> {
+        input_data: Vec<(String, ColumnTypeValue)>,
+        date_format: StorageFormat,
+        major_units: bool,
+        exclude_refunds: bool,
+    ) -> Result<Data, AppError> {
         let mut stmt = conn
             .prepare(&query.sql)
             .map_err(ExecQueryErrConv::empty())?;
-        let ctypes: Vec<Type> = query
-            .table_header
-            .0
-            .iter()
-            .map(|row_entry| row_entry.ty.into())
-            .collect();
-
-        let store = gtk::ListStore::new(ctypes.as_slice());
-
-        for (n, _) in &mut input_data {
-            n.insert(0, ':');
+        // every input is bound exactly once by name, so the sql may reference it multiple times
+        let mut params = Vec::with_capacity(input_data.len());
+        for (n, v) in input_data {
+            // relative dates are resolved to the day the query is executed
+            let v = match v {
+                ColumnTypeValue::Date(date) => {
+                    let date = resolve_date(&date, date_format).map_err(|_| {
+                        format!("The input '{n}' is neither a YYYY-MM-DD date nor a relative date like today or -7d.")
+                    })?;
+                    ColumnTypeValue::Date(date)
+                }
+                v => v,
+            };
+            let name = format!(":{n}");
+            let used = match &v {
+                ColumnTypeValue::Period(period) => {
+                    // the period and its range are optional, only bind the parts the sql uses
+                    for (suffix, date) in [("_from", &period.from), ("_to", &period.to)] {
+                        let name = format!(":{n}{suffix}");
+                        if stmt
+                            .parameter_index(&name)
+                            .map_err(ExecQueryErrConv::empty())?
+                            .is_some()
+                        {
+                            let date =
+                                convert_date(date, date_format).unwrap_or_else(|| date.clone());
+                            params.push((name, ColumnTypeValue::Date(date)));
+                        }
+                    }
+                    stmt.parameter_index(&name)
+                        .map_err(ExecQueryErrConv::empty())?
+                        .is_some()
+                }
+                _ => true,
+            };
+            if used {
+                params.push((name, v));
+            }
+        }
+        let name = format!(":{EXCLUDE_REFUNDS_PARAMETER}");
+        if !params.iter().any(|(n, _)| *n == name)
+            && stmt
+                .parameter_index(&name)
+                .map_err(ExecQueryErrConv::empty())?
+                .is_some()
+        {
+            params.push((name, ColumnTypeValue::Number(exclude_refunds.into())));
         }
-        let input_data: Vec<_> = input_data
+        let input_data: Vec<_> = params
             .iter()
             .map(|(n, v)| (n.as_str(), v as &dyn ToSql))
             .collect();
+        let mut result = Vec::new();
         let mut rows = stmt
             .query(input_data.as_slice())
             .map_err(ExecQueryErrConv::empty())?;
@@ -560,7 +2456,7 @@ impl Analysis {
             let mut values = Vec::with_capacity(query.table_header.0.len());
             for (i, row_entry) in query.table_header.0.iter().enumerate() {
                 match row_entry.ty {
-                    ColumnType::String => {
+                    ColumnType::String | ColumnType::Period => {
                         let v: String = row
                             .get(i)
                             .map_err(ExecQueryErrConv::new(ColumnType::String, &row_entry.name))?;
@@ -580,44 +2476,337 @@ impl Analysis {
                     }
                 }
             }
-            let mut value_refs = Vec::with_capacity(query.table_header.0.len());
-            for (i, value) in values.iter().enumerate() {
-                value_refs.push((i as u32, value as &dyn ToValue));
-            }
-
-            let iter = store.append();
-            store.set(&iter, value_refs.as_slice());
+            result.push(values);
         }
-        Ok(Data { store, query_id })
+        let editable = editable_columns(conn, query).map_err(ExecQueryErrConv::empty())?;
+        Ok(Data::new(query_id, query, &result, editable, major_units))
     }
 }
 
-fn save_queries(queries: &[(String, Query)]) -> std::io::Result<()> {
-    if let Ok(file) = File::options()
+/// The content of a queries file.
+#[derive(Serialize)]
+struct QueriesFile<'a> {
+    version: u64,
+    queries: &'a [(String, Query)],
+}
+
+/// The queries of a database are kept next to it, e.g. `expenses.db.queries.json` for
+/// `expenses.db`.
+fn queries_file(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{db_path}.queries.json"))
+}
+
+fn save_queries(path: &Path, queries: &[(String, Query)]) -> std::io::Result<()> {
+    let file = File::options()
         .create(true)
         .write(true)
         .truncate(true)
-        .open("./sqlbon_queries.json")
-    {
-        serde_json::to_writer(file, queries)?;
-    }
+        .open(path)?;
+    serde_json::to_writer(
+        file,
+        &QueriesFile {
+            version: QUERIES_VERSION,
+            queries,
+        },
+    )?;
     Ok(())
 }
 
-fn read_queries() -> std::io::Result<Vec<(String, Query)>> {
-    let file = File::open("./sqlbon_queries.json")?;
-    let mut data: Vec<(String, Query)> = serde_json::from_reader(file)?;
-    for (_, q) in &mut data {
-        let mut id_counter = 0;
-        for row in &mut q.table_header.0 {
-            row.id = id_counter;
-            id_counter += 1;
+/// Reads the saved queries and describes the ones that could not be read. A missing file has no
+/// queries.
+fn read_queries(path: &Path) -> std::io::Result<(Vec<(String, Query)>, Vec<String>)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((Vec::new(), Vec::new()))
         }
-        id_counter = 0;
-        for row in &mut q.query_input.0 {
-            row.id = id_counter;
-            id_counter += 1;
+        Err(err) => return Err(err),
+    };
+    Ok(parse_queries(&text)?)
+}
+
+/// Parses every query on its own, so one broken query does not discard the others. Files of
+/// older versions are read as well.
+fn parse_queries(text: &str) -> serde_json::Result<(Vec<(String, Query)>, Vec<String>)> {
+    let entries = upgrade_queries(serde_json::from_str(text)?)?;
+    let mut queries = Vec::new();
+    let mut problems = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let name = entry
+            .get(0)
+            .and_then(serde_json::Value::as_str)
+            .map_or_else(|| format!("#{}", i + 1), |name| format!("'{name}'"));
+        match serde_json::from_value::<(String, Query)>(entry) {
+            Ok((name, mut q)) => {
+                q.table_header.reindex();
+                q.query_input.reindex();
+                queries.push((name, q));
+            }
+            Err(err) => problems.push(format!("Query {name} could not be read: {err}")),
+        }
+    }
+    Ok((queries, problems))
+}
+
+/// Returns the entries of the queries of a file in the latest version. Files written by a newer
+/// version of sqlbon are not read, their queries may not survive being saved again.
+fn upgrade_queries(file: serde_json::Value) -> serde_json::Result<Vec<serde_json::Value>> {
+    use serde::de::Error;
+    let (version, queries) = match file {
+        serde_json::Value::Array(queries) => Ok((0, queries)),
+        serde_json::Value::Object(mut file) => match (
+            file.get("version").and_then(serde_json::Value::as_u64),
+            file.remove("queries"),
+        ) {
+            (Some(version), Some(serde_json::Value::Array(queries))) => Ok((version, queries)),
+            _ => Err(Error::custom("expected a version and a list of queries")),
+        },
+        _ => Err(Error::custom("expected a list of queries")),
+    }?;
+    if version > QUERIES_VERSION {
+        Err(Error::custom(format!(
+            "the file has version {version}, this version of sqlbon reads up to version {QUERIES_VERSION}"
+        )))
+    } else {
+        Ok((version..QUERIES_VERSION).fold(queries, upgrade_queries_from))
+    }
+}
+
+/// Upgrades the queries of a file of the version to the next version.
+fn upgrade_queries_from(queries: Vec<serde_json::Value>, version: u64) -> Vec<serde_json::Value> {
+    match version {
+        // version 1 only wrapped the list in an object with the version
+        0 => queries,
+        _ => unreachable!("version {version} is the latest"),
+    }
+}
+
+/// Loads the saved queries and describes what went wrong, if anything. Before the queries are
+/// saved again, which would drop the unreadable ones, the file is backed up.
+fn load_queries(path: &Path) -> (Vec<(String, Query)>, String) {
+    // a database without a queries file starts with the shared queries
+    let path = if path.exists() {
+        path
+    } else {
+        Path::new(SHARED_QUERIES_FILE)
+    };
+    let (queries, problems) = match read_queries(path) {
+        Ok((queries, problems)) if problems.is_empty() => return (queries, String::new()),
+        Ok((queries, problems)) => (queries, problems),
+        Err(err) => {
+            eprintln!("[read queries]{err:#?}");
+            (
+                Vec::new(),
+                vec![format!("{} could not be read: {err}", path.display())],
+            )
         }
+    };
+    let backup_path = path.with_extension("json.bak");
+    let backup = match std::fs::copy(path, &backup_path) {
+        Ok(_) => format!("The file was copied to {}.", backup_path.display()),
+        Err(err) => format!("The file could not be backed up: {err}"),
+    };
+    (queries, format!("{}\n{backup}", problems.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{sample_db, temp_path};
+
+    fn prepare_error(sql: &str) -> String {
+        let conn = sample_db();
+        let err = conn.prepare(sql).map(|_| ()).unwrap_err();
+        AppError::from(ExecQueryErrConv::empty()(err)).to_string()
+    }
+
+    #[test]
+    fn missing_column_is_named() {
+        let msg = prepare_error("SELECT brand FROM Item;");
+        assert!(
+            msg.starts_with("The database has no column 'brand'."),
+            "{msg}"
+        );
+    }
+
+    #[test]
+    fn missing_table_is_named() {
+        let msg = prepare_error("SELECT * FROM Category;");
+        assert!(
+            msg.starts_with("The database has no table 'Category'."),
+            "{msg}"
+        );
+    }
+
+    #[test]
+    fn row_matches_text_and_number_ranges() {
+        let row = [
+            ColumnTypeValue::String("Milk".to_string()),
+            ColumnTypeValue::Date("2022-06-01".to_string()),
+            ColumnTypeValue::Number(250),
+        ];
+        assert!(row_matches(&row, ""));
+        assert!(row_matches(&row, "milk"));
+        assert!(row_matches(&row, "2022-06"));
+        assert!(row_matches(&row, "250"));
+        assert!(row_matches(&row, "100..250"));
+        assert!(row_matches(&row, "..300"));
+        assert!(!row_matches(&row, "251.."));
+        assert!(!row_matches(&row, "bread"));
+        // numbers are not matched as text
+        assert!(!row_matches(&row, "25"));
+    }
+
+    #[test]
+    fn results_are_saved_as_a_new_table() {
+        let conn = sample_db();
+        let header = RowData(
+            [("name", ColumnType::String), ("total", ColumnType::Number)]
+                .into_iter()
+                .enumerate()
+                .map(|(id, (name, ty))| RowEntry {
+                    name: name.to_string(),
+                    ty,
+                    id,
+                })
+                .collect(),
+        );
+        let rows = vec![
+            vec![
+                ColumnTypeValue::String("Milk".to_string()),
+                ColumnTypeValue::Number(6170),
+            ],
+            vec![
+                ColumnTypeValue::String("Bread".to_string()),
+                ColumnTypeValue::Number(3000),
+            ],
+        ];
+        assert_eq!(save_as_table(&conn, "Totals", &header, &rows), Ok(2));
+        let total: i64 = conn
+            .query_row(
+                "SELECT total FROM Totals WHERE name == 'Milk';",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(total, 6170);
+
+        let err = save_as_table(&conn, "item", &header, &rows).unwrap_err();
+        assert_eq!(err, "The database already has a table 'item'.");
+
+        // a row that does not fit the header leaves no table behind
+        let broken = vec![vec![ColumnTypeValue::Number(1)]];
+        assert!(save_as_table(&conn, "Broken", &header, &broken).is_err());
+        let tables: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name == 'Broken';",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn only_columns_of_the_table_are_editable() {
+        let conn = sample_db();
+        let query = |sql: &str, header: &[&str]| Query {
+            sql: sql.to_string(),
+            table_header: RowData(
+                header
+                    .iter()
+                    .enumerate()
+                    .map(|(id, name)| RowEntry {
+                        name: name.to_string(),
+                        ty: ColumnType::String,
+                        id,
+                    })
+                    .collect(),
+            ),
+            write_back: WriteBack::new("Item", "id", "name, unit, store"),
+            ..Query::new()
+        };
+        let joined = query(
+            "SELECT Item.id, Item.name, UPPER(Item.unit) AS unit, Store.name AS store FROM Item
+            INNER JOIN Receipt ON Item.receipt == Receipt.id
+            INNER JOIN Store ON Receipt.store == Store.id;",
+            &["id", "name", "unit", "store"],
+        );
+        assert_eq!(
+            editable_columns(&conn, &joined).unwrap(),
+            [false, true, false, false]
+        );
+        // a renamed column of the table is not the column of its header name
+        let renamed = query("SELECT id, unit AS name FROM Item;", &["id", "name"]);
+        assert_eq!(editable_columns(&conn, &renamed).unwrap(), [false, false]);
+        let receipt_id = query("SELECT receipt AS id, name FROM Item;", &["id", "name"]);
+        assert_eq!(
+            editable_columns(&conn, &receipt_id).unwrap(),
+            [false, false]
+        );
+    }
+
+    #[test]
+    fn indices_of_later_queries_move_down_on_deletion() {
+        assert_eq!(index_after_removal(0, 1), Some(0));
+        assert_eq!(index_after_removal(1, 1), None);
+        assert_eq!(index_after_removal(2, 1), Some(1));
+    }
+
+    #[test]
+    fn unreadable_queries_are_skipped() {
+        let valid = serde_json::to_string(&("Totals", Query::new())).unwrap();
+        let text = format!("[{valid}, [\"Broken\", {{\"sql\": 1}}], 5]");
+        let (queries, problems) = parse_queries(&text).unwrap();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].0, "Totals");
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("Query 'Broken' could not be read"));
+        assert!(problems[1].starts_with("Query #3 could not be read"));
+
+        assert!(parse_queries("[{").is_err());
+    }
+
+    #[test]
+    fn queries_files_are_upgraded() {
+        let queries = vec![("Totals".to_string(), Query::new())];
+        let current = serde_json::to_string(&QueriesFile {
+            version: QUERIES_VERSION,
+            queries: &queries,
+        })
+        .unwrap();
+        let legacy = serde_json::to_string(&queries).unwrap();
+        for text in [current, legacy] {
+            let (read, problems) = parse_queries(&text).unwrap();
+            assert_eq!(read.len(), 1, "{text}");
+            assert_eq!(read[0].0, "Totals");
+            assert!(problems.is_empty());
+        }
+
+        let newer = format!("{{\"version\": {}, \"queries\": []}}", QUERIES_VERSION + 1);
+        let err = parse_queries(&newer).unwrap_err().to_string();
+        assert!(err.contains("reads up to version"), "{err}");
+        assert!(parse_queries("{\"queries\": []}").is_err());
+        assert!(parse_queries("5").is_err());
+    }
+
+    #[test]
+    fn export_reports_progress_and_removes_cancelled_files() {
+        let path = temp_path("export.csv");
+        let rows = vec!["1\n".to_string(); 2500];
+        let mut reported = Vec::new();
+        let written = write_export(&path, "n\n", &rows, &AtomicBool::new(false), |rows| {
+            reported.push(rows)
+        });
+        assert_eq!(written.unwrap(), Some(2500));
+        assert_eq!(reported, [1000, 2000]);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap().lines().count(),
+            2501
+        );
+
+        let written = write_export(&path, "n\n", &rows, &AtomicBool::new(true), |_| {});
+        assert_eq!(written.unwrap(), None);
+        assert!(!path.exists());
     }
-    Ok(data)
 }
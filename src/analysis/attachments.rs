@@ -0,0 +1,240 @@
+use crate::analysis::{Analysis, AnalysisMsg};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use relm4::ComponentSender;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// Another database attached to the connection, so queries can use its tables as e.g.
+/// `alias.Item`. Attachments are stored in the database they belong to and attached again
+/// whenever it is connected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Attachment {
+    pub(crate) alias: String,
+    pub(crate) path: String,
+    /// Stored attachments whose file is missing stay listed, so they can be removed.
+    pub(crate) attached: bool,
+}
+
+/// Aliases are used unquoted in queries, so they have to be plain identifiers.
+fn check_alias(alias: &str, attachments: &[Attachment]) -> Result<(), String> {
+    let identifier = !alias.is_empty()
+        && !alias.starts_with(|c: char| c.is_ascii_digit())
+        && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !identifier {
+        Err(format!(
+            "'{alias}' is not a valid alias, use letters, digits and '_'."
+        ))
+    } else if ["main", "temp"].contains(&alias.to_lowercase().as_str())
+        || attachments
+            .iter()
+            .any(|attachment| attachment.alias.eq_ignore_ascii_case(alias))
+    {
+        Err(format!("The alias '{alias}' is already in use."))
+    } else {
+        Ok(())
+    }
+}
+
+/// `ATTACH` creates missing files, so only existing SQLite files are attached.
+fn attach_db(conn: &Connection, alias: &str, path: &str) -> Result<(), String> {
+    if !Path::new(path).is_file() {
+        return Err(format!("The file '{path}' does not exist."));
+    }
+    conn.execute("ATTACH DATABASE ?1 AS ?2;", params![path, alias])
+        .map_err(|err| format!("Could not attach '{path}': {err}"))?;
+    // the file is only read when it is first used
+    let readable = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {alias}.sqlite_master;"),
+        [],
+        |row| row.get::<_, i64>(0),
+    );
+    if let Err(err) = readable {
+        detach_db(conn, alias);
+        return Err(format!("'{path}' is not a SQLite database: {err}"));
+    }
+    Ok(())
+}
+
+fn detach_db(conn: &Connection, alias: &str) {
+    if let Err(err) = conn.execute("DETACH DATABASE ?1;", params![alias]) {
+        eprintln!("[detach]{err:#?}");
+    }
+}
+
+/// Attaches the database at `path` and stores the attachment.
+pub(crate) fn add(
+    conn: &Connection,
+    alias: &str,
+    path: &str,
+    attachments: &[Attachment],
+) -> Result<Attachment, String> {
+    check_alias(alias, attachments)?;
+    attach_db(conn, alias, path)?;
+    if let Err(err) = conn.execute(
+        "INSERT INTO Attachment (alias, path) VALUES (?1, ?2);",
+        params![alias, path],
+    ) {
+        detach_db(conn, alias);
+        return Err(format!("Could not store the attachment: {err}"));
+    }
+    Ok(Attachment {
+        alias: alias.to_string(),
+        path: path.to_string(),
+        attached: true,
+    })
+}
+
+/// Detaches the database and forgets the attachment.
+pub(crate) fn remove(conn: &Connection, attachment: &Attachment) -> rusqlite::Result<()> {
+    if attachment.attached {
+        conn.execute("DETACH DATABASE ?1;", params![attachment.alias])?;
+    }
+    conn.execute(
+        "DELETE FROM Attachment WHERE alias == ?1;",
+        params![attachment.alias],
+    )?;
+    Ok(())
+}
+
+/// Attaches the stored attachments of a newly connected database. Returns them together with
+/// the reasons why some could not be attached.
+pub(crate) fn attach_stored(conn: &Connection) -> (Vec<Attachment>, Vec<String>) {
+    let stored = conn
+        .prepare("SELECT alias, path FROM Attachment ORDER BY alias ASC;")
+        .and_then(|mut query| {
+            query
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<(String, String)>>>()
+        });
+    let stored = match stored {
+        Ok(stored) => stored,
+        Err(err) => {
+            return (
+                Vec::new(),
+                vec![format!("Could not load the attachments: {err}")],
+            )
+        }
+    };
+
+    let mut problems = Vec::new();
+    let attachments = stored
+        .into_iter()
+        .map(|(alias, path)| {
+            let attached = match attach_db(conn, &alias, &path) {
+                Ok(()) => true,
+                Err(err) => {
+                    problems.push(format!("'{alias}' is not attached. {err}"));
+                    false
+                }
+            };
+            Attachment {
+                alias,
+                path,
+                attached,
+            }
+        })
+        .collect();
+    (attachments, problems)
+}
+
+/// Detaches all attached databases, e.g. before the connection is closed.
+pub(crate) fn detach_all(conn: &Connection, attachments: &[Attachment]) {
+    for attachment in attachments.iter().filter(|attachment| attachment.attached) {
+        detach_db(conn, &attachment.alias);
+    }
+}
+
+pub(crate) trait ShowAttachments {
+    /// Replaces the children with a row per attachment with a button to remove it.
+    fn show_attachments(&self, attachments: &[Attachment], sender: &ComponentSender<Analysis>);
+}
+
+impl ShowAttachments for gtk::Box {
+    fn show_attachments(&self, attachments: &[Attachment], sender: &ComponentSender<Analysis>) {
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+        for (idx, attachment) in attachments.iter().enumerate() {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+            let label = gtk::Label::new(Some(&format!(
+                "{}{}",
+                attachment.alias,
+                if attachment.attached {
+                    ""
+                } else {
+                    " (not attached)"
+                }
+            )));
+            label.set_hexpand(true);
+            label.set_xalign(0.0);
+            label.set_tooltip_text(Some(&attachment.path));
+            row.append(&label);
+            let button = gtk::Button::with_label("remove");
+            let sender = sender.clone();
+            button.connect_clicked(move |_| sender.input(AnalysisMsg::Detach(idx)));
+            row.append(&button);
+            self.append(&row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{sample_db, temp_path};
+
+    #[test]
+    fn attachments_are_stored_and_queried_across_databases() {
+        let path = temp_path("attach.db");
+        let path_str = path.to_str().unwrap();
+        {
+            let other = Connection::open(&path).unwrap();
+            crate::schema::init(&other).unwrap();
+            other
+                .execute_batch("INSERT INTO Store (name, location) VALUES ('Market', 'City');")
+                .unwrap();
+        }
+
+        let conn = sample_db();
+        let attached = [add(&conn, "other", path_str, &[]).unwrap()];
+        let stores: i64 = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM Store) + (SELECT COUNT(*) FROM other.Store);",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stores, 3);
+        assert!(add(&conn, "OTHER", path_str, &attached).is_err());
+        assert!(add(&conn, "main", path_str, &[]).is_err());
+        assert!(add(&conn, "1st", path_str, &[]).is_err());
+
+        detach_all(&conn, &attached);
+        let (attachments, problems) = attach_stored(&conn);
+        assert_eq!(attachments, attached);
+        assert!(problems.is_empty());
+
+        remove(&conn, &attached[0]).unwrap();
+        assert!(conn
+            .query_row("SELECT 1 FROM other.Store;", [], |_| Ok(()))
+            .is_err());
+        assert_eq!(attach_stored(&conn), (Vec::new(), Vec::new()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_files_are_not_attached() {
+        let conn = sample_db();
+        assert!(add(&conn, "other", "/nonexistent/sqlbon.db", &[]).is_err());
+        conn.execute(
+            "INSERT INTO Attachment (alias, path) VALUES ('gone', '/nonexistent/sqlbon.db');",
+            [],
+        )
+        .unwrap();
+        let (attachments, problems) = attach_stored(&conn);
+        assert!(!attachments[0].attached);
+        assert_eq!(problems.len(), 1);
+        remove(&conn, &attachments[0]).unwrap();
+    }
+}
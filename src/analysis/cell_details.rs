@@ -0,0 +1,96 @@
+use crate::analysis::{unit_column, ColumnType, ColumnTypeValue, RowData};
+use crate::date::{convert_date, StorageFormat};
+use crate::unit::{Money, Unit};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+
+/// Which result columns show the stored value of a cell when it is hovered.
+#[derive(Debug, Default)]
+pub(crate) struct CellDetails {
+    /// The type of every column with details, `None` for the others
+    columns: Vec<Option<ColumnType>>,
+    /// A string column named `unit`, whose value is the currency of the numbers of its row
+    unit_column: Option<usize>,
+}
+
+impl CellDetails {
+    /// Details are shown for the columns of the header whose type is one of `types`.
+    pub(crate) fn new(header: &RowData, types: &[ColumnType]) -> Self {
+        CellDetails {
+            columns: header
+                .0
+                .iter()
+                .map(|entry| Some(entry.ty).filter(|ty| types.contains(ty)))
+                .collect(),
+            unit_column: unit_column(header),
+        }
+    }
+
+    /// The tooltip of the cell in `column` of the row, `None` if the column shows no details.
+    pub(crate) fn tooltip(
+        &self,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+        column: usize,
+    ) -> Option<String> {
+        let value = match (*self.columns.get(column)?)? {
+            ColumnType::Number => ColumnTypeValue::Number(model.get(iter, column as i32)),
+            ColumnType::Date => ColumnTypeValue::Date(model.get(iter, column as i32)),
+            ColumnType::String | ColumnType::Period => return None,
+        };
+        let unit: Option<String> = self.unit_column.map(|unit| model.get(iter, unit as i32));
+        detail_text(&value, unit.as_deref())
+    }
+}
+
+/// Describes the stored value. Numbers are shown with the unit of their row and, for a known
+/// unit, in major units. Dates are shown in ISO format as well, if they are stored otherwise.
+fn detail_text(value: &ColumnTypeValue, unit: Option<&str>) -> Option<String> {
+    match value {
+        ColumnTypeValue::Number(n) => Some(match unit.map(|unit| (unit, Unit::from_str(unit))) {
+            Some((_, Ok(unit))) => format!("stored: {n} {unit}\n{:#}", Money::new(*n, unit)),
+            Some((unit, Err(()))) => format!("stored: {n} {unit}"),
+            None => format!("stored: {n}"),
+        }),
+        ColumnTypeValue::Date(date) => Some(match convert_date(date, StorageFormat::Iso) {
+            Some(iso) if iso != *date => format!("stored: {date}\nISO: {iso}"),
+            Some(_) => format!("stored: {date}"),
+            None => format!("stored: {date}\nnot a valid date"),
+        }),
+        ColumnTypeValue::String(_) | ColumnTypeValue::Period(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn details_show_the_stored_value() {
+        let number = ColumnTypeValue::Number(-1299);
+        assert_eq!(
+            detail_text(&number, Some("NOK")).unwrap(),
+            "stored: -1299 NOK\n-12.99 NOK"
+        );
+        assert_eq!(
+            detail_text(&number, Some("USD")).unwrap(),
+            "stored: -1299 USD"
+        );
+        assert_eq!(detail_text(&number, None).unwrap(), "stored: -1299");
+
+        let date = |date: &str| detail_text(&ColumnTypeValue::Date(date.to_string()), None);
+        assert_eq!(
+            date("24.12.2022").unwrap(),
+            "stored: 24.12.2022\nISO: 2022-12-24"
+        );
+        assert_eq!(date("2022-12-24").unwrap(), "stored: 2022-12-24");
+        assert_eq!(
+            date("2022-13-24").unwrap(),
+            "stored: 2022-13-24\nnot a valid date"
+        );
+        assert_eq!(
+            detail_text(&ColumnTypeValue::String("milk".to_string()), None),
+            None
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::analysis::{row_values, ColumnType, ColumnTypeValue, RowData};
+use relm4::gtk;
+use relm4::gtk::cairo;
+use relm4::gtk::prelude::*;
+
+const MARGIN: f64 = 30.0;
+const FONT_SIZE: f64 = 11.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Kind {
+    /// Categories, e.g. per store
+    Bar,
+    /// A time series, e.g. per month
+    Line,
+}
+
+/// A result with exactly one `String` or `Date` label column followed by one `Number` column.
+#[derive(Debug)]
+pub(crate) struct Chart {
+    kind: Kind,
+    points: Vec<(String, i64)>,
+}
+
+impl Chart {
+    /// `None` if the result does not have the shape of a chart.
+    pub(crate) fn new(store: &gtk::ListStore, header: &RowData) -> Option<Self> {
+        let kind = match header.0.as_slice() {
+            [label, value] if value.ty == ColumnType::Number => match label.ty {
+                ColumnType::String => Kind::Bar,
+                ColumnType::Date => Kind::Line,
+                ColumnType::Number | ColumnType::Period => return None,
+            },
+            _ => return None,
+        };
+        let mut points = Vec::new();
+        if let Some(iter) = store.iter_first() {
+            loop {
+                if let [label, ColumnTypeValue::Number(value)] =
+                    row_values(store, &iter, header).as_slice()
+                {
+                    points.push((label.to_string(), *value));
+                }
+                if !store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        Some(Chart { kind, points })
+    }
+
+    pub(crate) fn draw(&self, cr: &cairo::Context, width: i32, height: i32) {
+        if let Err(err) = self.try_draw(cr, width as f64, height as f64) {
+            eprintln!("[draw chart]{err:#?}");
+        }
+    }
+
+    fn try_draw(&self, cr: &cairo::Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+        if self.points.is_empty() {
+            return Ok(());
+        }
+        // the zero line is always part of the chart
+        let max = self.points.iter().fold(0, |max, (_, v)| max.max(*v));
+        let min = self.points.iter().fold(0, |min, (_, v)| min.min(*v));
+        let range = (max - min).max(1) as f64;
+        let plot_width = (width - 2.0 * MARGIN).max(1.0);
+        let plot_height = (height - 2.0 * MARGIN).max(1.0);
+        let y = |value: i64| MARGIN + (max - value) as f64 / range * plot_height;
+        let slot = plot_width / self.points.len() as f64;
+        let x = |i: usize| MARGIN + (i as f64 + 0.5) * slot;
+
+        cr.set_font_size(FONT_SIZE);
+        cr.set_line_width(1.0);
+
+        // axis at zero
+        cr.set_source_rgb(0.5, 0.5, 0.5);
+        cr.move_to(MARGIN, y(0));
+        cr.line_to(MARGIN + plot_width, y(0));
+        cr.stroke()?;
+        cr.move_to(2.0, MARGIN);
+        cr.show_text(&max.to_string())?;
+        if min < 0 {
+            cr.move_to(2.0, MARGIN + plot_height);
+            cr.show_text(&min.to_string())?;
+        }
+
+        cr.set_source_rgb(0.2, 0.4, 0.8);
+        match self.kind {
+            Kind::Bar => {
+                for (i, (_, value)) in self.points.iter().enumerate() {
+                    let top = y((*value).max(0));
+                    let bottom = y((*value).min(0));
+                    cr.rectangle(x(i) - slot * 0.4, top, slot * 0.8, bottom - top);
+                }
+                cr.fill()?;
+            }
+            Kind::Line => {
+                cr.set_line_width(2.0);
+                for (i, (_, value)) in self.points.iter().enumerate() {
+                    cr.line_to(x(i), y(*value));
+                }
+                cr.stroke()?;
+            }
+        }
+
+        // only label as many points as fit next to each other
+        cr.set_source_rgb(0.3, 0.3, 0.3);
+        let label_step = (FONT_SIZE * 8.0 / slot).ceil().max(1.0) as usize;
+        for (i, (label, _)) in self.points.iter().enumerate().step_by(label_step) {
+            let extents = cr.text_extents(label)?;
+            cr.move_to(x(i) - extents.width() / 2.0, height - MARGIN / 2.0);
+            cr.show_text(label)?;
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
-use crate::analysis::type_component::{TypeMsg, Validity};
-use crate::analysis::{type_component, Query, RowData};
+use crate::analysis::type_component::{TypeInit, TypeMsg, Validity, ValidityMsg};
+use crate::analysis::{
+    type_component, ColumnType, Query, RowData, WriteBack, DEFAULT_REQUIRED_ROWS,
+    EXCLUDE_REFUNDS_PARAMETER,
+};
 use crate::dialog_ext::AppendDialog;
 use crate::AnalysisMsg;
 use relm4::gtk::glib::GString;
@@ -8,6 +11,8 @@ use relm4::{
     gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
     RelmWidgetExt, SimpleComponent,
 };
+use rusqlite::Connection;
+use std::rc::Rc;
 
 #[tracker::track]
 struct Ui {
@@ -23,7 +28,19 @@ struct Ui {
     #[tracker::no_eq]
     sql: String,
     #[tracker::no_eq]
+    auto_run: bool,
+    #[tracker::no_eq]
+    cache_result: bool,
+    /// Table, id column and editable columns of the write back
+    #[tracker::no_eq]
+    write_back: (String, String, String),
+    #[tracker::no_eq]
+    description: String,
+    #[tracker::no_eq]
     input_status: String,
+    /// Mismatches between the parameters of the sql and the inputs
+    #[tracker::no_eq]
+    parameter_status: String,
     #[tracker::no_eq]
     output_status: String,
     #[tracker::no_eq]
@@ -32,6 +49,8 @@ struct Ui {
 
 pub(crate) struct QueryDialog {
     hidden: bool,
+    /// Used to find the parameters of the sql
+    conn: Option<Rc<Connection>>,
     id: usize,
     names: Vec<String>,
     ui: Ui,
@@ -50,11 +69,22 @@ pub(crate) enum QueryDialogMsg {
     Accept {
         name: String,
         sql: String,
+        auto_run: bool,
+        cache_result: bool,
+        write_back: Option<WriteBack>,
+        description: String,
     },
     Cancel,
     NameChanged(GString),
     OutputValidityChanged(Validity),
     InputValidityChanged(Validity),
+    ConnectDb(Rc<Connection>),
+    DisconnectDb,
+    SqlChanged(GString),
+    /// Compares the parameters of the sql with the inputs
+    CheckParameters,
+    /// How many inputs and header columns a query needs at least
+    RequiredRows(usize, usize),
 }
 
 #[relm4::component(pub(crate))]
@@ -108,12 +138,54 @@ impl SimpleComponent for QueryDialog {
                         set_halign: gtk::Align::Fill,
                         #[track(model.ui.changed(Ui::sql()))]
                         set_text: model.ui.sql.as_str(),
+                        set_tooltip_text: Some("Ctrl+Enter saves the query, once it is valid."),
+                        connect_changed[sender] => move |sql| {
+                            sender.input(QueryDialogMsg::SqlChanged(sql.text()));
+                        },
+                        add_controller = &gtk::EventControllerKey {
+                            connect_key_pressed[dialog] => move |_, key, _, modifier| {
+                                if modifier.contains(gtk::gdk::ModifierType::CONTROL_MASK)
+                                    && matches!(key, gtk::gdk::Key::Return | gtk::gdk::Key::KP_Enter)
+                                {
+                                    // like the ok button, but the query is checked by the update
+                                    dialog.response(gtk::ResponseType::Accept);
+                                    return gtk::Inhibit(true);
+                                }
+                                gtk::Inhibit(false)
+                            },
+                        },
+                    },
+                    attach[2, 1, 1, 1]: auto_run_check = &gtk::CheckButton {
+                        set_label: Some("run on selection"),
+                        set_tooltip_text: Some("Only applies to queries without inputs."),
+                        #[track(model.ui.changed(Ui::auto_run()))]
+                        set_active: model.ui.auto_run,
+                    },
+                    attach[2, 2, 1, 1]: cache_result_check = &gtk::CheckButton {
+                        set_label: Some("cache result"),
+                        set_tooltip_text: Some("Shows the last result when the query is selected, until the SQL or the database changes. Only applies to queries without inputs."),
+                        #[track(model.ui.changed(Ui::cache_result()))]
+                        set_active: model.ui.cache_result,
                     },
                     attach[1, 2, 1, 1] = &gtk::Label {
                         #[track(model.ui.changed(Ui::name_status()))]
                         set_text: model.ui.name_status.as_str(),
                         set_halign: gtk::Align::Center,
                     },
+                    attach[0, 11, 1, 1] = &gtk::Label {
+                        set_text: "Description:",
+                        set_halign: gtk::Align::End,
+                        set_valign: gtk::Align::Start,
+                    },
+                    attach[1, 11, 1, 1] = &gtk::ScrolledWindow {
+                        set_min_content_height: 60,
+                        #[name(description_view)]
+                        gtk::TextView {
+                            set_wrap_mode: gtk::WrapMode::WordChar,
+                            #[track(model.ui.changed(Ui::description()))]
+                            set_buffer: Some(&gtk::TextBuffer::builder().text(&model.ui.description).build()),
+                        },
+                    },
                     attach[0, 3, 2, 1] = &gtk::Separator {},
                     attach[0, 4, 1, 1] = &gtk::Label {
                         set_text: "Header Definition:",
@@ -132,19 +204,71 @@ impl SimpleComponent for QueryDialog {
                     },
                     attach[1, 7, 1, 1]: model.input_types.widget(),
                     attach[1, 8, 1, 1] = &gtk::Label {
-                        #[track(model.ui.changed(Ui::input_status()))]
-                        set_text: model.ui.input_status.as_str(),
+                        #[track(model.ui.changed(Ui::input_status()) || model.ui.changed(Ui::parameter_status()))]
+                        set_text: &[model.ui.input_status.as_str(), model.ui.parameter_status.as_str()]
+                            .into_iter()
+                            .filter(|status| !status.is_empty())
+                            .collect::<Vec<_>>()
+                            .join("\n"),
                         set_halign: gtk::Align::Center,
                     },
+                    attach[0, 9, 2, 1] = &gtk::Separator {},
+                    attach[0, 10, 1, 1] = &gtk::Label {
+                        set_text: "Write Back:",
+                        set_halign: gtk::Align::End,
+                    },
+                    attach[1, 10, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        set_tooltip_text: Some("Edited result cells update the row of the table with the id of the result row. Leave the table empty to disable editing."),
+                        gtk::Label {
+                            set_text: "table",
+                        },
+                        #[name(write_back_table_entry)]
+                        gtk::Entry {
+                            #[track(model.ui.changed(Ui::write_back()))]
+                            set_text: model.ui.write_back.0.as_str(),
+                        },
+                        gtk::Label {
+                            set_text: "id column",
+                        },
+                        #[name(write_back_id_entry)]
+                        gtk::Entry {
+                            #[track(model.ui.changed(Ui::write_back()))]
+                            set_text: model.ui.write_back.1.as_str(),
+                        },
+                        gtk::Label {
+                            set_text: "editable columns",
+                        },
+                        #[name(write_back_columns_entry)]
+                        gtk::Entry {
+                            set_hexpand: true,
+                            set_placeholder_text: Some("comma separated header names"),
+                            #[track(model.ui.changed(Ui::write_back()))]
+                            set_text: model.ui.write_back.2.as_str(),
+                        },
+                    },
                 },
             },
-            connect_response[sender, sql_entry, name_entry] => move |_, resp| {
+            connect_response[sender, sql_entry, name_entry, auto_run_check, cache_result_check, write_back_table_entry, write_back_id_entry, write_back_columns_entry, description_view] => move |_, resp| {
                 let response = if resp == gtk::ResponseType::Accept {
                     let name = name_entry.text().trim().to_string();
                     let sql = sql_entry.text().trim().to_string();
                     QueryDialogMsg::Accept{
                         sql,
                         name,
+                        auto_run: auto_run_check.is_active(),
+                        cache_result: cache_result_check.is_active(),
+                        write_back: WriteBack::new(
+                            &write_back_table_entry.text(),
+                            &write_back_id_entry.text(),
+                            &write_back_columns_entry.text(),
+                        ),
+                        description: {
+                            let buffer = description_view.buffer();
+                            let (start, end) = buffer.bounds();
+                            buffer.text(&start, &end, false).trim().to_string()
+                        },
                     }
                 } else {
                     QueryDialogMsg::Cancel
@@ -180,25 +304,31 @@ impl SimpleComponent for QueryDialog {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let input_types =
-            type_component::Type::builder()
-                .launch(0)
-                .forward(sender.input_sender(), |val_msg| match val_msg {
-                    type_component::ValidityMsg::ValidityChanged(val) => {
-                        QueryDialogMsg::InputValidityChanged(val)
-                    }
-                });
-        let output_types =
-            type_component::Type::builder()
-                .launch(1)
-                .forward(sender.input_sender(), |val_msg| match val_msg {
-                    type_component::ValidityMsg::ValidityChanged(val) => {
-                        QueryDialogMsg::OutputValidityChanged(val)
-                    }
-                });
+        let input_types = type_component::Type::builder()
+            .launch(TypeInit {
+                required_rows: DEFAULT_REQUIRED_ROWS.0,
+                parameter_names: true,
+                column_types: &ColumnType::INPUT,
+            })
+            .forward(sender.input_sender(), |val_msg| match val_msg {
+                ValidityMsg::ValidityChanged(val) => QueryDialogMsg::InputValidityChanged(val),
+                ValidityMsg::NamesChanged => QueryDialogMsg::CheckParameters,
+            });
+        let output_types = type_component::Type::builder()
+            .launch(TypeInit {
+                required_rows: DEFAULT_REQUIRED_ROWS.1,
+                parameter_names: false,
+                column_types: &ColumnType::OUTPUT,
+            })
+            .forward(sender.input_sender(), |val_msg| match val_msg {
+                ValidityMsg::ValidityChanged(val) => QueryDialogMsg::OutputValidityChanged(val),
+                // header names are no parameters, so this is never sent
+                ValidityMsg::NamesChanged => QueryDialogMsg::CheckParameters,
+            });
 
         let model = QueryDialog {
             hidden: true,
+            conn: None,
             id: 0,
             names: Vec::new(),
             ui: Ui {
@@ -209,7 +339,12 @@ impl SimpleComponent for QueryDialog {
                 init_query: RowData::new(),
                 name: String::new(),
                 sql: String::new(),
+                auto_run: false,
+                cache_result: false,
+                write_back: Default::default(),
+                description: String::new(),
                 input_status: String::new(),
+                parameter_status: String::new(),
                 output_status: String::new(),
                 name_status: String::new(),
                 tracker: 0,
@@ -252,27 +387,63 @@ impl SimpleComponent for QueryDialog {
                 self.ui.set_ok_button_name(ok_button_name);
                 self.ui.set_name(current_name.clone());
                 self.ui.set_sql(query.sql);
+                self.ui.set_auto_run(query.auto_run);
+                self.ui.set_cache_result(query.cache_result);
+                self.ui
+                    .set_write_back(WriteBack::to_entries(&query.write_back));
+                self.ui.set_description(query.description);
                 self.output_types
                     .emit(TypeMsg::Replicate(query.table_header));
                 self.input_types.emit(TypeMsg::Replicate(query.query_input));
                 self.names = names;
             }
-            QueryDialogMsg::Accept { name, sql } => {
-                if self.ui.input_valid && self.ui.output_valid {
+            QueryDialogMsg::Accept {
+                name,
+                sql,
+                auto_run,
+                cache_result,
+                write_back,
+                description,
+            } => {
+                // Ctrl+Enter in the sql entry accepts even while the ok button is insensitive
+                if self.ui.name_valid && self.ui.input_valid && self.ui.output_valid {
                     let table_header = self.output_types.state().get().model.get_row_data();
                     let query_input = self.input_types.state().get().model.get_row_data();
                     let query = Query {
                         sql,
                         table_header,
                         query_input,
+                        auto_run,
+                        column_layout: Default::default(),
+                        cell_details: Vec::new(),
+                        cache_result,
+                        last_run: None,
+                        write_back,
+                        description,
                     };
                     sender.output(AnalysisMsg::EditQueryResult(query, name, self.id));
                     self.hidden = true;
+                } else if !self.ui.name_valid && self.ui.name_status.is_empty() {
+                    // the name of a new query is only checked once it is edited
+                    self.ui
+                        .set_name_status("Each query needs a name.".to_string());
                 }
             }
             QueryDialogMsg::Cancel => {
                 self.hidden = true;
             }
+            QueryDialogMsg::ConnectDb(conn) => self.conn = Some(conn),
+            QueryDialogMsg::DisconnectDb => self.conn = None,
+            QueryDialogMsg::SqlChanged(sql) => {
+                // silent, the entry already shows the text
+                self.ui.sql = sql.to_string();
+                self.check_parameters();
+            }
+            QueryDialogMsg::CheckParameters => self.check_parameters(),
+            QueryDialogMsg::RequiredRows(inputs, outputs) => {
+                self.input_types.emit(TypeMsg::RequiredRows(inputs));
+                self.output_types.emit(TypeMsg::RequiredRows(outputs));
+            }
             QueryDialogMsg::NameChanged(name) => {
                 let name = name.trim();
                 let is_filled = !name.is_empty();
@@ -304,10 +475,18 @@ impl SimpleComponent for QueryDialog {
                     Validity::NotFilled => self
                         .ui
                         .set_input_status("All query input entries need a name.".to_string()),
-                    Validity::Duplicates => self
-                        .ui
-                        .set_input_status("All query input entries need to be unique.".to_string()),
-                    Validity::Valid => self.ui.set_input_status(String::new()),
+                    Validity::Duplicates => self.ui.set_input_status(
+                        "All query input entries need to be unique. Reference an input multiple times in the SQL instead of defining it twice."
+                            .to_string(),
+                    ),
+                    Validity::InvalidName => self.ui.set_input_status(
+                        "Query input names may only contain letters, digits and '_' and must not start with a digit."
+                            .to_string(),
+                    ),
+                    Validity::Valid => self.ui.set_input_status(
+                        "Each input is bound once as ':name' and can be used any number of times in the SQL. ':exclude_refunds' is 1 if refunds are left out of totals."
+                            .to_string(),
+                    ),
                 }
             }
             QueryDialogMsg::OutputValidityChanged(val) => {
@@ -322,9 +501,164 @@ impl SimpleComponent for QueryDialog {
                     Validity::Duplicates => self.ui.set_output_status(
                         "All table header entries need to be unique.".to_string(),
                     ),
+                    Validity::InvalidName => self.ui.set_output_status(
+                        "All table header entries need a valid name.".to_string(),
+                    ),
                     Validity::Valid => self.ui.set_output_status(String::new()),
                 }
             }
         }
     }
 }
+
+impl QueryDialog {
+    /// Highlights inputs the sql does not use and lists parameters without an input.
+    /// Nothing is checked without a database or if the sql can not be prepared.
+    fn check_parameters(&mut self) {
+        let inputs = self.input_types.state().get().model.get_row_data();
+        let check = match &self.conn {
+            Some(conn) => match sql_parameters(conn, &self.ui.sql) {
+                Ok(parameters) => ParameterCheck::new(&parameters, &inputs),
+                Err(_) => ParameterCheck::default(),
+            },
+            None => ParameterCheck::default(),
+        };
+        self.ui.set_parameter_status(check.to_string());
+        self.input_types
+            .emit(TypeMsg::MarkUnreferenced(check.unreferenced));
+    }
+}
+
+/// The named parameters of the sql without their `:` prefix.
+fn sql_parameters(conn: &Connection, sql: &str) -> rusqlite::Result<Vec<String>> {
+    let stmt = conn.prepare(sql)?;
+    Ok((1..=stmt.parameter_count())
+        .filter_map(|i| stmt.parameter_name(i))
+        .filter_map(|name| name.strip_prefix(':'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ParameterCheck {
+    /// Inputs without a parameter, binding them fails
+    unreferenced: Vec<String>,
+    /// Parameters without an input, they are NULL
+    unbound: Vec<String>,
+}
+
+impl ParameterCheck {
+    /// A period input `p` is bound as `:p` and covers the optional `:p_from` and `:p_to`.
+    /// [`EXCLUDE_REFUNDS_PARAMETER`] is bound without an input.
+    fn new(parameters: &[String], inputs: &RowData) -> Self {
+        let unreferenced = inputs
+            .0
+            .iter()
+            .map(|input| &input.name)
+            .filter(|name| !name.is_empty() && !parameters.contains(name))
+            .cloned()
+            .collect();
+        let unbound = parameters
+            .iter()
+            .filter(|parameter| *parameter != EXCLUDE_REFUNDS_PARAMETER)
+            .filter(|parameter| {
+                !inputs.0.iter().any(|input| {
+                    **parameter == input.name
+                        || (input.ty == ColumnType::Period
+                            && [format!("{}_from", input.name), format!("{}_to", input.name)]
+                                .contains(*parameter))
+                })
+            })
+            .cloned()
+            .collect();
+        ParameterCheck {
+            unreferenced,
+            unbound,
+        }
+    }
+}
+
+impl std::fmt::Display for ParameterCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| format!("':{name}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if !self.unreferenced.is_empty() {
+            write!(
+                f,
+                "The SQL does not use the input(s) {}, executing it will fail.",
+                list(&self.unreferenced)
+            )?;
+        }
+        if !self.unbound.is_empty() {
+            if !self.unreferenced.is_empty() {
+                f.write_str(" ")?;
+            }
+            write!(
+                f,
+                "The parameter(s) {} have no input and are NULL.",
+                list(&self.unbound)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::RowEntry;
+    use crate::schema::testing::empty_db;
+
+    fn inputs(rows: &[(&str, ColumnType)]) -> RowData {
+        RowData(
+            rows.iter()
+                .enumerate()
+                .map(|(id, (name, ty))| RowEntry {
+                    name: name.to_string(),
+                    ty: *ty,
+                    id,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn finds_parameters_of_the_sql() {
+        let parameters = sql_parameters(
+            &empty_db(),
+            "SELECT name FROM Item WHERE unit == :unit AND price > :min OR price < :min;",
+        )
+        .unwrap();
+        assert_eq!(parameters, ["unit", "min"]);
+    }
+
+    #[test]
+    fn cross_checks_inputs_and_parameters() {
+        let parameters = ["unit", "month_from", "missing", "exclude_refunds"].map(String::from);
+        let check = ParameterCheck::new(
+            &parameters,
+            &inputs(&[
+                ("unit", ColumnType::String),
+                ("month", ColumnType::Period),
+                ("unused", ColumnType::Number),
+            ]),
+        );
+        assert_eq!(
+            check,
+            ParameterCheck {
+                // the period itself is always bound
+                unreferenced: vec!["month".to_string(), "unused".to_string()],
+                unbound: vec!["missing".to_string()],
+            }
+        );
+        assert_eq!(
+            check.to_string(),
+            "The SQL does not use the input(s) ':month', ':unused', executing it will fail. The parameter(s) ':missing' have no input and are NULL."
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::analysis::{Analysis, AnalysisMsg, Query};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use relm4::ComponentSender;
+use rusqlite::Connection;
+use std::fmt;
+
+/// What happens to an imported query whose name is already taken
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Resolution {
+    Skip,
+    /// Imported with a free name like `name (2)`
+    Rename,
+    Overwrite,
+}
+
+impl Resolution {
+    pub(crate) const ALL: [Resolution; 3] =
+        [Resolution::Skip, Resolution::Rename, Resolution::Overwrite];
+}
+
+impl TryFrom<u32> for Resolution {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Resolution::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<Resolution> for u32 {
+    fn from(resolution: Resolution) -> Self {
+        Resolution::ALL
+            .iter()
+            .position(|other| *other == resolution)
+            .unwrap_or_default() as u32
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Skip => f.write_str("skip"),
+            Resolution::Rename => f.write_str("rename"),
+            Resolution::Overwrite => f.write_str("overwrite"),
+        }
+    }
+}
+
+struct ImportEntry {
+    name: String,
+    query: Query,
+    /// `None` if the name is free
+    resolution: Option<Resolution>,
+    /// Why the SQL does not fit the connected database
+    problem: Option<String>,
+}
+
+/// The queries read from a queries file, waiting for a decision on the names that are taken.
+pub(crate) struct Import {
+    entries: Vec<ImportEntry>,
+}
+
+impl Import {
+    /// Names taken by an existing or an earlier imported query are renamed by default, unless
+    /// the SQL does not fit the connected database. Those are skipped, so a broken query does
+    /// not replace a working one by accident.
+    pub(crate) fn new(
+        imported: Vec<(String, Query)>,
+        existing: &[(String, Query)],
+        conn: Option<&Connection>,
+    ) -> Self {
+        let mut names: Vec<String> = existing.iter().map(|(name, _)| name.clone()).collect();
+        let entries = imported
+            .into_iter()
+            .map(|(name, query)| {
+                let problem =
+                    conn.and_then(|conn| conn.prepare(&query.sql).err().map(|err| err.to_string()));
+                let resolution = names.contains(&name).then_some(if problem.is_some() {
+                    Resolution::Skip
+                } else {
+                    Resolution::Rename
+                });
+                names.push(name.clone());
+                ImportEntry {
+                    name,
+                    query,
+                    resolution,
+                    problem,
+                }
+            })
+            .collect();
+        Import { entries }
+    }
+
+    pub(crate) fn has_conflicts(&self) -> bool {
+        self.entries.iter().any(|entry| entry.resolution.is_some())
+    }
+
+    /// Changes the resolution of the entry at the index, if its name is taken.
+    pub(crate) fn resolve(&mut self, idx: usize, resolution: Resolution) {
+        if let Some(current) = self
+            .entries
+            .get_mut(idx)
+            .and_then(|entry| entry.resolution.as_mut())
+        {
+            *current = resolution;
+        }
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let conflicts = self
+            .entries
+            .iter()
+            .filter(|entry| entry.resolution.is_some())
+            .count();
+        format!(
+            "{} queries were read, {conflicts} of them have the name of another query.",
+            self.entries.len()
+        )
+    }
+
+    /// The queries with the imported ones added. Existing queries keep their position, so
+    /// overwritten ones are replaced in place.
+    pub(crate) fn apply(
+        self,
+        queries: &[(String, Query)],
+    ) -> (Vec<(String, Query)>, ImportSummary) {
+        let mut queries = queries.to_vec();
+        let mut summary = ImportSummary::default();
+        for entry in self.entries {
+            let existing = queries.iter().position(|(name, _)| *name == entry.name);
+            match (entry.resolution, existing) {
+                (Some(Resolution::Skip), _) => summary.skipped += 1,
+                (Some(Resolution::Overwrite), Some(position)) => {
+                    queries[position].1 = entry.query;
+                    summary.overwritten.push(position);
+                }
+                (Some(Resolution::Rename), Some(_)) => {
+                    let name = free_name(&entry.name, &queries);
+                    queries.push((name, entry.query));
+                    summary.renamed += 1;
+                }
+                _ => {
+                    queries.push((entry.name, entry.query));
+                    summary.added += 1;
+                }
+            }
+        }
+        (queries, summary)
+    }
+}
+
+/// `name (2)`, `name (3)`, … whichever is free first.
+fn free_name(name: &str, queries: &[(String, Query)]) -> String {
+    (2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|candidate| queries.iter().all(|(other, _)| other != candidate))
+        .unwrap()
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ImportSummary {
+    pub(crate) added: usize,
+    pub(crate) renamed: usize,
+    /// Positions of the overwritten queries
+    pub(crate) overwritten: Vec<usize>,
+    pub(crate) skipped: usize,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "added {}, renamed {}, overwrote {}, skipped {} queries",
+            self.added,
+            self.renamed,
+            self.overwritten.len(),
+            self.skipped
+        )
+    }
+}
+
+pub(crate) trait ShowImport {
+    /// Replaces the children with a row per imported query whose name is taken, to choose
+    /// what happens to it.
+    fn show_import(&self, import: Option<&Import>, sender: &ComponentSender<Analysis>);
+}
+
+impl ShowImport for gtk::Box {
+    fn show_import(&self, import: Option<&Import>, sender: &ComponentSender<Analysis>) {
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+        let entries = import.map_or(&[][..], |import| &import.entries);
+        let conflicts = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| Some((idx, entry, entry.resolution?)));
+        for (idx, entry, resolution) in conflicts {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+            let name = gtk::Label::new(Some(&entry.name));
+            name.set_hexpand(true);
+            name.set_xalign(0.0);
+            row.append(&name);
+            if let Some(problem) = &entry.problem {
+                let icon = gtk::Image::from_icon_name("dialog-warning-symbolic");
+                icon.set_tooltip_text(Some(&format!(
+                    "The SQL does not fit the connected database: {problem}"
+                )));
+                row.append(&icon);
+            }
+            let combo = gtk::ComboBoxText::new();
+            for resolution in Resolution::ALL {
+                combo.append(None, &resolution.to_string());
+            }
+            combo.set_active(Some(resolution.into()));
+            let sender = sender.clone();
+            combo.connect_changed(move |combo| {
+                if let Some(resolution) = combo.active().and_then(|idx| idx.try_into().ok()) {
+                    sender.input(AnalysisMsg::ImportResolution(idx, resolution));
+                }
+            });
+            row.append(&combo);
+            self.append(&row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    fn query(sql: &str) -> Query {
+        let mut query = Query::new();
+        query.sql = sql.to_string();
+        query
+    }
+
+    fn names(queries: &[(String, Query)]) -> Vec<(&str, &str)> {
+        queries
+            .iter()
+            .map(|(name, query)| (name.as_str(), query.sql.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn taken_names_are_skipped_renamed_or_overwritten() {
+        let conn = sample_db();
+        let existing = vec![
+            ("stores".to_string(), query("SELECT * FROM Store;")),
+            ("items".to_string(), query("SELECT * FROM Item;")),
+            ("items (2)".to_string(), query("SELECT name FROM Item;")),
+        ];
+        let imported = vec![
+            ("stores".to_string(), query("SELECT name FROM Store;")),
+            ("items".to_string(), query("SELECT price FROM Item;")),
+            ("receipts".to_string(), query("SELECT * FROM Receipt;")),
+            ("receipts".to_string(), query("SELECT * FROM Category;")),
+        ];
+        let mut import = Import::new(imported, &existing, Some(&conn));
+        assert!(import.has_conflicts());
+        assert_eq!(
+            import.summary(),
+            "4 queries were read, 3 of them have the name of another query."
+        );
+        // the second receipts query does not fit the database and is skipped by default
+        assert_eq!(import.entries[3].resolution, Some(Resolution::Skip));
+        import.resolve(0, Resolution::Overwrite);
+        // names that are free have nothing to resolve
+        import.resolve(2, Resolution::Skip);
+
+        let (queries, summary) = import.apply(&existing);
+        assert_eq!(
+            names(&queries),
+            [
+                ("stores", "SELECT name FROM Store;"),
+                ("items", "SELECT * FROM Item;"),
+                ("items (2)", "SELECT name FROM Item;"),
+                ("items (3)", "SELECT price FROM Item;"),
+                ("receipts", "SELECT * FROM Receipt;"),
+            ]
+        );
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 1,
+                renamed: 1,
+                overwritten: vec![0],
+                skipped: 1,
+            }
+        );
+    }
+}
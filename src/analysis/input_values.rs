@@ -1,9 +1,14 @@
-use crate::analysis::{ColumnTypeValue, RowData};
+use crate::analysis::{ColumnTypeValue, Granularity, RowData};
+use crate::combobox::AppendAll;
+use crate::date::{is_relative_date, parse_iso_date, resolve_date, to_iso_date, StorageFormat};
 use relm4::factory::{DynamicIndex, FactoryComponent, FactoryComponentSender, FactoryVecDeque};
+use relm4::gtk::glib::DateTime;
 use relm4::gtk::{self, prelude::*};
 use relm4::{ComponentParts, ComponentSender, SimpleComponent};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 
 #[tracker::track]
 #[derive(Debug)]
@@ -14,20 +19,54 @@ struct Value {
     value: ColumnTypeValue,
 }
 
+#[derive(Debug)]
+enum ValueMsg {
+    Set(ColumnTypeValue),
+    Granularity(Granularity),
+    PeriodFrom(String),
+    PeriodTo(String),
+}
+
 trait SetDateFromString {
     fn set_date_from_string(&self, date: &ColumnTypeValue);
+    fn set_period_from(&self, period: &ColumnTypeValue);
+    fn set_period_to(&self, period: &ColumnTypeValue);
+}
+
+/// Shows the date a relative date input resolves to, or that the input is no date.
+fn date_hint(input: &str) -> String {
+    match resolve_date(input, StorageFormat::Iso) {
+        Ok(date) if is_relative_date(input) => format!("= {date}"),
+        Ok(_) => String::new(),
+        Err(_) => "invalid date".to_string(),
+    }
+}
+
+fn select_iso_date(calendar: &gtk::Calendar, date: &str) {
+    let date = resolve_date(date, StorageFormat::Iso).and_then(|date| parse_iso_date(&date));
+    let date = date.unwrap_or_else(|err| {
+        eprintln!("[input date]{err}");
+        DateTime::now_local().unwrap()
+    });
+    calendar.select_day(&date);
 }
 
 impl SetDateFromString for gtk::Calendar {
     fn set_date_from_string(&self, date: &ColumnTypeValue) {
         if let ColumnTypeValue::Date(date) = date {
-            let mut chunks = date.split('-');
-            let year: i32 = chunks.next().unwrap().parse().unwrap();
-            let month: i32 = chunks.next().unwrap().parse().unwrap();
-            let day: i32 = chunks.next().unwrap().parse().unwrap();
-            self.set_year(year);
-            self.set_month(month - 1);
-            self.set_day(day);
+            select_iso_date(self, date);
+        }
+    }
+
+    fn set_period_from(&self, period: &ColumnTypeValue) {
+        if let ColumnTypeValue::Period(period) = period {
+            select_iso_date(self, &period.from);
+        }
+    }
+
+    fn set_period_to(&self, period: &ColumnTypeValue) {
+        if let ColumnTypeValue::Period(period) = period {
+            select_iso_date(self, &period.to);
         }
     }
 }
@@ -36,7 +75,7 @@ impl SetDateFromString for gtk::Calendar {
 impl FactoryComponent for Value {
     type CommandOutput = ();
     type Init = (String, ColumnTypeValue);
-    type Input = ColumnTypeValue;
+    type Input = ValueMsg;
     type Output = ();
     type ParentInput = InputValueMsg;
     type ParentWidget = gtk::Box;
@@ -48,10 +87,37 @@ impl FactoryComponent for Value {
             gtk::Calendar {
                 #[track(self.changed(Value::value()))]
                 set_date_from_string: &self.value,
-                connect_day_selected[sender, date_button] => move |this| {
-                    let date = this.date().format("%F").unwrap();
-                    date_button.set_label(&date);
-                    sender.input(ColumnTypeValue::Date(date.to_string()));
+                connect_day_selected[date_entry] => move |this| {
+                    let date = to_iso_date(&this.date());
+                    // a relative date stays relative while its day is shown
+                    let shown = resolve_date(&date_entry.text(), StorageFormat::Iso).ok();
+                    if shown.as_deref() != Some(date.as_str()) {
+                        date_entry.set_text(&date);
+                    }
+                },
+            }
+        },
+        #[name(period_from_selector)]
+        gtk::Popover {
+            gtk::Calendar {
+                #[track(self.changed(Value::value()))]
+                set_period_from: &self.value,
+                connect_day_selected[sender, period_from_button] => move |this| {
+                    let date = to_iso_date(&this.date());
+                    period_from_button.set_label(&date);
+                    sender.input(ValueMsg::PeriodFrom(date.to_string()));
+                },
+            }
+        },
+        #[name(period_to_selector)]
+        gtk::Popover {
+            gtk::Calendar {
+                #[track(self.changed(Value::value()))]
+                set_period_to: &self.value,
+                connect_day_selected[sender, period_to_button] => move |this| {
+                    let date = to_iso_date(&this.date());
+                    period_to_button.set_label(&date);
+                    sender.input(ValueMsg::PeriodTo(date.to_string()));
                 },
             }
         },
@@ -72,7 +138,7 @@ impl FactoryComponent for Value {
                         set_margin_end: 2,
                         set_margin_start: 2,
                         connect_changed[sender] => move |this| {
-                            sender.input(ColumnTypeValue::String(this.text().trim().to_string()));
+                            sender.input(ValueMsg::Set(ColumnTypeValue::String(this.text().trim().to_string())));
                         },
                     }
                 },
@@ -89,19 +155,73 @@ impl FactoryComponent for Value {
                         set_margin_end: 2,
                         set_margin_start: 2,
                         connect_changed[sender] => move |this| {
-                            sender.input(ColumnTypeValue::Number(this.value() as i64));
+                            sender.input(ValueMsg::Set(ColumnTypeValue::Number(this.value() as i64)));
                         },
                     }
                 }
                 ColumnTypeValue::Date(d) => {
-                    #[name(date_button)]
-                    gtk::MenuButton {
-                        #[track(self.changed(Value::value()))]
-                        set_label: d,
-                        set_popover: Some(&date_selector),
-                        set_size_request: (150, -1),
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
                         set_margin_end: 2,
                         set_margin_start: 2,
+                        #[name(date_entry)]
+                        gtk::Entry {
+                            #[track(self.changed(Value::value()))]
+                            set_text: d,
+                            set_size_request: (110, -1),
+                            set_tooltip_text: Some("YYYY-MM-DD, or relative to the day the query is executed: today, yesterday, start-of-week, start-of-month, last-month or start-of-year, optionally with an offset like -7d, +2w, -1m or -1y"),
+                            // the text is kept as typed, the date is resolved from it trimmed
+                            connect_changed[sender, date_hint] => move |this| {
+                                let text = this.text().to_string();
+                                date_hint.set_text(&date_hint(&text));
+                                sender.input(ValueMsg::Set(ColumnTypeValue::Date(text)));
+                            },
+                        },
+                        gtk::MenuButton {
+                            set_icon_name: "x-office-calendar-symbolic",
+                            set_popover: Some(&date_selector),
+                        },
+                        #[name(date_hint)]
+                        gtk::Label {
+                            add_css_class: "dim-label",
+                            set_margin_start: 2,
+                            #[track(self.changed(Value::value()))]
+                            set_text: &date_hint(d),
+                        },
+                    }
+                }
+                ColumnTypeValue::Period(p) => {
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_margin_end: 2,
+                        set_margin_start: 2,
+                        gtk::ComboBoxText {
+                            #[track(self.changed(Value::value()))]
+                            append_all_and_select: (
+                                Granularity::ALL.iter().map(ToString::to_string),
+                                Granularity::ALL.iter().position(|g| *g == p.granularity).map(|idx| idx as u32),
+                            ),
+                            connect_changed[sender] => move |this| {
+                                if let Some(idx) = this.active() {
+                                    sender.input(ValueMsg::Granularity(Granularity::ALL[idx as usize]));
+                                }
+                            },
+                        },
+                        #[name(period_from_button)]
+                        gtk::MenuButton {
+                            #[track(self.changed(Value::value()))]
+                            set_label: &p.from,
+                            set_popover: Some(&period_from_selector),
+                        },
+                        gtk::Label {
+                            set_label: "-",
+                        },
+                        #[name(period_to_button)]
+                        gtk::MenuButton {
+                            #[track(self.changed(Value::value()))]
+                            set_label: &p.to,
+                            set_popover: Some(&period_to_selector),
+                        },
                     }
                 }
             }
@@ -122,12 +242,45 @@ impl FactoryComponent for Value {
 
     fn update(&mut self, message: Self::Input, _sender: FactoryComponentSender<Self>) {
         self.reset();
-        self.value = message;
+        match message {
+            ValueMsg::Set(value) => self.value = value,
+            ValueMsg::Granularity(granularity) => {
+                if let ColumnTypeValue::Period(period) = &mut self.value {
+                    period.granularity = granularity;
+                }
+            }
+            ValueMsg::PeriodFrom(from) => {
+                if let ColumnTypeValue::Period(period) = &mut self.value {
+                    period.from = from;
+                }
+            }
+            ValueMsg::PeriodTo(to) => {
+                if let ColumnTypeValue::Period(period) = &mut self.value {
+                    period.to = to;
+                }
+            }
+        }
     }
 }
 
+/// The last used input values of every query by input name
+type SavedValues = HashMap<String, Vec<(String, ColumnTypeValue)>>;
+
+/// Input values used before a database is connected
+const SHARED_INPUT_VALUES_FILE: &str = "./sqlbon_input_values.json";
+
+/// The input values of the queries of a database are kept next to it, like its queries, e.g.
+/// `expenses.db.input_values.json` for `expenses.db`.
+pub(crate) fn input_values_file(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{db_path}.input_values.json"))
+}
+
 pub(crate) struct InputValue {
     data: HashMap<String, Vec<ColumnTypeValue>>,
+    /// Kept across restarts. Values are matched by input name, so they survive edits of the input definition.
+    saved: SavedValues,
+    /// Where [`InputValue::saved`] is written
+    file: PathBuf,
     values: FactoryVecDeque<Value>,
     show: String,
 }
@@ -135,6 +288,11 @@ pub(crate) struct InputValue {
 #[derive(Debug)]
 pub(crate) enum InputValueMsg {
     Replicate(String, RowData),
+    Reset,
+    /// Remembers the current values, even after a restart.
+    Persist,
+    /// Switches to the values saved in the file, e.g. of another database.
+    Load(PathBuf),
 }
 
 #[relm4::component(pub(crate))]
@@ -146,9 +304,18 @@ impl SimpleComponent for InputValue {
 
     view! {
         #[root]
-        #[name(values)]
         gtk::Box {
-           set_orientation: gtk::Orientation::Vertical,
+            set_orientation: gtk::Orientation::Vertical,
+            #[name(values)]
+            gtk::Box {
+               set_orientation: gtk::Orientation::Vertical,
+            },
+            gtk::Button {
+                set_label: "reset inputs",
+                connect_clicked[sender] => move |_| {
+                    sender.input(InputValueMsg::Reset);
+                },
+            },
         }
     }
 
@@ -159,8 +326,11 @@ impl SimpleComponent for InputValue {
     ) -> ComponentParts<Self> {
         let widgets = view_output!();
 
+        let file = PathBuf::from(SHARED_INPUT_VALUES_FILE);
         let model = InputValue {
             data: HashMap::new(),
+            saved: load_input_values(&file),
+            file,
             show: String::new(),
             values: FactoryVecDeque::new(widgets.values.clone(), sender.input_sender()),
         };
@@ -180,10 +350,19 @@ impl InputValue {
                 let mut v = self.values.guard();
                 // ------ save current data -----------
                 let old_name = std::mem::replace(&mut self.show, name.clone());
+                if !old_name.is_empty() {
+                    self.saved.insert(
+                        old_name.clone(),
+                        v.iter()
+                            .map(|row| (row.name.clone(), row.value.clone()))
+                            .collect(),
+                    );
+                }
                 self.data.insert(
                     old_name,
                     v.iter().map(|row_entry| row_entry.value.clone()).collect(),
                 );
+                let saved = self.saved.get(&name);
 
                 // -------- load old data -------------
                 match self.data.entry(name) {
@@ -195,7 +374,16 @@ impl InputValue {
                             .0
                             .into_iter()
                             .map(|row_entry| {
-                                let v_ty: ColumnTypeValue = row_entry.ty.into();
+                                // the input definition may have changed since the values were saved
+                                let v_ty = saved
+                                    .and_then(|saved| {
+                                        saved.iter().find(|(n, value)| {
+                                            *n == row_entry.name
+                                                && value.is_column_type(row_entry.ty)
+                                        })
+                                    })
+                                    .map(|(_, value)| value.clone())
+                                    .unwrap_or_else(|| row_entry.ty.into());
                                 v.push_back((row_entry.name, v_ty.clone()));
                                 v_ty
                             })
@@ -240,6 +428,33 @@ impl InputValue {
                     }
                 }
             }
+            InputValueMsg::Persist => {
+                if !self.show.is_empty() {
+                    self.saved
+                        .insert(self.show.clone(), self.get_input_values());
+                    if let Err(err) = save_input_values(&self.file, &self.saved) {
+                        eprintln!("[save input values]{err:#?}");
+                    }
+                }
+            }
+            InputValueMsg::Load(file) => {
+                // the values of one database are not offered to the queries of another
+                self.saved = load_input_values(&file);
+                self.file = file;
+                self.data.clear();
+                self.show.clear();
+                self.values.guard().clear();
+            }
+            InputValueMsg::Reset => {
+                // the values saved for other queries are left untouched
+                let mut v = self.values.guard();
+                for i in 0..v.len() {
+                    if let Some(value) = v.get_mut(i) {
+                        let default = value.value.column_type().into();
+                        value.set_value(default);
+                    }
+                }
+            }
         }
     }
 
@@ -250,3 +465,58 @@ impl InputValue {
             .collect()
     }
 }
+
+fn save_input_values(path: &Path, values: &SavedValues) -> std::io::Result<()> {
+    let file = File::options()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)?;
+    serde_json::to_writer(file, values)?;
+    Ok(())
+}
+
+/// A missing file has no values.
+fn read_input_values(path: &Path) -> std::io::Result<SavedValues> {
+    match File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(file)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SavedValues::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Values that can't be read are not offered, the queries still run with the default values.
+fn load_input_values(path: &Path) -> SavedValues {
+    read_input_values(path).unwrap_or_else(|err| {
+        eprintln!("[read input values]{err:#?}");
+        SavedValues::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::temp_path;
+
+    #[test]
+    fn input_values_are_saved_per_file() {
+        let path = temp_path("input_values.json");
+        assert!(read_input_values(&path).unwrap().is_empty());
+
+        let mut values = SavedValues::new();
+        values.insert(
+            "Totals".to_string(),
+            vec![(
+                "store".to_string(),
+                ColumnTypeValue::String("Shop".to_string()),
+            )],
+        );
+        save_input_values(&path, &values).unwrap();
+        let read = read_input_values(&path).unwrap();
+        assert!(matches!(
+            read["Totals"].as_slice(),
+            [(name, ColumnTypeValue::String(store))] if name == "store" && store == "Shop"
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+/// Formats the rows as a Markdown table with a header row and a separator row.
+pub(crate) fn markdown_table<'a>(
+    header: impl IntoIterator<Item = &'a str>,
+    rows: &[Vec<String>],
+) -> String {
+    let header: Vec<_> = header.into_iter().map(escape_cell).collect();
+    let mut table = markdown_row(&header);
+    table.push_str(&markdown_row(&vec!["---".to_string(); header.len()]));
+    for row in rows {
+        let row: Vec<_> = row.iter().map(|cell| escape_cell(cell)).collect();
+        table.push_str(&markdown_row(&row));
+    }
+    table
+}
+
+fn markdown_row(cells: &[String]) -> String {
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/// A pipe would end the cell and a line break the row.
+fn escape_cell(cell: &str) -> String {
+    cell.replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_are_escaped() {
+        let rows = vec![
+            vec!["Milk".to_string(), "1990".to_string()],
+            vec!["a|b".to_string(), "two\nlines".to_string()],
+        ];
+        assert_eq!(
+            markdown_table(["name", "price"], &rows),
+            "| name | price |\n| --- | --- |\n| Milk | 1990 |\n| a\\|b | two lines |\n"
+        );
+        assert_eq!(markdown_table(["name"], &[]), "| name |\n| --- |\n");
+    }
+}
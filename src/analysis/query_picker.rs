@@ -0,0 +1,55 @@
+use crate::analysis::Query;
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+
+/// Indices of the queries whose name contains every word of the filter, ignoring case.
+pub(crate) fn matching_queries(queries: &[(String, Query)], filter: &str) -> Vec<usize> {
+    let words: Vec<String> = filter.split_whitespace().map(str::to_lowercase).collect();
+    queries
+        .iter()
+        .enumerate()
+        .filter(|(_, (name, _))| {
+            let name = name.to_lowercase();
+            words.iter().all(|word| name.contains(word.as_str()))
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+pub(crate) trait ShowQueryPicker {
+    /// Replaces the rows with the names of the queries matching the filter, in the order of
+    /// [`matching_queries`].
+    fn show_queries(&self, queries: &[(String, Query)], filter: &str);
+}
+
+impl ShowQueryPicker for gtk::ListBox {
+    fn show_queries(&self, queries: &[(String, Query)], filter: &str) {
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+        for idx in matching_queries(queries, filter) {
+            let label = gtk::Label::new(Some(&queries[idx].0));
+            label.set_xalign(0.0);
+            label.set_margin_top(3);
+            label.set_margin_bottom(3);
+            self.append(&label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_filtered_by_the_words_of_their_name() {
+        let queries: Vec<(String, Query)> = ["Spending per Store", "Items today", "store totals"]
+            .into_iter()
+            .map(|name| (name.to_string(), Query::new()))
+            .collect();
+        assert_eq!(matching_queries(&queries, ""), [0, 1, 2]);
+        assert_eq!(matching_queries(&queries, "STORE"), [0, 2]);
+        assert_eq!(matching_queries(&queries, " tot  store"), [2]);
+        assert_eq!(matching_queries(&queries, "budget"), Vec::<usize>::new());
+    }
+}
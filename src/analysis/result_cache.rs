@@ -0,0 +1,144 @@
+use crate::analysis::{ColumnType, ColumnTypeValue, Query};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// When a query was executed the last time and, if the query caches it, its result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct LastRun {
+    /// Local time, e.g. `2022-12-24 18:30:05`
+    pub(crate) at: String,
+    #[serde(default)]
+    pub(crate) cache: Option<CachedResult>,
+}
+
+/// The rows of a result with the SQL and the state of the database they were read from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct CachedResult {
+    sql: String,
+    /// See [`database_version`]
+    database: String,
+    pub(crate) rows: Vec<Vec<ColumnTypeValue>>,
+}
+
+impl CachedResult {
+    /// `None` if the database is not a file, its changes could not be noticed.
+    pub(crate) fn new(
+        query: &Query,
+        conn: &Connection,
+        rows: Vec<Vec<ColumnTypeValue>>,
+    ) -> Option<Self> {
+        Some(CachedResult {
+            sql: query.sql.clone(),
+            database: database_version(conn)?,
+            rows,
+        })
+    }
+
+    /// Whether the result is still the one of the query. It is not, once the SQL or the
+    /// header of the query or the database changed, or the query got inputs, as the result does
+    /// not note the values it was read with.
+    pub(crate) fn is_valid(&self, query: &Query, conn: &Connection) -> bool {
+        let header = &query.table_header.0;
+        // period columns are read as strings
+        let read_as = |ty| match ty {
+            ColumnType::Period => ColumnType::String,
+            ty => ty,
+        };
+        self.sql == query.sql
+            && query.query_input.0.is_empty()
+            && database_version(conn).as_ref() == Some(&self.database)
+            && self.rows.iter().all(|row| {
+                row.len() == header.len()
+                    && row
+                        .iter()
+                        .zip(header)
+                        .all(|(value, entry)| value.column_type() == read_as(entry.ty))
+            })
+    }
+}
+
+/// Identifies the state of the database file, it changes with every write. `None` if the
+/// database is not a file, e.g. in memory.
+fn database_version(conn: &Connection) -> Option<String> {
+    let path = conn.path()?;
+    let modified = |path: &Path| {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        modified
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|since| since.as_nanos())
+    };
+    let database = modified(path)?;
+    // in WAL mode writes only reach the database file at checkpoints
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    let wal = modified(Path::new(&wal)).unwrap_or_default();
+    Some(format!("{}:{database}:{wal}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{RowData, RowEntry};
+    use crate::schema::testing::{sample_db, temp_path};
+
+    fn cached(query: &Query, database: &str, rows: Vec<Vec<ColumnTypeValue>>) -> CachedResult {
+        CachedResult {
+            sql: query.sql.clone(),
+            database: database.to_string(),
+            rows,
+        }
+    }
+
+    #[test]
+    fn cached_results_are_invalid_after_changes() {
+        let path = temp_path("cache.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE Store (name TEXT);")
+            .unwrap();
+        let database = database_version(&conn).unwrap();
+
+        let mut query = Query::new();
+        query.sql = "SELECT name, 1 FROM Store;".to_string();
+        query.table_header = RowData(vec![
+            RowEntry {
+                name: "name".to_string(),
+                ty: ColumnType::String,
+                id: 0,
+            },
+            RowEntry {
+                name: "count".to_string(),
+                ty: ColumnType::Number,
+                id: 1,
+            },
+        ]);
+        let row = vec![
+            ColumnTypeValue::String("Rema".to_string()),
+            ColumnTypeValue::Number(1),
+        ];
+        assert!(cached(&query, &database, vec![row.clone()]).is_valid(&query, &conn));
+        assert!(!cached(&query, "elsewhere", vec![row.clone()]).is_valid(&query, &conn));
+        // the header no longer fits the rows
+        assert!(!cached(&query, &database, vec![row[..1].to_vec()]).is_valid(&query, &conn));
+        let mut edited = query.clone();
+        edited.table_header.0[1].ty = ColumnType::Date;
+        assert!(!cached(&query, &database, vec![row.clone()]).is_valid(&edited, &conn));
+        edited = query.clone();
+        edited.sql = "SELECT name, 2 FROM Store;".to_string();
+        assert!(!cached(&query, &database, vec![row.clone()]).is_valid(&edited, &conn));
+        edited = query.clone();
+        edited.query_input = RowData(vec![RowEntry {
+            name: "store".to_string(),
+            ty: ColumnType::String,
+            id: 0,
+        }]);
+        assert!(!cached(&query, &database, vec![row]).is_valid(&edited, &conn));
+
+        drop(conn);
+        std::fs::remove_file(path).unwrap();
+        // changes of a database in memory can not be noticed
+        assert!(CachedResult::new(&query, &sample_db(), Vec::new()).is_none());
+    }
+}
@@ -15,7 +15,11 @@ struct Row {
     name: String,
     ty: ColumnType,
     id: usize,
+    column_types: &'static [ColumnType],
     duplicate: bool,
+    invalid: bool,
+    /// The sql does not use the parameter of this row
+    unreferenced: bool,
     up: bool,
     down: bool,
 }
@@ -27,19 +31,41 @@ enum RowMsg {
     Delete(DynamicIndex),
     MoveUp(DynamicIndex),
     MoveDown(DynamicIndex),
+    DragBegin(DynamicIndex),
+    DragEnd,
+    DropOn(DynamicIndex),
 }
 
 impl Row {
-    fn new(name: String, ty: ColumnType, id: usize) -> Self {
+    fn new(name: String, ty: ColumnType, id: usize, column_types: &'static [ColumnType]) -> Self {
         Row {
             name,
             ty,
             id,
+            column_types,
             duplicate: false,
+            invalid: false,
+            unreferenced: false,
             up: true,
             down: true,
         }
     }
+
+    /// Why the name is marked. The border of the css classes may be hard to see depending on
+    /// the theme and means nothing to a screen reader, so this is also shown as text.
+    fn name_problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if self.duplicate {
+            problems.push("duplicate name");
+        }
+        if self.invalid {
+            problems.push("not a parameter name, use letters, digits and '_'");
+        }
+        if self.unreferenced {
+            problems.push("not used by the SQL");
+        }
+        problems
+    }
 }
 
 #[derive(Debug)]
@@ -51,7 +77,7 @@ enum RowValid {
 #[relm4::factory]
 impl FactoryComponent for Row {
     type CommandOutput = ();
-    type Init = (String, ColumnType, usize);
+    type Init = (String, ColumnType, usize, &'static [ColumnType]);
     type Input = RowValid;
     type Output = RowMsg;
     type ParentInput = TypeMsg;
@@ -62,6 +88,30 @@ impl FactoryComponent for Row {
         #[root]
         gtk::Box{
             set_orientation: gtk::Orientation::Horizontal,
+            add_controller = &gtk::DropTarget::new(u64::static_type(), gtk::gdk::DragAction::MOVE) {
+                connect_drop[sender, index] => move |_, _, _, _| {
+                    sender.output(RowMsg::DropOn(index.clone()));
+                    true
+                },
+            },
+            gtk::Label {
+                set_label: "☰",
+                set_margin_end: 4,
+                set_tooltip_text: Some("drag to reorder"),
+                add_controller = &gtk::DragSource {
+                    set_actions: gtk::gdk::DragAction::MOVE,
+                    connect_prepare[index] => move |_, _, _| {
+                        let idx = index.current_index() as u64;
+                        Some(gtk::gdk::ContentProvider::for_value(&idx.to_value()))
+                    },
+                    connect_drag_begin[sender, index] => move |_, _| {
+                        sender.output(RowMsg::DragBegin(index.clone()));
+                    },
+                    connect_drag_end[sender] => move |_, _, _| {
+                        sender.output(RowMsg::DragEnd);
+                    },
+                },
+            },
             #[name(name_entry)]
             gtk::Entry::builder().text(&self.name).build() {
                 connect_changed[sender, index] => move |name_entry| {
@@ -71,11 +121,7 @@ impl FactoryComponent for Row {
             gtk::ComboBoxText {
                 set_size_request: (100, -1),
                 append_all_and_select: (
-                    [
-                        ColumnType::String.to_string(),
-                        ColumnType::Number.to_string(),
-                        ColumnType::Date.to_string(),
-                    ],
+                    self.column_types.iter().map(ToString::to_string),
                     Some(self.ty.into()),
                 ),
                 connect_changed[sender] => move |type_box| {
@@ -122,15 +168,18 @@ impl FactoryComponent for Row {
             RowMsg::Delete(index) => TypeMsg::Delete(index),
             RowMsg::MoveUp(index) => TypeMsg::MoveUp(index),
             RowMsg::MoveDown(index) => TypeMsg::MoveDown(index),
+            RowMsg::DragBegin(index) => TypeMsg::DragBegin(index),
+            RowMsg::DragEnd => TypeMsg::DragEnd,
+            RowMsg::DropOn(index) => TypeMsg::DropOn(index),
         })
     }
 
     fn init_model(
-        (name, ty, id): Self::Init,
+        (name, ty, id, column_types): Self::Init,
         _index: &DynamicIndex,
         _sender: FactoryComponentSender<Self>,
     ) -> Self {
-        Row::new(name, ty, id)
+        Row::new(name, ty, id, column_types)
     }
 
     fn update(&mut self, message: Self::Input, sender: FactoryComponentSender<Self>) {
@@ -153,6 +202,43 @@ impl FactoryComponent for Row {
         } else {
             name_entry.remove_css_class("duplicate-name");
         }
+        if self.invalid {
+            name_entry.add_css_class("invalid-name");
+        } else {
+            name_entry.remove_css_class("invalid-name");
+        }
+        if self.unreferenced {
+            name_entry.add_css_class("unreferenced-name");
+        } else {
+            name_entry.remove_css_class("unreferenced-name");
+        }
+
+        let problems = self.name_problems().join(", ");
+        let icon = if self.duplicate || self.invalid {
+            Some("dialog-error-symbolic")
+        } else if self.unreferenced {
+            Some("dialog-warning-symbolic")
+        } else {
+            None
+        };
+        let position = gtk::EntryIconPosition::Secondary;
+        name_entry.set_icon_from_icon_name(position, icon);
+        if problems.is_empty() {
+            name_entry.set_tooltip_text(None);
+            name_entry.reset_property(gtk::AccessibleProperty::Description);
+        } else {
+            name_entry.set_icon_tooltip_text(position, Some(&problems));
+            name_entry.set_tooltip_text(Some(&problems));
+            name_entry.update_property(&[gtk::accessible::Property::Description(&problems)]);
+        }
+        // an unused parameter is only a warning, the dialog still accepts it
+        if self.duplicate || self.invalid {
+            name_entry.update_state(&[gtk::accessible::State::Invalid(
+                gtk::AccessibleInvalidState::True,
+            )]);
+        } else {
+            name_entry.reset_state(gtk::AccessibleState::Invalid);
+        }
     }
 }
 
@@ -161,16 +247,57 @@ pub(crate) enum Validity {
     NotEnoughRows,
     NotFilled,
     Duplicates,
+    InvalidName,
     Valid,
 }
 
+impl Validity {
+    /// Validity of rows that are all filled. Duplicates take precedence over invalid names.
+    fn filled(has_duplicates: bool, has_invalid_names: bool) -> Self {
+        if has_duplicates {
+            Validity::Duplicates
+        } else if has_invalid_names {
+            Validity::InvalidName
+        } else {
+            Validity::Valid
+        }
+    }
+}
+
+/// Checks that `name` can be used as a named sql parameter (`:name`).
+fn is_parameter_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TypeInit {
+    pub(crate) required_rows: usize,
+    /// The row names are bound as sql parameters and have to be valid identifiers.
+    pub(crate) parameter_names: bool,
+    /// The types a row can choose from. They are selected by their index in this list.
+    pub(crate) column_types: &'static [ColumnType],
+}
+
 pub(crate) struct Type {
     ty: FactoryVecDeque<Row>,
     id_counter: usize,
     is_filled: bool,
     /// This field may only contain a useful value if [`Type::is_filled`] is true
     has_duplicates: bool,
+    /// This field may only contain a useful value if [`Type::is_filled`] is true
+    has_invalid_names: bool,
     required_rows: usize,
+    parameter_names: bool,
+    column_types: &'static [ColumnType],
+    /// The row that is currently dragged. Drops from the rows of another [`Type`] are ignored,
+    /// because this is only set for drags that started here.
+    dragging: Option<DynamicIndex>,
 }
 
 impl Type {
@@ -196,12 +323,19 @@ pub(crate) enum TypeMsg {
     Delete(DynamicIndex),
     MoveUp(DynamicIndex),
     MoveDown(DynamicIndex),
+    DragBegin(DynamicIndex),
+    DragEnd,
+    DropOn(DynamicIndex),
     Replicate(RowData),
+    /// Highlights the rows with these names
+    MarkUnreferenced(Vec<String>),
+    RequiredRows(usize),
 }
 
 trait RestoreMoveValid {
     fn restore_move_valid(&mut self);
     fn check_duplicates(&mut self) -> bool;
+    fn check_parameter_names(&mut self) -> bool;
     fn is_filled(&self) -> bool;
 }
 
@@ -248,6 +382,23 @@ impl RestoreMoveValid for FactoryVecDequeGuard<'_, Row> {
         has_duplicates
     }
 
+    fn check_parameter_names(&mut self) -> bool {
+        let mut has_invalid_names = false;
+        let mut invalid_vec = Vec::new();
+        for (i, row) in self.iter().enumerate() {
+            let name = row.name.trim();
+            let is_invalid = !name.is_empty() && !is_parameter_name(name);
+            if row.invalid != is_invalid {
+                invalid_vec.push((i, is_invalid));
+            }
+            has_invalid_names |= is_invalid;
+        }
+        for (invalid_idx, is_invalid) in invalid_vec {
+            self.get_mut(invalid_idx).unwrap().invalid = is_invalid;
+        }
+        has_invalid_names
+    }
+
     fn is_filled(&self) -> bool {
         self.iter().all(|row| !row.name.trim().is_empty())
     }
@@ -256,13 +407,15 @@ impl RestoreMoveValid for FactoryVecDequeGuard<'_, Row> {
 #[derive(Debug)]
 pub(crate) enum ValidityMsg {
     ValidityChanged(Validity),
+    /// A row was added, removed or renamed. Only sent for rows with parameter names.
+    NamesChanged,
 }
 
 #[relm4::component(pub(crate))]
 impl SimpleComponent for Type {
     type Input = TypeMsg;
     type Output = ValidityMsg;
-    type Init = usize;
+    type Init = TypeInit;
     type Widgets = TypeWidgets;
 
     view! {
@@ -300,7 +453,11 @@ impl SimpleComponent for Type {
             id_counter: 0,
             is_filled: false,
             has_duplicates: false,
-            required_rows: init,
+            has_invalid_names: false,
+            required_rows: init.required_rows,
+            parameter_names: init.parameter_names,
+            column_types: init.column_types,
+            dragging: None,
         };
 
         let widgets = view_output!();
@@ -319,9 +476,26 @@ impl Type {
         let send = |val: Validity| {
             sender.output(ValidityMsg::ValidityChanged(val));
         };
+        if self.parameter_names
+            && matches!(
+                message,
+                TypeMsg::Add
+                    | TypeMsg::AddAbove(_)
+                    | TypeMsg::Delete(_)
+                    | TypeMsg::NameChanged(..)
+                    | TypeMsg::Replicate(_)
+            )
+        {
+            sender.output(ValidityMsg::NamesChanged);
+        }
         match message {
             TypeMsg::Add => {
-                types.push_back((String::new(), ColumnType::String, self.id_counter));
+                types.push_back((
+                    String::new(),
+                    ColumnType::String,
+                    self.id_counter,
+                    self.column_types,
+                ));
                 self.id_counter += 1;
                 types.restore_move_valid();
                 if self.is_filled {
@@ -331,7 +505,15 @@ impl Type {
             }
             TypeMsg::AddAbove(idx) => {
                 let idx = idx.current_index();
-                types.insert(idx, (String::new(), ColumnType::String, self.id_counter));
+                types.insert(
+                    idx,
+                    (
+                        String::new(),
+                        ColumnType::String,
+                        self.id_counter,
+                        self.column_types,
+                    ),
+                );
                 self.id_counter += 1;
                 types.restore_move_valid();
                 if self.is_filled {
@@ -350,32 +532,33 @@ impl Type {
                     // if filled, deleting wont empty a row
 
                     let has_duplicates = types.check_duplicates();
+                    let has_invalid_names = self.parameter_names && types.check_parameter_names();
                     let is_filled = types.is_filled();
 
                     //  n n => do nothing
-                    //  n f => check dup[emit dup/emit valid]
+                    //  n f => check dup/names[emit dup/emit invalid/emit valid]
                     //  f n => impossible
-                    //  f f => if dup: check dup[emit valid if not]
+                    //  f f => if dup/invalid: check dup/names[emit if changed]
                     match (self.is_filled, is_filled) {
                         (false, false) => {}
                         (false, true) => {
-                            if has_duplicates {
-                                send(Validity::Duplicates);
-                                self.has_duplicates = true;
-                            } else {
-                                send(Validity::Valid);
-                                self.has_duplicates = false;
-                            }
+                            send(Validity::filled(has_duplicates, has_invalid_names));
+                            self.has_duplicates = has_duplicates;
+                            self.has_invalid_names = has_invalid_names;
                             self.is_filled = true;
                         }
                         (true, false) => {
                             panic!("deleting a row should not be able to make another row empty.");
                         }
                         (true, true) => {
-                            if self.has_duplicates && !has_duplicates {
-                                send(Validity::Valid);
-                                self.has_duplicates = false;
+                            let validity = Validity::filled(has_duplicates, has_invalid_names);
+                            if Validity::filled(self.has_duplicates, self.has_invalid_names)
+                                != validity
+                            {
+                                send(validity);
                             }
+                            self.has_duplicates = has_duplicates;
+                            self.has_invalid_names = has_invalid_names;
                         }
                     }
                 }
@@ -401,25 +584,57 @@ impl Type {
                     }
                 }
             }
+            TypeMsg::DragBegin(idx) => self.dragging = Some(idx),
+            TypeMsg::DragEnd => self.dragging = None,
+            TypeMsg::DropOn(idx) => {
+                // rows keep their id when moved, so the input values stay associated with them
+                if let Some(source) = self.dragging.take() {
+                    let source_idx = source.current_index();
+                    let target_idx = idx.current_index();
+                    if source_idx != target_idx {
+                        types.move_to(source_idx, target_idx);
+                        types.restore_move_valid();
+                        if self.has_duplicates {
+                            types.check_duplicates();
+                        }
+                    }
+                }
+            }
             TypeMsg::Replicate(row_data) => {
                 self.id_counter = 0;
                 types.clear();
                 for row in row_data.0 {
-                    types.push_back((row.name, row.ty, row.id));
+                    types.push_back((row.name, row.ty, row.id, self.column_types));
                     self.id_counter = max(row.id + 1, self.id_counter);
                 }
                 types.restore_move_valid();
 
                 self.is_filled = types.is_filled();
                 self.has_duplicates = types.check_duplicates();
+                self.has_invalid_names = self.parameter_names && types.check_parameter_names();
                 if types.len() < self.required_rows {
                     send(Validity::NotEnoughRows);
                 } else if !self.is_filled {
                     send(Validity::NotFilled);
-                } else if self.has_duplicates {
-                    send(Validity::Duplicates);
                 } else {
-                    send(Validity::Valid);
+                    send(Validity::filled(
+                        self.has_duplicates,
+                        self.has_invalid_names,
+                    ));
+                }
+            }
+            // validated with the rows of the next query, the dialog is closed while the
+            // settings are changed
+            TypeMsg::RequiredRows(required_rows) => self.required_rows = required_rows,
+            TypeMsg::MarkUnreferenced(names) => {
+                let marks: Vec<_> = types
+                    .iter()
+                    .map(|row| names.iter().any(|name| name == row.name.trim()))
+                    .collect();
+                for (idx, unreferenced) in marks.into_iter().enumerate() {
+                    if types.get(idx).unwrap().unreferenced != unreferenced {
+                        types.get_mut(idx).unwrap().unreferenced = unreferenced;
+                    }
                 }
             }
             TypeMsg::NameChanged(idx, prev_not_empty) => {
@@ -427,6 +642,7 @@ impl Type {
                 let name = &types.get(idx).unwrap().name;
                 let current_not_empty = !name.trim().is_empty();
                 let has_duplicates = types.check_duplicates();
+                let has_invalid_names = self.parameter_names && types.check_parameter_names();
 
                 match (prev_not_empty, current_not_empty) {
                     (false, false) => {
@@ -444,32 +660,24 @@ impl Type {
                         if types.is_filled() {
                             // no other fields empty
                             self.is_filled = true;
-                            if has_duplicates {
-                                // duplicates
-                                send(Validity::Duplicates);
-                                self.has_duplicates = true;
-                            } else {
-                                // valid
-                                send(Validity::Valid);
-                                self.has_duplicates = false;
-                            }
+                            // duplicates, invalid names or valid
+                            send(Validity::filled(has_duplicates, has_invalid_names));
+                            self.has_duplicates = has_duplicates;
+                            self.has_invalid_names = has_invalid_names;
                         }
                     }
                     (true, true) => {
-                        // still filled, but maybe duplicate
+                        // still filled, but maybe duplicate or invalid
                         // it is either, valid or not filled
                         if self.is_filled {
-                            match (self.has_duplicates, has_duplicates) {
-                                (false, true) => {
-                                    send(Validity::Duplicates);
-                                    self.has_duplicates = true;
-                                }
-                                (true, false) => {
-                                    send(Validity::Valid);
-                                    self.has_duplicates = false;
-                                }
-                                _ => {}
+                            let validity = Validity::filled(has_duplicates, has_invalid_names);
+                            if Validity::filled(self.has_duplicates, self.has_invalid_names)
+                                != validity
+                            {
+                                send(validity);
                             }
+                            self.has_duplicates = has_duplicates;
+                            self.has_invalid_names = has_invalid_names;
                         }
                     }
                 }
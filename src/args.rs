@@ -0,0 +1,108 @@
+use crate::tabs::Tab;
+
+pub(crate) const USAGE: &str = "usage: sqlbon [--db <path>] [--settings <path>] \
+    [--tab <dashboard|store|receipt|item|analysis|settings>]";
+
+/// Path of the settings file, if it is not given with `--settings`
+pub(crate) const SETTINGS_ENV: &str = "SQLBON_SETTINGS";
+
+/// Command-line arguments, they take precedence over `sqlbon_settings.json`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Args {
+    /// Connected instead of the database of the settings
+    pub(crate) db: Option<String>,
+    /// Shown instead of the settings tab
+    pub(crate) tab: Option<Tab>,
+    /// Read and written instead of `sqlbon_settings.json`
+    pub(crate) settings: Option<String>,
+}
+
+impl Args {
+    /// Parses the arguments without the program name. Values follow their option as the next
+    /// argument or after `=`.
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (option, value) = match arg.split_once('=') {
+                Some((option, value)) => (option.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let value = || {
+                value
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{option} needs a value"))
+            };
+            match option.as_str() {
+                "--db" => parsed.db = Some(value()?),
+                "--settings" => parsed.settings = Some(value()?),
+                "--tab" => {
+                    let name = value()?;
+                    parsed.tab =
+                        Some(Tab::from_name(&name).ok_or_else(|| format!("unknown tab '{name}'"))?);
+                }
+                _ => return Err(format!("unknown argument '{option}'")),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The settings file of `--settings`, otherwise of the environment variable, otherwise
+    /// `sqlbon_settings.json` in the working directory.
+    pub(crate) fn settings_file(&self, env: Option<String>) -> String {
+        self.settings
+            .clone()
+            .or(env.filter(|path| !path.is_empty()))
+            .unwrap_or_else(|| "sqlbon_settings.json".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn database_and_tab_are_parsed() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+        assert_eq!(
+            parse(&["--db", "path/to.db", "--tab=Item"]),
+            Ok(Args {
+                db: Some("path/to.db".to_string()),
+                tab: Some(Tab::Item),
+                settings: None,
+            })
+        );
+        assert_eq!(parse(&["--db"]), Err("--db needs a value".to_string()));
+        assert_eq!(
+            parse(&["--tab", "items"]),
+            Err("unknown tab 'items'".to_string())
+        );
+        assert_eq!(
+            parse(&["receipts.db"]),
+            Err("unknown argument 'receipts.db'".to_string())
+        );
+    }
+
+    #[test]
+    fn settings_file_prefers_the_command_line() {
+        let args = parse(&["--settings=/tmp/sqlbon.json"]).unwrap();
+        assert_eq!(
+            args.settings_file(Some("/env/sqlbon.json".to_string())),
+            "/tmp/sqlbon.json"
+        );
+        let args = Args::default();
+        assert_eq!(
+            args.settings_file(Some("/env/sqlbon.json".to_string())),
+            "/env/sqlbon.json"
+        );
+        assert_eq!(
+            args.settings_file(Some(String::new())),
+            "sqlbon_settings.json"
+        );
+        assert_eq!(args.settings_file(None), "sqlbon_settings.json");
+    }
+}
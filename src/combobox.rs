@@ -3,6 +3,15 @@ use relm4::gtk;
 
 pub trait AppendAll {
     fn append_all_and_select(&self, data: impl IntoIterator<Item = String>, to_select: Option<u32>);
+    /// Selects the row whose key is `selected_key` instead of a position, so the selection
+    /// follows its value when rows are reordered, inserted or removed.
+    fn append_all_and_select_by<T, K: PartialEq>(
+        &self,
+        data: impl IntoIterator<Item = T>,
+        label_of: impl Fn(&T) -> String,
+        key_of: impl Fn(&T) -> K,
+        selected_key: Option<K>,
+    );
     fn append_all(&self, data: impl IntoIterator<Item = String>);
 }
 
@@ -19,6 +28,18 @@ impl AppendAll for gtk::ComboBoxText {
         self.set_active(to_select);
     }
 
+    fn append_all_and_select_by<T, K: PartialEq>(
+        &self,
+        data: impl IntoIterator<Item = T>,
+        label_of: impl Fn(&T) -> String,
+        key_of: impl Fn(&T) -> K,
+        selected_key: Option<K>,
+    ) {
+        let data: Vec<T> = data.into_iter().collect();
+        let to_select = position_of_key(&data, key_of, selected_key.as_ref());
+        self.append_all_and_select(data.iter().map(label_of), to_select);
+    }
+
     fn append_all(&self, data: impl IntoIterator<Item = String>) {
         self.remove_all();
         for d in data {
@@ -26,3 +47,31 @@ impl AppendAll for gtk::ComboBoxText {
         }
     }
 }
+
+/// The position of the first row whose key is `key`, as a combobox index.
+pub fn position_of_key<T, K: PartialEq>(
+    data: &[T],
+    key_of: impl Fn(&T) -> K,
+    key: Option<&K>,
+) -> Option<u32> {
+    let key = key?;
+    data.iter()
+        .position(|row| key_of(row) == *key)
+        .map(|idx| idx as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_found_by_their_key() {
+        let rows = [(3, "Shop"), (1, "Kiosk"), (7, "Bakery")];
+        let id = |row: &(i64, &str)| row.0;
+        assert_eq!(position_of_key(&rows, id, Some(&1)), Some(1));
+        assert_eq!(position_of_key(&rows, id, Some(&7)), Some(2));
+        // a removed row selects nothing instead of its neighbour
+        assert_eq!(position_of_key(&rows, id, Some(&2)), None);
+        assert_eq!(position_of_key(&rows, id, None), None);
+    }
+}
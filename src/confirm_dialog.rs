@@ -0,0 +1,95 @@
+use crate::Msg;
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+
+pub(crate) struct Dialog {
+    hidden: bool,
+    text: String,
+    secondary_text: String,
+    on_accept: Option<Msg>,
+}
+
+#[derive(Debug)]
+pub(crate) enum DialogMsg {
+    Show {
+        text: String,
+        secondary_text: String,
+        on_accept: Msg,
+    },
+    Accept,
+    Cancel,
+}
+
+#[relm4::component(pub(crate))]
+impl SimpleComponent for Dialog {
+    type Input = DialogMsg;
+    type Output = Msg;
+    type Init = gtk::Window;
+    type Widgets = DialogWidgets;
+
+    view! {
+        #[root]
+        #[name(dialog)]
+        gtk::MessageDialog {
+            set_modal: true,
+            set_transient_for: Some(&parent_window),
+            #[watch]
+            set_visible: !model.hidden,
+            #[watch]
+            set_text: Some(&model.text),
+            #[watch]
+            set_secondary_text: Some(&model.secondary_text),
+            add_button: ("Confirm", gtk::ResponseType::Accept),
+            add_button: ("Cancel", gtk::ResponseType::Cancel),
+            connect_response[sender] => move |_, resp| {
+                sender.input(if resp == gtk::ResponseType::Accept {
+                    DialogMsg::Accept
+                } else {
+                    DialogMsg::Cancel
+                });
+            }
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            DialogMsg::Show {
+                text,
+                secondary_text,
+                on_accept,
+            } => {
+                self.hidden = false;
+                self.text = text;
+                self.secondary_text = secondary_text;
+                self.on_accept = Some(on_accept);
+            }
+            DialogMsg::Accept => {
+                self.hidden = true;
+                if let Some(msg) = self.on_accept.take() {
+                    sender.output(msg);
+                }
+            }
+            DialogMsg::Cancel => {
+                self.hidden = true;
+                self.on_accept = None;
+            }
+        }
+    }
+
+    fn init(
+        parent_window: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Dialog {
+            hidden: true,
+            text: String::new(),
+            secondary_text: String::new(),
+            on_accept: None,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Field separator of exported and imported CSV files.
+/// Some locales use `;`, because `,` is their decimal mark.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Delimiter {
+    #[default]
+    Comma,
+    Semicolon,
+    Tab,
+}
+
+impl Delimiter {
+    pub(crate) const ALL: [Delimiter; 3] = [Delimiter::Comma, Delimiter::Semicolon, Delimiter::Tab];
+
+    pub(crate) fn as_char(&self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Semicolon => ';',
+            Delimiter::Tab => '\t',
+        }
+    }
+
+    /// Keeps the configured delimiter, unless it splits the header line into a single column.
+    /// Then the delimiter that yields the most columns is used instead.
+    pub(crate) fn detect(self, header: &str) -> Delimiter {
+        let column_count = |delimiter: &Delimiter| header.split(delimiter.as_char()).count();
+        if column_count(&self) > 1 {
+            return self;
+        }
+        Delimiter::ALL
+            .into_iter()
+            .filter(|delimiter| column_count(delimiter) > 1)
+            .max_by_key(column_count)
+            .unwrap_or(self)
+    }
+}
+
+impl TryFrom<u32> for Delimiter {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Delimiter::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<Delimiter> for u32 {
+    fn from(delimiter: Delimiter) -> Self {
+        match delimiter {
+            Delimiter::Comma => 0,
+            Delimiter::Semicolon => 1,
+            Delimiter::Tab => 2,
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delimiter::Comma => f.write_str("comma (,)"),
+            Delimiter::Semicolon => f.write_str("semicolon (;)"),
+            Delimiter::Tab => f.write_str("tab"),
+        }
+    }
+}
+
+/// Joins the fields to a line. Fields containing the delimiter, quotes or line breaks are quoted.
+pub(crate) fn write_record<S: AsRef<str>>(
+    fields: impl IntoIterator<Item = S>,
+    delimiter: Delimiter,
+) -> String {
+    let delimiter = delimiter.as_char();
+    let mut line = String::new();
+    for (i, field) in fields.into_iter().enumerate() {
+        let field = field.as_ref();
+        if i > 0 {
+            line.push(delimiter);
+        }
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+    line.push('\n');
+    line
+}
+
+/// Splits the text into records and fields. Quoted fields may contain the delimiter and line breaks.
+/// Empty lines are skipped.
+pub(crate) fn parse_records(text: &str, delimiter: Delimiter) -> Vec<Vec<String>> {
+    let delimiter = delimiter.as_char();
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip_with_special_fields() {
+        let fields = [
+            "plain",
+            "with, comma",
+            "with; semicolon",
+            "with\ttab",
+            "\"quoted\" and \"\"doubled\"\"",
+            "two\nlines",
+            "crlf\r\nline",
+            "",
+        ];
+        for delimiter in Delimiter::ALL {
+            let text = [
+                write_record(fields, delimiter),
+                write_record(["next"], delimiter),
+            ]
+            .concat();
+            assert_eq!(
+                parse_records(&text, delimiter),
+                [
+                    fields.map(ToString::to_string).to_vec(),
+                    vec!["next".to_string()]
+                ],
+                "{delimiter}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_fields_that_need_it_are_quoted() {
+        assert_eq!(
+            write_record(["a", "b,c", "d\"e"], Delimiter::Comma),
+            "a,\"b,c\",\"d\"\"e\"\n"
+        );
+        assert_eq!(write_record(["a", "b,c"], Delimiter::Semicolon), "a;b,c\n");
+    }
+
+    #[test]
+    fn empty_lines_are_skipped() {
+        assert_eq!(
+            parse_records("a,b\r\n\r\nc,d", Delimiter::Comma),
+            [vec!["a", "b"], vec!["c", "d"]]
+        );
+        assert!(parse_records("", Delimiter::Comma).is_empty());
+    }
+
+    #[test]
+    fn delimiter_is_detected_from_the_header() {
+        // the configured delimiter is kept while it splits the header
+        assert_eq!(Delimiter::Comma.detect("name,price"), Delimiter::Comma);
+        assert_eq!(Delimiter::Semicolon.detect("a;b,c"), Delimiter::Semicolon);
+        assert_eq!(
+            Delimiter::Comma.detect("name;price;unit"),
+            Delimiter::Semicolon
+        );
+        assert_eq!(Delimiter::Comma.detect("name\tprice"), Delimiter::Tab);
+        // without a better one the configured delimiter is used
+        assert_eq!(Delimiter::Semicolon.detect("name"), Delimiter::Semicolon);
+    }
+}
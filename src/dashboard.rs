@@ -0,0 +1,342 @@
+use crate::date::iso_date_sql;
+use crate::{amount_text, Total, TotalRow};
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{ComponentParts, ComponentSender, RelmWidgetExt, SimpleComponent};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::rc::Rc;
+
+/// Spending of the current month, counted from the receipt dates.
+pub(crate) struct Metrics {
+    /// `YYYY-MM`
+    month: String,
+    spent: Total,
+    receipts: i64,
+    /// Name and location of the store with the most receipts, and their number
+    top_store: Option<(String, i64)>,
+    /// Name of the item bought most often, and how often. Weighed items count their weight.
+    top_item: Option<(String, f64)>,
+    /// Spending per city and country of the stores, see [`place`]
+    by_city: Vec<(String, Total)>,
+}
+
+impl Metrics {
+    /// Lines with a negative amount are not part of the spending with `exclude_refunds`.
+    pub(crate) fn load(conn: &Connection, exclude_refunds: bool) -> rusqlite::Result<Self> {
+        let month: String =
+            conn.query_row("SELECT strftime('%Y-%m', 'now', 'localtime');", [], |row| {
+                row.get(0)
+            })?;
+        let in_month = format!("strftime('%Y-%m', {}) == ?1", iso_date_sql("Receipt.date"));
+        let spent = conn
+            .prepare(&format!(
+                "SELECT Item.unit, SUM(CAST(ROUND(Item.price * Item.quantity) AS INTEGER)) FROM Item
+                INNER JOIN Receipt ON Item.receipt = Receipt.id
+                WHERE {in_month} AND (NOT ?2 OR Item.price * Item.quantity >= 0)
+                GROUP BY Item.unit ORDER BY Item.unit;"
+            ))?
+            .query_map(params![month, exclude_refunds], |row| {
+                Ok(TotalRow::new(row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let receipts = conn.query_row(
+            &format!("SELECT COUNT(*) FROM Receipt WHERE {in_month};"),
+            params![month],
+            |row| row.get(0),
+        )?;
+        let top_store = conn
+            .query_row(
+                &format!(
+                    "SELECT Store.name || ' (' || Store.location || ')', COUNT(*) c FROM Receipt
+                    INNER JOIN Store ON Receipt.store = Store.id
+                    WHERE {in_month} GROUP BY Store.id ORDER BY c DESC, Store.id ASC LIMIT 1;"
+                ),
+                params![month],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let top_item = conn
+            .query_row(
+                &format!(
+                    "SELECT Item.name, SUM(Item.quantity) c FROM Item
+                    INNER JOIN Receipt ON Item.receipt = Receipt.id
+                    WHERE {in_month} GROUP BY Item.name ORDER BY c DESC, Item.name ASC LIMIT 1;"
+                ),
+                params![month],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let city_rows = conn
+            .prepare(&format!(
+                "SELECT Store.city, Store.country, Item.unit, SUM(CAST(ROUND(Item.price * Item.quantity) AS INTEGER))
+                FROM Item
+                INNER JOIN Receipt ON Item.receipt = Receipt.id
+                INNER JOIN Store ON Receipt.store = Store.id
+                WHERE {in_month} AND (NOT ?2 OR Item.price * Item.quantity >= 0)
+                GROUP BY Store.country, Store.city, Item.unit
+                ORDER BY Store.country, Store.city, Item.unit;"
+            ))?
+            .query_map(params![month, exclude_refunds], |row| {
+                let city: String = row.get(0)?;
+                let country: String = row.get(1)?;
+                Ok((
+                    place(&city, &country),
+                    TotalRow::new(row.get(2)?, row.get(3)?),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut by_city: Vec<(String, Total)> = Vec::new();
+        for (place, row) in city_rows {
+            match by_city.last_mut() {
+                Some((last, total)) if *last == place => total.0.push(row),
+                _ => by_city.push((place, Total(vec![row]))),
+            }
+        }
+        Ok(Metrics {
+            month,
+            spent: Total(spent),
+            receipts,
+            top_store,
+            top_item,
+            by_city,
+        })
+    }
+
+    fn by_city_text(&self, major_units: bool) -> String {
+        if self.by_city.is_empty() {
+            return "-".to_string();
+        }
+        self.by_city
+            .iter()
+            .map(|(place, total)| format!("{place}: {}", amount_text(total, major_units)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn spent_text(&self, major_units: bool) -> String {
+        if self.spent.0.is_empty() {
+            "nothing yet".to_string()
+        } else {
+            amount_text(&self.spent, major_units)
+        }
+    }
+}
+
+/// `city, country` of a store, either may be empty.
+fn place(city: &str, country: &str) -> String {
+    match (city.is_empty(), country.is_empty()) {
+        (false, false) => format!("{city}, {country}"),
+        (false, true) => city.to_string(),
+        (true, false) => country.to_string(),
+        (true, true) => "no city".to_string(),
+    }
+}
+
+/// Shows the name and count of a top store or item.
+fn top_text(top: &Option<(String, impl std::fmt::Display)>, counted: &str) -> String {
+    match top {
+        Some((name, count)) => format!("{name}\n{count} {counted}"),
+        None => "-".to_string(),
+    }
+}
+
+/// A read-only overview of the current month, reloaded when receipts or items are added.
+#[tracker::track]
+pub(crate) struct Dashboard {
+    #[tracker::do_not_track]
+    conn: Option<Rc<Connection>>,
+    #[tracker::no_eq]
+    metrics: Option<Metrics>,
+    major_units: bool,
+    exclude_refunds: bool,
+}
+
+#[derive(Debug)]
+pub(crate) enum DashboardMsg {
+    ConnectDb(Rc<Connection>),
+    Refresh,
+    MajorUnits(bool),
+    ExcludeRefunds(bool),
+}
+
+impl Dashboard {
+    fn load(&mut self) {
+        if let Some(conn) = &self.conn {
+            match Metrics::load(conn, self.exclude_refunds) {
+                Ok(metrics) => self.set_metrics(Some(metrics)),
+                Err(err) => eprintln!("[dashboard]{err:#?}"),
+            }
+        }
+    }
+}
+
+#[relm4::component(pub(crate))]
+impl SimpleComponent for Dashboard {
+    type Input = DashboardMsg;
+    type Output = ();
+    type Init = ();
+    type Widgets = DashboardWidgets;
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_margin_all: 5,
+            set_spacing: 5,
+            set_valign: gtk::Align::Center,
+            gtk::Label {
+                add_css_class: "title-2",
+                #[track(model.changed(Dashboard::metrics()))]
+                set_label: &model.metrics.as_ref().map_or_else(
+                    || "Connect a database to see its spending.".to_string(),
+                    |metrics| format!("This month, {}", metrics.month),
+                ),
+            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 5,
+                set_homogeneous: true,
+                #[track(model.changed(Dashboard::metrics()))]
+                set_visible: model.metrics.is_some(),
+                gtk::Frame {
+                    #[track(model.changed(Dashboard::exclude_refunds()))]
+                    set_label: Some(if model.exclude_refunds { "spent, without refunds" } else { "spent" }),
+                    #[wrap(Some)]
+                    set_child = &gtk::Label {
+                        set_margin_all: 10,
+                        #[track(model.changed(Dashboard::metrics()) || model.changed(Dashboard::major_units()))]
+                        set_label: &model.metrics.as_ref().map(|metrics| metrics.spent_text(model.major_units)).unwrap_or_default(),
+                    },
+                },
+                gtk::Frame {
+                    set_label: Some("receipts"),
+                    #[wrap(Some)]
+                    set_child = &gtk::Label {
+                        set_margin_all: 10,
+                        #[track(model.changed(Dashboard::metrics()))]
+                        set_label: &model.metrics.as_ref().map(|metrics| metrics.receipts.to_string()).unwrap_or_default(),
+                    },
+                },
+                gtk::Frame {
+                    set_label: Some("top store"),
+                    #[wrap(Some)]
+                    set_child = &gtk::Label {
+                        set_margin_all: 10,
+                        #[track(model.changed(Dashboard::metrics()))]
+                        set_label: &model.metrics.as_ref().map(|metrics| top_text(&metrics.top_store, "receipt(s)")).unwrap_or_default(),
+                    },
+                },
+                gtk::Frame {
+                    set_label: Some("top item"),
+                    #[wrap(Some)]
+                    set_child = &gtk::Label {
+                        set_margin_all: 10,
+                        #[track(model.changed(Dashboard::metrics()))]
+                        set_label: &model.metrics.as_ref().map(|metrics| top_text(&metrics.top_item, "bought")).unwrap_or_default(),
+                    },
+                },
+                gtk::Frame {
+                    set_label: Some("by city"),
+                    #[wrap(Some)]
+                    set_child = &gtk::Label {
+                        set_margin_all: 10,
+                        #[track(model.changed(Dashboard::metrics()) || model.changed(Dashboard::major_units()))]
+                        set_label: &model.metrics.as_ref().map(|metrics| metrics.by_city_text(model.major_units)).unwrap_or_default(),
+                    },
+                },
+            },
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        self.reset();
+        match message {
+            DashboardMsg::ConnectDb(conn) => {
+                self.conn = Some(conn);
+                self.load();
+            }
+            DashboardMsg::Refresh => self.load(),
+            DashboardMsg::MajorUnits(major_units) => self.set_major_units(major_units),
+            DashboardMsg::ExcludeRefunds(exclude_refunds) => {
+                self.set_exclude_refunds(exclude_refunds);
+                self.load();
+            }
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Dashboard {
+            conn: None,
+            metrics: None,
+            major_units: false,
+            exclude_refunds: false,
+            tracker: 0,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    #[test]
+    fn metrics_count_the_current_month() {
+        let conn = sample_db();
+        // the sample receipts are from 2022, these two are from today
+        conn.execute_batch(
+            "INSERT INTO Receipt (id, store, date) VALUES
+                (4, 2, date('now', 'localtime')),
+                (5, 2, strftime('%d.%m.%Y', 'now', 'localtime'));
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES
+                ('Coffee', 2, 350, 'EUR', 4),
+                ('Milk', 1, 2190, 'NOK', 5),
+                ('Deposit', 1, -200, 'NOK', 5);",
+        )
+        .unwrap();
+        let metrics = Metrics::load(&conn, false).unwrap();
+        assert_eq!(metrics.spent.to_string(), "700 EUR, 1990 NOK");
+        assert_eq!(metrics.receipts, 2);
+        assert_eq!(metrics.top_store, Some(("Kiosk (Port)".to_string(), 2)));
+        assert_eq!(metrics.top_item, Some(("Coffee".to_string(), 2.0)));
+        assert_eq!(top_text(&metrics.top_item, "bought"), "Coffee\n2 bought");
+        let gross = Metrics::load(&conn, true).unwrap();
+        assert_eq!(gross.spent.to_string(), "700 EUR, 2190 NOK");
+
+        let empty = Metrics::load(&crate::schema::testing::empty_db(), false).unwrap();
+        assert_eq!(empty.spent_text(false), "nothing yet");
+        assert_eq!(empty.top_store, None);
+        assert_eq!(empty.by_city_text(false), "-");
+    }
+
+    #[test]
+    fn spending_is_grouped_by_city() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "UPDATE Store SET city = 'Bergen', country = 'Norway' WHERE id == 2;
+            INSERT INTO Store (id, name, location, country) VALUES (3, 'Bakery', 'Corner', 'Norway');
+            INSERT INTO Receipt (id, store, date) VALUES
+                (4, 1, date('now', 'localtime')),
+                (5, 2, date('now', 'localtime')),
+                (6, 3, date('now', 'localtime'));
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES
+                ('Coffee', 2, 350, 'EUR', 4),
+                ('Milk', 1, 2190, 'NOK', 5),
+                ('Coffee', 1, 400, 'EUR', 5),
+                ('Bun', 1, 300, 'NOK', 6);",
+        )
+        .unwrap();
+        let metrics = Metrics::load(&conn, false).unwrap();
+        assert_eq!(
+            metrics.by_city_text(true),
+            "no city: 7.00 EUR\nNorway: 3.00 NOK\nBergen, Norway: 4.00 EUR, 21.90 NOK"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use relm4::gtk::glib::{DateTime, GString};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How receipt dates are written to the database. Dates in either format are read.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum StorageFormat {
+    /// `YYYY-MM-DD`, understood by the date functions of SQLite and sorted correctly as text
+    #[default]
+    Iso,
+    /// `DD.MM.YYYY`, expected by some other tools
+    DayMonthYear,
+}
+
+impl StorageFormat {
+    pub(crate) const ALL: [StorageFormat; 2] = [StorageFormat::Iso, StorageFormat::DayMonthYear];
+
+    fn format(self, day: Day) -> String {
+        match self {
+            StorageFormat::Iso => day.to_string(),
+            StorageFormat::DayMonthYear => {
+                format!("{:02}.{:02}.{:04}", day.day, day.month, day.year)
+            }
+        }
+    }
+}
+
+impl TryFrom<u32> for StorageFormat {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        StorageFormat::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<StorageFormat> for u32 {
+    fn from(format: StorageFormat) -> Self {
+        match format {
+            StorageFormat::Iso => 0,
+            StorageFormat::DayMonthYear => 1,
+        }
+    }
+}
+
+impl fmt::Display for StorageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageFormat::Iso => f.write_str("YYYY-MM-DD"),
+            StorageFormat::DayMonthYear => f.write_str("DD.MM.YYYY"),
+        }
+    }
+}
+
+/// A date that is not of the form `YYYY-MM-DD` or does not exist.
+#[derive(Debug)]
+pub(crate) struct InvalidDate(pub(crate) String);
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a valid YYYY-MM-DD date", self.0)
+    }
+}
+
+/// Formats the date as `YYYY-MM-DD`, the default storage format.
+pub(crate) fn to_iso_date(date: &DateTime) -> GString {
+    date.format("%F").unwrap()
+}
+
+/// Formats the date for the database in the storage format.
+pub(crate) fn to_stored_date(date: &DateTime, format: StorageFormat) -> String {
+    format.format(Day {
+        year: date.year(),
+        month: date.month() as u32,
+        day: date.day_of_month() as u32,
+    })
+}
+
+/// Converts a date in either storage format to the given one, `None` if it is no valid date.
+pub(crate) fn convert_date(date: &str, format: StorageFormat) -> Option<String> {
+    Day::parse(date).map(|day| format.format(day))
+}
+
+/// An SQL expression of the date column as `YYYY-MM-DD`, whichever format it is stored in,
+/// e.g. for `strftime` or to sort by it.
+pub(crate) fn iso_date_sql(column: &str) -> String {
+    format!(
+        "(CASE WHEN {column} LIKE '__.__.____%' THEN substr({column}, 7, 4) || '-' || substr({column}, 4, 2) || '-' || substr({column}, 1, 2) ELSE {column} END)"
+    )
+}
+
+/// Formats a stored date for display, e.g. with `%-d %B %Y` as "3 May 2024" in the language of
+/// the user. The stored date is shown if the format is empty or invalid.
+pub(crate) fn display_date(stored_date: &str, format: &str) -> String {
+    if format.trim().is_empty() {
+        return stored_date.to_string();
+    }
+    Day::parse(stored_date)
+        .and_then(|day| {
+            DateTime::from_local(day.year, day.month as i32, day.day as i32, 0, 0, 0.0).ok()
+        })
+        .and_then(|date| date.format(format).ok())
+        .map_or_else(|| stored_date.to_string(), |date| date.to_string())
+}
+
+/// Parses a `YYYY-MM-DD` date. A trailing time (`YYYY-MM-DD HH:MM:SS`) is ignored.
+pub(crate) fn parse_iso_date(date: &str) -> Result<DateTime, InvalidDate> {
+    let invalid = || InvalidDate(date.to_string());
+    let day_part = date
+        .trim()
+        .split(|c: char| c == ' ' || c == 'T')
+        .next()
+        .ok_or_else(invalid)?;
+    let mut chunks = day_part.split('-');
+    let mut next_number = || -> Result<i32, InvalidDate> {
+        chunks
+            .next()
+            .and_then(|chunk| chunk.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let year = next_number()?;
+    let month = next_number()?;
+    let day = next_number()?;
+    if chunks.next().is_some() {
+        return Err(invalid());
+    }
+    DateTime::from_local(year, month, day, 0, 0, 0.0).map_err(|_| invalid())
+}
+
+/// A calendar day without the time zone handling of [`DateTime`], for date arithmetic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Day {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Day {
+    /// Parses `YYYY-MM-DD` or `DD.MM.YYYY`, a trailing time is ignored. The day has to exist.
+    fn parse(date: &str) -> Option<Self> {
+        let day_part = date.trim().split([' ', 'T']).next()?;
+        let numbers = |separator| {
+            day_part
+                .split(separator)
+                .map(|number: &str| number.parse().ok())
+                .collect::<Option<Vec<u32>>>()
+        };
+        let (year, month, day) = match (numbers('-'), numbers('.')) {
+            (Some(numbers), _) => match numbers[..] {
+                [year, month, day] => (year, month, day),
+                _ => return None,
+            },
+            (_, Some(numbers)) => match numbers[..] {
+                [day, month, year] => (year, month, day),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let year = i32::try_from(year).ok()?;
+        let valid =
+            (1..=12).contains(&month) && (1..=Day::days_in_month(year, month)).contains(&day);
+        valid.then_some(Day { year, month, day })
+    }
+
+    fn today() -> Self {
+        let now = DateTime::now_local().unwrap();
+        Day {
+            year: now.year(),
+            month: now.month() as u32,
+            day: now.day_of_month() as u32,
+        }
+    }
+
+    fn days_in_month(year: i32, month: u32) -> u32 {
+        match month {
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// Days since 1970-01-01, see <http://howardhinnant.github.io/date_algorithms.html>
+    fn to_days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    fn from_days(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Day {
+            year: year as i32,
+            month,
+            day,
+        }
+    }
+
+    fn add_days(self, days: i64) -> Self {
+        Day::from_days(self.to_days() + days)
+    }
+
+    /// The day is clamped to the length of the month, so a month after 01-31 is 02-28.
+    fn add_months(self, months: i64) -> Self {
+        let month_index = i64::from(self.year) * 12 + i64::from(self.month) - 1 + months;
+        let year = month_index.div_euclid(12) as i32;
+        let month = month_index.rem_euclid(12) as u32 + 1;
+        Day {
+            year,
+            month,
+            day: self.day.min(Day::days_in_month(year, month)),
+        }
+    }
+
+    /// 0 for monday
+    fn weekday(self) -> i64 {
+        // 1970-01-01 was a thursday
+        (self.to_days() + 3).rem_euclid(7)
+    }
+}
+
+impl fmt::Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Gets the day a relative date stands for from today.
+type RelativeDay = fn(Day) -> Day;
+
+/// Names of relative dates
+const RELATIVE_DAYS: [(&str, RelativeDay); 6] = [
+    ("today", |today| today),
+    ("yesterday", |today| today.add_days(-1)),
+    ("start-of-week", |today| today.add_days(-today.weekday())),
+    ("start-of-month", |today| Day { day: 1, ..today }),
+    ("last-month", |today| Day { day: 1, ..today }.add_months(-1)),
+    ("start-of-year", |today| Day {
+        month: 1,
+        day: 1,
+        ..today
+    }),
+];
+
+/// Resolves a date relative to `today`: one of [`RELATIVE_DAYS`], an offset like `-7d`, `+2w`,
+/// `-1m` or `-1y`, or both, e.g. `start-of-month-1m`. Weeks start on monday.
+fn relative_day(input: &str, today: Day) -> Option<Day> {
+    let input = input.trim().to_lowercase();
+    let (base, offset) = match RELATIVE_DAYS.iter().find_map(|(name, day)| {
+        input
+            .strip_prefix(name)
+            .map(|rest| (day(today), rest.trim()))
+    }) {
+        Some((base, "")) => return Some(base),
+        Some(relative) => relative,
+        None => (today, input.as_str()),
+    };
+    let (sign, offset) = if let Some(offset) = offset.strip_prefix('+') {
+        (1, offset.trim_start())
+    } else if let Some(offset) = offset.strip_prefix('-') {
+        (-1, offset.trim_start())
+    } else {
+        return None;
+    };
+    let unit = offset.chars().last()?;
+    let amount: i64 = offset[..offset.len() - unit.len_utf8()].parse().ok()?;
+    let amount = sign * amount;
+    match unit {
+        'd' => Some(base.add_days(amount)),
+        'w' => Some(base.add_days(amount * 7)),
+        'm' => Some(base.add_months(amount)),
+        'y' => Some(base.add_months(amount * 12)),
+        _ => None,
+    }
+}
+
+/// Whether the date input is relative to the day it is used, see [`relative_day`].
+pub(crate) fn is_relative_date(input: &str) -> bool {
+    Day::parse(input).is_none() && relative_day(input, Day::today()).is_some()
+}
+
+/// Resolves a date input to the storage format. It is either a date in a storage format or a
+/// date relative to today, see [`relative_day`]. Surrounding whitespace is ignored.
+pub(crate) fn resolve_date(input: &str, format: StorageFormat) -> Result<String, InvalidDate> {
+    let input = input.trim();
+    Day::parse(input)
+        .or_else(|| relative_day(input, Day::today()))
+        .map(|day| format.format(day))
+        .ok_or_else(|| InvalidDate(input.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_dates_are_resolved() {
+        let today = Day {
+            year: 2024,
+            month: 3,
+            day: 31,
+        };
+        let resolve = |input| relative_day(input, today).map(|day| day.to_string());
+        assert_eq!(resolve("today").as_deref(), Some("2024-03-31"));
+        assert_eq!(resolve("Yesterday").as_deref(), Some("2024-03-30"));
+        assert_eq!(resolve("-7d").as_deref(), Some("2024-03-24"));
+        assert_eq!(resolve("+1d").as_deref(), Some("2024-04-01"));
+        assert_eq!(resolve("-1m").as_deref(), Some("2024-02-29"));
+        assert_eq!(resolve("-1y").as_deref(), Some("2023-03-31"));
+        assert_eq!(resolve("start-of-week").as_deref(), Some("2024-03-25"));
+        assert_eq!(resolve("start-of-month").as_deref(), Some("2024-03-01"));
+        assert_eq!(resolve("last-month").as_deref(), Some("2024-02-01"));
+        assert_eq!(resolve("start-of-year - 2w").as_deref(), Some("2023-12-18"));
+        assert_eq!(resolve(""), None);
+        assert_eq!(resolve("7d"), None);
+        assert_eq!(resolve("-7x"), None);
+        assert_eq!(resolve("next week"), None);
+        // inputs are resolved as typed, with spaces around them
+        assert_eq!(
+            resolve_date(" 2024-05-03 ", StorageFormat::DayMonthYear).unwrap(),
+            "03.05.2024"
+        );
+        assert_eq!(
+            resolve_date(" -7x ", StorageFormat::Iso).unwrap_err().0,
+            "-7x"
+        );
+    }
+
+    #[test]
+    fn stored_dates_round_trip_in_both_formats() {
+        for (iso, dotted) in [
+            ("2024-05-03", "03.05.2024"),
+            ("2024-02-29", "29.02.2024"),
+            ("1999-12-31", "31.12.1999"),
+        ] {
+            assert_eq!(
+                convert_date(iso, StorageFormat::DayMonthYear).as_deref(),
+                Some(dotted)
+            );
+            assert_eq!(
+                convert_date(dotted, StorageFormat::Iso).as_deref(),
+                Some(iso)
+            );
+            assert_eq!(convert_date(iso, StorageFormat::Iso).as_deref(), Some(iso));
+        }
+        assert_eq!(
+            convert_date("2024-05-03 10:00:00", StorageFormat::DayMonthYear).as_deref(),
+            Some("03.05.2024")
+        );
+        for invalid in [
+            "",
+            "2023-02-29",
+            "2024-13-01",
+            "32.01.2024",
+            "2024-05",
+            "03.05.2024.1",
+            "2024/05/03",
+            "-1-05-03",
+        ] {
+            assert_eq!(convert_date(invalid, StorageFormat::Iso), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn display_date_uses_the_format() {
+        assert_eq!(display_date("2024-05-03", "%d.%m.%Y"), "03.05.2024");
+        assert_eq!(display_date("03.05.2024", "%F"), "2024-05-03");
+        assert_eq!(display_date("2024-05-03", ""), "2024-05-03");
+        assert_eq!(display_date("not a date", "%d.%m.%Y"), "not a date");
+    }
+}
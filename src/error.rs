@@ -0,0 +1,119 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// A failure of an action of the user, shown by the error dialog of the main window.
+#[derive(Debug)]
+pub(crate) enum AppError {
+    Db(rusqlite::Error),
+    Json(serde_json::Error),
+    Io(io::Error),
+    /// The file dialog could not be shown
+    Dialog(native_dialog::Error),
+    /// Already explained in words, e.g. an invalid record of an imported file
+    Message(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Db(err) => write!(f, "The database reported an error: {err}"),
+            AppError::Json(err) => write!(f, "The file is not valid JSON: {err}"),
+            AppError::Io(err) => write!(f, "The file could not be accessed: {err}"),
+            AppError::Dialog(err) => write!(f, "The file dialog could not be shown: {err}"),
+            AppError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Db(err) => Some(err),
+            AppError::Json(err) => Some(err),
+            AppError::Io(err) => Some(err),
+            AppError::Dialog(err) => Some(err),
+            AppError::Message(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        AppError::Db(err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Json(err)
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(err: io::Error) -> Self {
+        AppError::Io(err)
+    }
+}
+
+impl From<native_dialog::Error> for AppError {
+    fn from(err: native_dialog::Error) -> Self {
+        AppError::Dialog(err)
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Message(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Message(message.to_string())
+    }
+}
+
+/// Shows the failures of user actions. The main window shows them in the error dialog, the
+/// analysis tab below its query.
+pub(crate) trait Report {
+    fn show_error(&mut self, message: String);
+
+    /// Logs the error and shows it. `action` completes "Could not …".
+    fn report(&mut self, action: &str, err: impl Into<AppError>) {
+        let err = err.into();
+        eprintln!("[{action}]{err:#?}");
+        self.show_error(format!("Could not {action}.\n{err}"));
+    }
+
+    /// Reports a file dialog that could not be shown, as if no file was chosen.
+    fn dialog_failed(&mut self, err: native_dialog::Error) -> Option<PathBuf> {
+        self.report("choose a file", err);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_keep_their_cause() {
+        let err = AppError::from(rusqlite::Error::QueryReturnedNoRows);
+        assert_eq!(
+            err.to_string(),
+            "The database reported an error: Query returned no rows"
+        );
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err = AppError::from(serde_json::from_str::<u32>("x").unwrap_err());
+        assert!(err.to_string().starts_with("The file is not valid JSON: "));
+
+        let err = AppError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(err.to_string(), "The file could not be accessed: gone");
+
+        let err = AppError::from("Record 2 has an invalid price.");
+        assert_eq!(err.to_string(), "Record 2 has an invalid price.");
+        assert!(std::error::Error::source(&err).is_none());
+    }
+}
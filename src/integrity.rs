@@ -0,0 +1,227 @@
+use crate::unit::Unit;
+use rusqlite::{params, Connection};
+use std::fmt;
+
+/// Selects items that reference a missing receipt, see [`Issue::MissingReceipt`].
+const ORPHANED_ITEMS: &str = "receipt NOT IN (SELECT id FROM Receipt)";
+
+/// What happens to items whose receipt is missing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum OrphanCleanup {
+    Delete,
+    /// Moves the items to the receipt with the id
+    Reassign(i64),
+}
+
+/// A finding of [`check_integrity`].
+#[derive(Debug, PartialEq)]
+pub(crate) enum Issue {
+    MissingStore {
+        receipt_id: i64,
+        store_id: i64,
+    },
+    MissingReceipt {
+        item_id: i64,
+        receipt_id: i64,
+    },
+    UnknownUnit {
+        item_id: i64,
+        unit: String,
+    },
+    /// Weighed items may have a fractional quantity, but every quantity has to be positive.
+    InvalidQuantity {
+        item_id: i64,
+        quantity: f64,
+    },
+    /// Single items may be negative (discounts, deposits), but a receipt in total should not.
+    NegativeTotal {
+        receipt_id: i64,
+        unit: String,
+        total: i64,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingStore {
+                receipt_id,
+                store_id,
+            } => write!(
+                f,
+                "Receipt #{receipt_id} references the missing store #{store_id}."
+            ),
+            Issue::MissingReceipt {
+                item_id,
+                receipt_id,
+            } => write!(
+                f,
+                "Item #{item_id} references the missing receipt #{receipt_id}."
+            ),
+            Issue::UnknownUnit { item_id, unit } => {
+                write!(f, "Item #{item_id} has the unknown unit '{unit}'.")
+            }
+            Issue::InvalidQuantity { item_id, quantity } => {
+                write!(f, "Item #{item_id} has the quantity {quantity}.")
+            }
+            Issue::NegativeTotal {
+                receipt_id,
+                unit,
+                total,
+            } => write!(
+                f,
+                "Receipt #{receipt_id} has a negative total of {total} {unit}."
+            ),
+        }
+    }
+}
+
+/// Runs diagnostic queries that find rows the app would not have created itself,
+/// e.g. after imports or manual edits of the database.
+pub(crate) fn check_integrity(conn: &Connection) -> rusqlite::Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+
+    let mut query = conn.prepare(
+        "SELECT id, store FROM Receipt WHERE store NOT IN (SELECT id FROM Store) ORDER BY id ASC;",
+    )?;
+    for issue in query.query_map([], |row| {
+        Ok(Issue::MissingStore {
+            receipt_id: row.get(0)?,
+            store_id: row.get(1)?,
+        })
+    })? {
+        issues.push(issue?);
+    }
+
+    let mut query = conn.prepare(&format!(
+        "SELECT id, receipt FROM Item WHERE {ORPHANED_ITEMS} ORDER BY id ASC;"
+    ))?;
+    for issue in query.query_map([], |row| {
+        Ok(Issue::MissingReceipt {
+            item_id: row.get(0)?,
+            receipt_id: row.get(1)?,
+        })
+    })? {
+        issues.push(issue?);
+    }
+
+    let mut query = conn.prepare("SELECT id, unit FROM Item ORDER BY id ASC;")?;
+    for row in query.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))? {
+        let (item_id, unit) = row?;
+        if Unit::from_str(&unit).is_err() {
+            issues.push(Issue::UnknownUnit { item_id, unit });
+        }
+    }
+
+    let mut query =
+        conn.prepare("SELECT id, quantity FROM Item WHERE quantity <= 0 ORDER BY id ASC;")?;
+    for issue in query.query_map([], |row| {
+        Ok(Issue::InvalidQuantity {
+            item_id: row.get(0)?,
+            quantity: row.get(1)?,
+        })
+    })? {
+        issues.push(issue?);
+    }
+
+    let mut query = conn.prepare(
+        "SELECT receipt, unit, SUM(CAST(ROUND(price * quantity) AS INTEGER)) total FROM Item GROUP BY receipt, unit HAVING total < 0 ORDER BY receipt ASC;",
+    )?;
+    for issue in query.query_map([], |row| {
+        Ok(Issue::NegativeTotal {
+            receipt_id: row.get(0)?,
+            unit: row.get(1)?,
+            total: row.get(2)?,
+        })
+    })? {
+        issues.push(issue?);
+    }
+
+    Ok(issues)
+}
+
+pub(crate) fn count_orphaned_items(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM Item WHERE {ORPHANED_ITEMS};"),
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Deletes or reassigns all items whose receipt is missing. Returns the number of changed items.
+pub(crate) fn clean_up_orphaned_items(
+    conn: &Connection,
+    cleanup: OrphanCleanup,
+) -> rusqlite::Result<usize> {
+    match cleanup {
+        OrphanCleanup::Delete => {
+            conn.execute(&format!("DELETE FROM Item WHERE {ORPHANED_ITEMS};"), [])
+        }
+        OrphanCleanup::Reassign(receipt_id) => conn.execute(
+            &format!(
+                "UPDATE Item SET receipt = ?1, updated_at = CURRENT_TIMESTAMP WHERE {ORPHANED_ITEMS};"
+            ),
+            params![receipt_id],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    #[test]
+    fn sample_db_has_no_issues() {
+        assert_eq!(check_integrity(&sample_db()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn finds_unknown_units_and_invalid_quantities() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "INSERT INTO Item (id, name, quantity, price, unit, receipt) VALUES (10, 'Tea', 0, 300, 'XYZ', 1);",
+        )
+        .unwrap();
+        assert_eq!(
+            check_integrity(&conn).unwrap(),
+            [
+                Issue::UnknownUnit {
+                    item_id: 10,
+                    unit: "XYZ".to_string()
+                },
+                Issue::InvalidQuantity {
+                    item_id: 10,
+                    quantity: 0.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn orphaned_items_are_deleted_or_reassigned() {
+        let conn = sample_db();
+        // like another tool that does not enforce foreign keys
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+            INSERT INTO Item (name, price, unit, receipt) VALUES ('Tea', 300, 'NOK', 10), ('Cake', 900, 'NOK', 11);",
+        )
+        .unwrap();
+        assert_eq!(count_orphaned_items(&conn).unwrap(), 2);
+        assert_eq!(
+            clean_up_orphaned_items(&conn, OrphanCleanup::Reassign(3)).unwrap(),
+            2
+        );
+        assert_eq!(count_orphaned_items(&conn).unwrap(), 0);
+        assert_eq!(check_integrity(&conn).unwrap(), Vec::new());
+
+        conn.execute_batch("DELETE FROM Receipt WHERE id == 3;")
+            .unwrap();
+        assert_eq!(count_orphaned_items(&conn).unwrap(), 4);
+        assert_eq!(
+            clean_up_orphaned_items(&conn, OrphanCleanup::Delete).unwrap(),
+            4
+        );
+        assert_eq!(count_orphaned_items(&conn).unwrap(), 0);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::csv;
+use crate::unit::format_amount;
+use crate::{amount_text, Total, TotalRow};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use rusqlite::{params, Connection};
+use std::fmt;
+
+/// What the items of a receipt are grouped by in the item list and its export.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) enum GroupBy {
+    /// Items without a category are put together in one group
+    #[default]
+    Category,
+    /// The store of the receipt of the items
+    Store,
+}
+
+impl GroupBy {
+    pub(crate) const ALL: [GroupBy; 2] = [GroupBy::Category, GroupBy::Store];
+
+    fn column(&self) -> &'static str {
+        match self {
+            GroupBy::Category => "COALESCE(Item.category, '')",
+            GroupBy::Store => "Store.name",
+        }
+    }
+}
+
+impl TryFrom<u32> for GroupBy {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        GroupBy::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<GroupBy> for u32 {
+    fn from(group_by: GroupBy) -> Self {
+        match group_by {
+            GroupBy::Category => 0,
+            GroupBy::Store => 1,
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupBy::Category => f.write_str("category"),
+            GroupBy::Store => f.write_str("store"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct GroupedItem {
+    name: String,
+    quantity: f64,
+    /// Per unit, in minor units of `unit`
+    price: i64,
+    unit: String,
+}
+
+/// Items of a receipt with the same value of the grouped column and their subtotal.
+pub(crate) struct GroupTotal {
+    /// Empty for the items without a category
+    group: String,
+    /// Per unit like the total of the receipt, a group may have items of several units
+    total: Total,
+    items: Vec<GroupedItem>,
+}
+
+impl GroupTotal {
+    fn title(&self) -> &str {
+        if self.group.is_empty() {
+            "without category"
+        } else {
+            &self.group
+        }
+    }
+}
+
+/// The items of the receipt grouped by the column, the groups ordered by it. With
+/// `exclude_refunds`, lines with a negative amount are listed, but left out of the subtotals like
+/// out of the total of the receipt.
+pub(crate) fn grouped_totals(
+    conn: &Connection,
+    receipt_id: i64,
+    group_by: GroupBy,
+    exclude_refunds: bool,
+) -> rusqlite::Result<Vec<GroupTotal>> {
+    let column = group_by.column();
+    let mut query = conn.prepare(&format!(
+        "SELECT {column}, Item.name, Item.quantity, Item.price, Item.unit FROM Item
+        INNER JOIN Receipt ON Item.receipt == Receipt.id
+        INNER JOIN Store ON Receipt.store == Store.id
+        WHERE Item.receipt == ?1 ORDER BY {column} COLLATE NOCASE ASC, Item.id ASC;"
+    ))?;
+    let rows = query.query_map(params![receipt_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            GroupedItem {
+                name: row.get(1)?,
+                quantity: row.get(2)?,
+                price: row.get(3)?,
+                unit: row.get(4)?,
+            },
+        ))
+    })?;
+
+    let mut groups: Vec<GroupTotal> = Vec::new();
+    for row in rows {
+        let (group, item) = row?;
+        // rounded like the lines of the total of the receipt
+        let line = (item.price as f64 * item.quantity).round() as i64;
+        if groups.last().map(|last| &last.group) != Some(&group) {
+            groups.push(GroupTotal {
+                group,
+                total: Total::new(),
+                items: Vec::new(),
+            });
+        }
+        let current = groups.last_mut().unwrap();
+        let line = if exclude_refunds && line < 0 { 0 } else { line };
+        match current
+            .total
+            .0
+            .iter_mut()
+            .find(|row| row.unit() == item.unit)
+        {
+            Some(row) => row.add(line),
+            None => current.total.0.push(TotalRow::new(item.unit.clone(), line)),
+        }
+        current.items.push(item);
+    }
+    Ok(groups)
+}
+
+pub(crate) trait ShowItemGroups {
+    /// Replaces the model with a row per group, its items as children.
+    fn show_item_groups(&self, groups: &[GroupTotal], major_units: bool);
+}
+
+impl ShowItemGroups for gtk::TreeView {
+    fn show_item_groups(&self, groups: &[GroupTotal], major_units: bool) {
+        let store = gtk::TreeStore::new(&[gtk::glib::Type::STRING, gtk::glib::Type::STRING]);
+        for group in groups {
+            let parent = store.insert_with_values(
+                None,
+                None,
+                &[
+                    (0, &group.title()),
+                    (1, &amount_text(&group.total, major_units)),
+                ],
+            );
+            for item in &group.items {
+                let price = TotalRow::new(item.unit.clone(), item.price);
+                store.insert_with_values(
+                    Some(&parent),
+                    None,
+                    &[
+                        (0, &item.name),
+                        (
+                            1,
+                            &format!("{} × {}", item.quantity, amount_text(&price, major_units)),
+                        ),
+                    ],
+                );
+            }
+        }
+        self.set_model(Some(&store));
+        self.expand_all();
+    }
+}
+
+/// The grouped items as CSV with a subtotal line per group and unit, for the receipt summary.
+/// Amounts are written like they are shown.
+pub(crate) fn grouped_csv(
+    groups: &[GroupTotal],
+    delimiter: csv::Delimiter,
+    major_units: bool,
+) -> String {
+    let mut text = csv::write_record(["group", "item", "quantity", "amount", "unit"], delimiter);
+    for group in groups {
+        for item in &group.items {
+            text.push_str(&csv::write_record(
+                [
+                    group.title(),
+                    &item.name,
+                    &item.quantity.to_string(),
+                    &format_amount(item.price, &item.unit, major_units),
+                    &item.unit,
+                ],
+                delimiter,
+            ));
+        }
+        for row in &group.total.0 {
+            text.push_str(&csv::write_record(
+                [
+                    group.title(),
+                    "subtotal",
+                    "",
+                    &format_amount(row.amount(), row.unit(), major_units),
+                    row.unit(),
+                ],
+                delimiter,
+            ));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    fn subtotals(groups: &[GroupTotal]) -> Vec<(String, String, usize)> {
+        groups
+            .iter()
+            .map(|group| {
+                (
+                    group.group.clone(),
+                    group.total.to_string(),
+                    group.items.len(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_have_subtotals() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "UPDATE Item SET category = 'Dairy' WHERE name == 'Milk';
+            INSERT INTO Item (name, quantity, price, unit, receipt, category) VALUES
+                ('Cheese', 1, 200, 'EUR', 1, 'Dairy');",
+        )
+        .unwrap();
+        let by_category = grouped_totals(&conn, 1, GroupBy::Category, false).unwrap();
+        assert_eq!(
+            subtotals(&by_category),
+            [
+                ("".to_string(), "3000 NOK".to_string(), 1),
+                ("Dairy".to_string(), "3980 NOK, 200 EUR".to_string(), 2),
+            ]
+        );
+        let by_store = grouped_totals(&conn, 1, GroupBy::Store, false).unwrap();
+        assert_eq!(
+            subtotals(&by_store),
+            [("Shop".to_string(), "6980 NOK, 200 EUR".to_string(), 3)]
+        );
+        assert!(grouped_totals(&conn, 4, GroupBy::Category, false)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            grouped_csv(&by_category, csv::Delimiter::Semicolon, true),
+            "group;item;quantity;amount;unit
+without category;Bread;1;30.00;NOK
+without category;subtotal;;30.00;NOK
+Dairy;Milk;2;19.90;NOK
+Dairy;Cheese;1;2.00;EUR
+Dairy;subtotal;;39.80;NOK
+Dairy;subtotal;;2.00;EUR
+"
+        );
+    }
+
+    #[test]
+    fn refunds_can_be_left_out_of_the_subtotals() {
+        let conn = sample_db();
+        let net = grouped_totals(&conn, 3, GroupBy::Store, false).unwrap();
+        assert_eq!(
+            subtotals(&net),
+            [("Kiosk".to_string(), "1990 NOK".to_string(), 2)]
+        );
+        let gross = grouped_totals(&conn, 3, GroupBy::Store, true).unwrap();
+        assert_eq!(
+            subtotals(&gross),
+            [("Kiosk".to_string(), "2190 NOK".to_string(), 2)]
+        );
+    }
+}
@@ -1,8 +1,22 @@
 extern crate core;
 
 use crate::analysis::{Analysis, AnalysisMsg};
-use crate::combobox::AppendAll;
-use crate::unit::Unit;
+use crate::args::{Args, SETTINGS_ENV};
+use crate::combobox::{position_of_key, AppendAll};
+use crate::dashboard::{Dashboard, DashboardMsg};
+use crate::date::StorageFormat;
+use crate::error::{AppError, Report};
+use crate::integrity::OrphanCleanup;
+use crate::item_groups::{GroupBy, GroupTotal, ShowItemGroups};
+use crate::name_casing::{collapse_whitespace, NameCasing};
+use crate::name_status::{NameField, NameStatus};
+use crate::pop_out::PopOut;
+use crate::rates::{CombinedTotal, Rate};
+use crate::receipt_selection::ReceiptSelection;
+use crate::recent_items::{LastPrice, RecentItem, ShowRecentItems};
+use crate::store_picker::{StorePicker, StorePickerMsg};
+use crate::tabs::{ShowTabLayout, Tab, TabLayout};
+use crate::unit::{format_amount, round_quantity, Money, Rounding, Unit, QUANTITY_DIGITS};
 use native_dialog::FileDialog;
 use relm4::gtk::glib::{DateTime, GString};
 use relm4::gtk::prelude::*;
@@ -11,48 +25,208 @@ use relm4::{
     Component, ComponentController, ComponentParts, ComponentSender, Controller, RelmApp,
     RelmWidgetExt, SimpleComponent,
 };
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::identity;
 use std::fmt;
 use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
+use tap::TapFallible;
 
 mod add_duplicate_alert;
 mod analysis;
+mod args;
 mod combobox;
+mod confirm_dialog;
+mod csv;
+mod dashboard;
+mod date;
 mod dialog_ext;
+mod error;
+mod integrity;
+mod item_groups;
+mod name_casing;
+mod name_status;
+mod pop_out;
+mod price_update;
+mod rates;
+mod receipt_selection;
+mod recent_items;
 mod schema;
+mod statement;
+mod store_picker;
+mod tabs;
 mod unit;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Settings {
     db_file: String,
+    /// Still written for older versions, they only knew [`NameCasing::Upper`].
+    #[serde(default)]
     capitalize_item_names: bool,
+    #[serde(default)]
+    item_name_casing: NameCasing,
+    #[serde(default)]
+    csv_delimiter: csv::Delimiter,
+    #[serde(default)]
+    sticky_item_fields: bool,
+    #[serde(default)]
+    collapse_name_whitespace: bool,
+    #[serde(default = "default_price_increments")]
+    price_increments: (f64, f64),
+    /// The most recently connected database first
+    #[serde(default)]
+    recent_dbs: Vec<String>,
+    /// Position of the divider between the analysis results and inputs
+    #[serde(default)]
+    input_panel_position: Option<i32>,
+    /// Use a write-ahead log, so other tools can read the database while it is open
+    #[serde(default)]
+    wal_mode: bool,
+    /// Show prices, totals, budgets and money columns of analysis results in major units instead
+    /// of the stored integers
+    #[serde(default)]
+    major_units: bool,
+    /// Ask before an item is added to a receipt that already has an item with the name
+    #[serde(default = "default_warn_duplicates")]
+    warn_duplicate_items: bool,
+    /// Ask before a store is added with the name and location of an existing one
+    #[serde(default = "default_warn_duplicates")]
+    warn_duplicate_stores: bool,
+    /// Ask before a receipt is added for a store and date that already have one
+    #[serde(default = "default_warn_duplicates")]
+    warn_duplicate_receipts: bool,
+    /// `DateTime::format` string of displayed receipt dates, empty for `YYYY-MM-DD`
+    #[serde(default)]
+    date_format: String,
+    /// How new receipt dates are written to the database
+    #[serde(default)]
+    storage_date_format: StorageFormat,
+    /// Ask before adding an item whose line costs more than this, per unit name in major units
+    #[serde(default)]
+    large_amounts: HashMap<String, f64>,
+    /// Order and visibility of the tabs, applied at the next start
+    #[serde(default)]
+    tabs: TabLayout,
+    /// Unit the item total is also shown in, converted with the rates of the database
+    #[serde(default)]
+    base_unit: Option<String>,
+    #[serde(default)]
+    receipt_selection: ReceiptSelection,
+    /// Used to convert between major and minor units and to divide line totals
+    #[serde(default)]
+    rounding: Rounding,
+    /// Leave lines with a negative amount out of the item total and the spending of the
+    /// dashboard
+    #[serde(default)]
+    exclude_refunds: bool,
+    /// Quantities with decimals can be entered, e.g. the weight of produce
+    #[serde(default)]
+    decimal_quantities: bool,
+    /// How many inputs and header columns a query needs at least
+    #[serde(default = "default_required_rows")]
+    required_rows: (usize, usize),
+    /// Id of the receipt selected last, by database path
+    #[serde(default)]
+    last_receipts: HashMap<String, i64>,
+}
+
+/// Writes the settings as JSON, replacing the file if it exists.
+fn write_settings(path: &str, settings: &Settings) -> Result<(), String> {
+    let file = File::options()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    serde_json::to_writer(file, settings).map_err(|err| err.to_string())
+}
+
+fn default_warn_duplicates() -> bool {
+    true
+}
+
+fn default_required_rows() -> (usize, usize) {
+    analysis::DEFAULT_REQUIRED_ROWS
+}
+
+/// Step and page increment of the price field in major units
+fn default_price_increments() -> (f64, f64) {
+    (1.0, 10.0)
 }
 
 #[derive(Debug)]
 struct Store {
     name: GString,
     location: GString,
+    /// Optional, like `country`
+    city: GString,
+    country: GString,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct StoreRow {
     id: i64,
     name: String,
     location: String,
 }
 
-#[derive(Debug)]
-struct TotalRow {
-    unit: String,
-    price: i64,
+impl fmt::Display for StoreRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}) #{}", self.name, self.location, self.id)
+    }
+}
+
+/// The sum of the lines of a unit. The items of a database may have units the app does not
+/// know, their sum can not be a [`Money`] and is kept as stored.
+#[derive(Clone, Debug, PartialEq)]
+enum TotalRow {
+    Money(Money),
+    Unknown { unit: String, amount: i64 },
+}
+
+impl TotalRow {
+    fn new(unit: String, amount: i64) -> Self {
+        match Unit::from_str(&unit) {
+            Ok(unit) => TotalRow::Money(Money::new(amount, unit)),
+            Err(()) => TotalRow::Unknown { unit, amount },
+        }
+    }
+
+    fn unit(&self) -> &str {
+        match self {
+            TotalRow::Money(money) => money.unit.as_str(),
+            TotalRow::Unknown { unit, .. } => unit,
+        }
+    }
+
+    /// In minor units of the unit
+    fn amount(&self) -> i64 {
+        match self {
+            TotalRow::Money(money) => money.minor,
+            TotalRow::Unknown { amount, .. } => *amount,
+        }
+    }
+
+    /// Adds a line of the unit.
+    fn add(&mut self, line: i64) {
+        match self {
+            TotalRow::Money(money) => money.minor += line,
+            TotalRow::Unknown { amount, .. } => *amount += line,
+        }
+    }
 }
 
+/// The alternate flag, `{:#}`, shows the price in major units.
 impl fmt::Display for TotalRow {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.price, self.unit)
+        match self {
+            TotalRow::Money(money) => fmt::Display::fmt(money, f),
+            TotalRow::Unknown { unit, amount } => write!(f, "{amount} {unit}"),
+        }
     }
 }
 
@@ -63,33 +237,264 @@ impl Total {
         Total(Vec::new())
     }
 
-    fn for_receipt(conn: &Connection, receipt_id: i64) -> Self {
-        let mut totals_query = conn
-            .prepare(
-                "SELECT unit, SUM(price * quantity) FROM Item WHERE receipt == ?1 GROUP BY unit;",
-            )
-            .unwrap();
+    /// Lines with a negative amount, e.g. refunds and deposits, are left out with
+    /// `exclude_refunds`. Fails if the `Item` table lacks a column of the query, e.g. in a
+    /// database of another tool.
+    fn for_receipt(
+        conn: &Connection,
+        receipt_id: i64,
+        exclude_refunds: bool,
+    ) -> rusqlite::Result<Self> {
+        let mut totals_query = conn.prepare(
+            "SELECT unit, SUM(CAST(ROUND(price * quantity) AS INTEGER)) FROM Item
+            WHERE receipt == ?1 AND (NOT ?2 OR price * quantity >= 0) GROUP BY unit;",
+        )?;
         let total: Vec<_> = totals_query
-            .query_map(params![receipt_id], |row| {
-                Ok(TotalRow {
-                    unit: row.get(0)?,
-                    price: row.get(1)?,
-                })
-            })
-            .unwrap()
+            .query_map(params![receipt_id, exclude_refunds], |row| {
+                Ok(TotalRow::new(row.get(0)?, row.get(1)?))
+            })?
             .filter_map(Result::ok)
             .collect();
-        Total(total)
+        Ok(Total(total))
+    }
+}
+
+/// The total printed on the receipt, if it was entered. It is always entered with a unit of
+/// the app.
+fn printed_total_of_receipt(conn: &Connection, receipt_id: i64) -> Option<Money> {
+    conn.query_row(
+        "SELECT printed_total_unit, printed_total FROM Receipt WHERE id == ?1 AND printed_total IS NOT NULL;",
+        params![receipt_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+    )
+    .optional()
+    .tap_err(|err| eprintln!("[printed total]{err:#?}"))
+    .ok()
+    .flatten()
+    .and_then(|(unit, amount)| Some(Money::new(amount, Unit::from_str(&unit).ok()?)))
+}
+
+impl Total {
+    /// Difference between the entered items and the printed total.
+    /// Only items with the unit of the printed total are compared.
+    fn delta(&self, printed: &Money) -> i64 {
+        let entered: i64 = self
+            .0
+            .iter()
+            .filter(|row| row.unit() == printed.unit.as_str())
+            .map(TotalRow::amount)
+            .sum();
+        entered - printed.minor
+    }
+}
+
+fn reconciliation_text(total: &Total, printed: &Option<Money>, major_units: bool) -> String {
+    match printed {
+        Some(printed) => {
+            let printed_text = amount_text(printed, major_units);
+            match total.delta(printed) {
+                0 => format!("matches the printed total of {printed_text}"),
+                delta => format!(
+                    "differs from the printed total of {printed_text} by {}{}",
+                    if delta > 0 { "+" } else { "" },
+                    amount_text(&Money::new(delta, printed.unit), major_units),
+                ),
+            }
+        }
+        None => String::new(),
+    }
+}
+
+/// The total converted to the base unit, empty without a base unit or if the total has no
+/// other unit.
+fn combined_total_text(
+    total: &Total,
+    base_unit: Option<Unit>,
+    rates: &[Rate],
+    rounding: Rounding,
+    major_units: bool,
+) -> String {
+    base_unit
+        .and_then(|base| CombinedTotal::new(total, base, rates, rounding))
+        .map(|combined| amount_text(&combined, major_units))
+        .unwrap_or_default()
+}
+
+/// Shows the amounts of a [`Total`], [`TotalRow`] or [`BudgetStatus`] in major units if enabled.
+fn amount_text(value: &impl fmt::Display, major_units: bool) -> String {
+    if major_units {
+        format!("{value:#}")
+    } else {
+        value.to_string()
+    }
+}
+
+/// The threshold of the unit of the item, if the line costs more than it.
+fn exceeded_large_amount(
+    item: &NewItem,
+    thresholds: &HashMap<String, f64>,
+    rounding: Rounding,
+) -> Option<Money> {
+    let threshold = thresholds
+        .get(item.price.unit.as_str())
+        .filter(|threshold| **threshold > 0.0)?;
+    // compared in minor units, so a threshold of 1000.00 is not exceeded by 100000 minor units
+    let threshold = Money::from_major(*threshold, item.price.unit, rounding);
+    (item.price.times(item.quantity).minor.abs() > threshold.minor).then_some(threshold)
+}
+
+fn large_amounts_text(thresholds: &HashMap<String, f64>, rounding: Rounding) -> String {
+    let text = Unit::ALL
+        .iter()
+        .filter_map(|unit| {
+            let threshold = thresholds
+                .get(unit.as_str())
+                .filter(|threshold| **threshold > 0.0)?;
+            Some(format!(
+                "{:#}",
+                Money::from_major(*threshold, *unit, rounding)
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if text.is_empty() {
+        "No amount is confirmed.".to_string()
+    } else {
+        format!("Confirm lines over {text}.")
+    }
+}
+
+/// Empty unless the entered line total had to be rounded to store a price per unit.
+fn rounding_note(entered: Money, price: Money, quantity: f64, line_total: bool) -> String {
+    let stored = price.times(quantity);
+    if !line_total || stored == entered {
+        return String::new();
     }
+    let difference = Money::new((stored.minor - entered.minor).abs(), price.unit);
+    format!(
+        "Stored as {quantity} × {price:#}, {difference:#} {} than the entered line total.",
+        if stored.minor > entered.minor {
+            "more"
+        } else {
+            "less"
+        }
+    )
 }
 
+/// The alternate flag, `{:#}`, shows the prices in major units.
 impl fmt::Display for Total {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if !self.0.is_empty() {
-            write!(f, "{}", self.0[0])?;
-            for total in &self.0[1..self.0.len()] {
-                write!(f, ", {}", total)?;
+        for (i, total) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
             }
+            fmt::Display::fmt(total, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Spending of a store in the month of a receipt
+#[derive(Debug)]
+struct BudgetStatus {
+    month: String,
+    unit: String,
+    spent: i64,
+    budget: i64,
+}
+
+impl BudgetStatus {
+    /// `None` if the store of the receipt has no budget for the unit.
+    fn for_receipt(
+        conn: &Connection,
+        receipt_id: i64,
+        unit: &str,
+    ) -> rusqlite::Result<Option<Self>> {
+        let receipt_date = date::iso_date_sql("Receipt.date");
+        conn.query_row(
+            &format!(
+                "SELECT strftime('%Y-%m', {receipt_date}), Budget.amount, (
+                    SELECT COALESCE(SUM(CAST(ROUND(Item.price * Item.quantity) AS INTEGER)), 0) FROM Item
+                    INNER JOIN Receipt AS r ON Item.receipt = r.id
+                    WHERE r.store == Receipt.store AND Item.unit == Budget.unit
+                    AND strftime('%Y-%m', {}) == strftime('%Y-%m', {receipt_date})
+                ) FROM Receipt INNER JOIN Budget ON Budget.store == Receipt.store
+                WHERE Receipt.id == ?1 AND Budget.unit == ?2;",
+                date::iso_date_sql("r.date")
+            ),
+            params![receipt_id, unit],
+            |row| {
+                Ok(BudgetStatus {
+                    month: row.get(0)?,
+                    budget: row.get(1)?,
+                    spent: row.get(2)?,
+                    unit: unit.to_string(),
+                })
+            },
+        )
+        .optional()
+    }
+
+    fn is_over(&self) -> bool {
+        self.spent > self.budget
+    }
+}
+
+/// The alternate flag, `{:#}`, shows the amounts in major units.
+impl fmt::Display for BudgetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let BudgetStatus {
+            month,
+            unit,
+            spent,
+            budget,
+        } = self;
+        let amount = |amount: i64| format_amount(amount, unit, f.alternate());
+        let (spent_text, budget_text) = (amount(*spent), amount(*budget));
+        if self.is_over() {
+            write!(
+                f,
+                "{spent_text} of the {budget_text} {unit} budget spent in {month}, {} {unit} over budget",
+                amount(spent - budget)
+            )
+        } else {
+            write!(
+                f,
+                "{spent_text} of the {budget_text} {unit} budget spent in {month}, {} {unit} left",
+                amount(budget - spent)
+            )
+        }
+    }
+}
+
+/// What was entered since the app was launched
+#[derive(Debug, Default)]
+struct SessionSummary {
+    receipts: usize,
+    items: usize,
+    /// Items created today in the connected database, including those of earlier sessions
+    items_today: Option<i64>,
+}
+
+impl SessionSummary {
+    fn count_items_today(conn: &Connection) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM Item WHERE date(created_at, 'localtime') == date('now', 'localtime');",
+            [],
+            |row| row.get(0),
+        )
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} receipt(s) and {} item(s) added since launch",
+            self.receipts, self.items
+        )?;
+        if let Some(items_today) = self.items_today {
+            write!(f, ", {items_today} item(s) created today")?;
         }
         Ok(())
     }
@@ -97,8 +502,73 @@ impl fmt::Display for Total {
 
 #[derive(Debug)]
 struct Receipt {
-    store_idx: Option<u32>,
     date: DateTime,
+    currency: ReceiptCurrency,
+    /// Switch to the item tab with the new receipt selected
+    go_to_items: bool,
+}
+
+/// The unit the items of a new receipt are expected to have, chosen in the receipt tab.
+#[derive(Debug, Clone, Copy)]
+enum ReceiptCurrency {
+    /// The unit of most items of the store, see [`store_default_unit`]
+    StoreDefault,
+    /// The items may have any unit
+    Any,
+    Unit(Unit),
+}
+
+impl ReceiptCurrency {
+    /// Labels of the choices, in the order of [`ReceiptCurrency::from_idx`].
+    fn labels() -> impl Iterator<Item = String> {
+        ["store default", "any unit"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(Unit::ALL.iter().map(|unit| unit.as_str().to_string()))
+    }
+
+    fn from_idx(idx: Option<u32>) -> Self {
+        match idx {
+            None | Some(0) => ReceiptCurrency::StoreDefault,
+            Some(1) => ReceiptCurrency::Any,
+            Some(idx) => Unit::from_idx(idx - 2)
+                .map(ReceiptCurrency::Unit)
+                .unwrap_or(ReceiptCurrency::Any),
+        }
+    }
+
+    /// The currency the receipt is stored with, `None` if its items may have any unit.
+    fn resolve(self, conn: &Connection, store_id: i64) -> rusqlite::Result<Option<Unit>> {
+        match self {
+            ReceiptCurrency::StoreDefault => store_default_unit(conn, store_id),
+            ReceiptCurrency::Any => Ok(None),
+            ReceiptCurrency::Unit(unit) => Ok(Some(unit)),
+        }
+    }
+}
+
+/// The unit most items of the receipts of the store have, the most recent one on a tie.
+/// `None` if the store has no items yet.
+fn store_default_unit(conn: &Connection, store_id: i64) -> rusqlite::Result<Option<Unit>> {
+    let unit: Option<String> = conn
+        .query_row(
+            "SELECT Item.unit FROM Item INNER JOIN Receipt ON Item.receipt = Receipt.id
+            WHERE Receipt.store == ?1 GROUP BY Item.unit ORDER BY COUNT(*) DESC, MAX(Item.id) DESC LIMIT 1;",
+            params![store_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(unit.and_then(|unit| Unit::from_str(&unit).ok()))
+}
+
+/// Shown next to the unit of a new item, if the receipt expects another unit.
+fn currency_warning(unit: Unit, currency: Option<Unit>) -> String {
+    match currency {
+        Some(currency) if currency != unit => {
+            format!("The items of this receipt are in {currency}.")
+        }
+        _ => String::new(),
+    }
 }
 
 #[derive(Debug)]
@@ -106,58 +576,40 @@ struct ReceiptRow {
     id: i64,
     store_name: String,
     date: String,
+    /// See [`ReceiptCurrency`], a unit the app does not know is ignored
+    currency: Option<Unit>,
 }
 
 #[derive(Debug)]
 struct Item {
     name: GString,
-    quantity: u32,
-    price: i32,
+    /// Rounded to [`QUANTITY_DIGITS`] decimals
+    quantity: f64,
+    /// In major units as entered, it is rounded with the rounding of the settings
+    price: f64,
     unit: Unit,
+    /// `price` is what the whole line cost instead of the price of one unit
+    line_total: bool,
+    /// Empty for an item without a category
+    category: GString,
     receipt_idx: Option<u32>,
 }
 
-#[derive(PartialEq, Eq)]
-struct InitUpdate {}
-
-#[derive(Copy, Clone, Eq, PartialEq)]
-enum NameStatus {
-    Valid,
-    NonEmpty,
-    Connected,
-    Invalid,
+/// An item as it is stored, after the name and price were normalized.
+#[derive(Debug, Clone)]
+struct NewItem {
+    name: String,
+    /// Stored as an integer if it is whole and as a `REAL` otherwise, see [`round_quantity`]
+    quantity: f64,
+    /// Per unit. Line totals are divided by the quantity before they are stored, see
+    /// [`Money::per_unit`].
+    price: Money,
+    category: Option<String>,
+    receipt_id: i64,
 }
 
-impl NameStatus {
-    fn connect(&mut self) {
-        match *self {
-            NameStatus::Valid => {}
-            NameStatus::NonEmpty => *self = NameStatus::Valid,
-            NameStatus::Connected => {}
-            NameStatus::Invalid => *self = NameStatus::Connected,
-        }
-    }
-
-    // we never disconnect from a database, only override a connection if it was successful established
-
-    fn name_non_empty(&mut self) {
-        match *self {
-            NameStatus::Valid => {}
-            NameStatus::NonEmpty => {}
-            NameStatus::Connected => *self = NameStatus::Valid,
-            NameStatus::Invalid => *self = NameStatus::NonEmpty,
-        }
-    }
-
-    fn name_empty(&mut self) {
-        match *self {
-            NameStatus::Valid => *self = NameStatus::Connected,
-            NameStatus::NonEmpty => *self = NameStatus::Invalid,
-            NameStatus::Connected => {}
-            NameStatus::Invalid => {}
-        }
-    }
-}
+#[derive(PartialEq, Eq)]
+struct InitUpdate {}
 
 #[tracker::track]
 struct Ui {
@@ -174,43 +626,644 @@ struct Ui {
     settings_db_path: String,
     #[tracker::no_eq]
     settings_db_path_status: String,
+    /// Database of the settings that could not be opened on startup, e.g. because it was moved
+    missing_db: Option<String>,
     #[tracker::no_eq]
     settings_db_create_path: String,
     #[tracker::no_eq]
-    settings_db_create_path_status: String,
+    /// Outcome of creating a database, errors are shown in red
+    settings_db_create_path_status: Result<String, String>,
+    /// Number of the shown page in the notebook, see [`TabLayout`]
     page: i32,
-    capitalize_item_names: bool,
+    /// The layout the tabs were arranged with at the start
+    #[tracker::do_not_track]
+    tab_layout: TabLayout,
+    /// The layout of the settings, applied at the next start
+    tabs: TabLayout,
+    item_name_casing: NameCasing,
+    /// Only clear the name and price of the item fields after adding an item
+    sticky_item_fields: bool,
+    /// Replace repeated whitespace within added store and item names by a single space
+    collapse_name_whitespace: bool,
+    /// Step and page increment of the price field in major units
+    price_increments: (f64, f64),
+    csv_delimiter: csv::Delimiter,
     store_name_valid: NameStatus,
     store_location_valid: NameStatus,
     item_name_valid: NameStatus,
+    /// Without refunds if they are excluded
     #[tracker::no_eq]
     total: Total,
+    /// Always with refunds, compared to the printed total
+    #[tracker::no_eq]
+    net_total: Total,
+    exclude_refunds: bool,
+    decimal_quantities: bool,
+    /// Inputs and header columns a query needs at least
+    required_rows: (usize, usize),
+    base_unit: Option<Unit>,
+    #[tracker::no_eq]
+    rates: Vec<Rate>,
+    rounding: Rounding,
+    #[tracker::no_eq]
+    printed_total: Option<Money>,
+    #[tracker::no_eq]
+    integrity_report: Option<String>,
+    connected_db: Option<String>,
+    /// Database of the settings file while the one of the command line is connected, it is
+    /// saved instead
+    #[tracker::do_not_track]
+    settings_db_file: Option<String>,
+    /// Path of the settings file, see [`Args::settings_file`]
+    #[tracker::do_not_track]
+    settings_file: String,
+    /// Why the settings could not be saved, shown in a dialog to retry
+    #[tracker::no_eq]
+    settings_error: Option<String>,
+    /// Why the last action of the user failed, shown in a dialog
+    #[tracker::no_eq]
+    app_error: Option<String>,
+    /// Result of exporting or resetting the settings
+    settings_status: String,
+    /// How many rows the last import or conversion changed, shown in the header bar
+    #[tracker::no_eq]
+    status: String,
+    recent_dbs: Vec<String>,
+    /// Spending of the store of the receipt the last item was added to, compared to its budget
+    #[tracker::no_eq]
+    budget_status: Option<BudgetStatus>,
+    #[tracker::no_eq]
+    session_summary: SessionSummary,
+    #[tracker::no_eq]
+    recent_items: Vec<RecentItem>,
+    #[tracker::no_eq]
+    prefill_item: Option<RecentItem>,
+    /// Currency of the selected receipt, set on every change of the receipt to default the
+    /// unit of a new item to it
+    #[tracker::no_eq]
+    receipt_currency: Option<Unit>,
+    /// Only read when the settings are saved
+    #[tracker::do_not_track]
+    input_panel_position: Option<i32>,
+    /// Whether the divider was moved or another receipt was selected since the settings were
+    /// saved
+    #[tracker::do_not_track]
+    unsaved_changes: bool,
+    receipt_selection: ReceiptSelection,
+    #[tracker::do_not_track]
+    last_receipts: HashMap<String, i64>,
+    wal_mode: bool,
+    /// Journal mode of the connected database as reported by SQLite
+    #[tracker::no_eq]
+    journal_mode: String,
+    major_units: bool,
+    warn_duplicate_items: bool,
+    warn_duplicate_stores: bool,
+    warn_duplicate_receipts: bool,
+    #[tracker::no_eq]
+    date_format: String,
+    storage_date_format: StorageFormat,
+    /// The price field holds the total of the line instead of the price per unit
+    price_is_line_total: bool,
+    /// Tells how a line total that did not divide evenly by the quantity was stored
+    #[tracker::no_eq]
+    rounding_note: String,
+    /// Per unit name, in major units
+    #[tracker::no_eq]
+    large_amounts: HashMap<String, f64>,
+    group_items_by: GroupBy,
+    /// Items of the selected receipt
+    #[tracker::no_eq]
+    item_groups: Vec<GroupTotal>,
+    /// Of the item whose name is entered
+    #[tracker::no_eq]
+    last_price: Option<LastPrice>,
+    /// The analysis is shown in its own window instead of its tab
+    analysis_popped_out: bool,
+    /// Only the latest scheduled lookup of the last price runs
+    #[tracker::do_not_track]
+    last_price_generation: u64,
+}
+
+impl Report for Ui {
+    fn show_error(&mut self, message: String) {
+        self.set_app_error(Some(message));
+    }
+}
+
+impl Ui {
+    fn connect_names(&mut self) {
+        self.update_store_name_valid(NameStatus::connect);
+        self.update_store_location_valid(NameStatus::connect);
+        self.update_item_name_valid(NameStatus::connect);
+    }
+
+    /// Makes the database the first one of the switcher, in place of the path it was moved from.
+    fn remember_db(&mut self, db_path: String, moved_from: Option<&String>) {
+        self.update_recent_dbs(|recent_dbs| {
+            recent_dbs.retain(|path| *path != db_path && Some(path) != moved_from);
+            recent_dbs.insert(0, db_path);
+            recent_dbs.truncate(MAX_RECENT_DBS);
+        });
+    }
+
+    /// The name as it is added: trimmed and, if [`Ui::collapse_name_whitespace`] is set, with
+    /// single spaces within, so it matches the names added before in duplicate checks.
+    fn normalize_name(&self, name: &str) -> String {
+        if self.collapse_name_whitespace {
+            collapse_whitespace(name)
+        } else {
+            name.trim().to_string()
+        }
+    }
+
+    fn selected_receipt_id(&self) -> Option<i64> {
+        self.receipts
+            .1
+            .and_then(|idx| self.receipts.0.get(idx as usize))
+            .map(|receipt| receipt.id)
+    }
+
+    /// Loads the total and the grouped items of the receipt, or clears them without one.
+    fn load_totals(&mut self, conn: &Connection, receipt_id: Option<i64>) {
+        match receipt_id {
+            Some(receipt_id) => {
+                // an empty total keeps the item tab usable with an unexpected schema
+                let total = |exclude_refunds| {
+                    Total::for_receipt(conn, receipt_id, exclude_refunds).unwrap_or_else(|err| {
+                        eprintln!("[total]{err:#?}");
+                        Total::new()
+                    })
+                };
+                self.set_total(total(self.exclude_refunds));
+                self.set_net_total(total(false));
+                match item_groups::grouped_totals(
+                    conn,
+                    receipt_id,
+                    self.group_items_by,
+                    self.exclude_refunds,
+                ) {
+                    Ok(item_groups) => self.set_item_groups(item_groups),
+                    Err(err) => eprintln!("[item groups]{err:#?}"),
+                }
+            }
+            None => {
+                self.set_total(Total::new());
+                self.set_net_total(Total::new());
+                self.set_item_groups(Vec::new());
+            }
+        }
+    }
+
+    fn load_rates(&mut self, conn: &Connection) {
+        match rates::load_rates(conn) {
+            Ok(rates) => self.set_rates(rates),
+            Err(err) => eprintln!("[rates]{err:#?}"),
+        }
+    }
+
+    fn load_items_today(&mut self, conn: &Connection) {
+        match SessionSummary::count_items_today(conn) {
+            Ok(items_today) => {
+                self.update_session_summary(|summary| summary.items_today = Some(items_today))
+            }
+            Err(err) => eprintln!("[items today]{err:#?}"),
+        }
+    }
+
+    fn load_recent_items(&mut self, conn: &Connection) {
+        match RecentItem::load(conn, RECENT_ITEMS) {
+            Ok(recent_items) => self.set_recent_items(recent_items),
+            Err(err) => eprintln!("[recent items]{err:#?}"),
+        }
+    }
 }
 
 struct App {
     conn: Option<Rc<Connection>>,
     ui: Ui,
+    receipt_list: gtk::ListStore,
+    /// Item names, the most frequently bought first
+    item_names: gtk::ListStore,
     dialog: Controller<add_duplicate_alert::Dialog>,
+    confirm_dialog: Controller<confirm_dialog::Dialog>,
     analysis: Controller<Analysis>,
+    dashboard: Controller<Dashboard>,
+    /// Store of new receipts
+    receipt_store: Controller<StorePicker>,
+    merge_source: Controller<StorePicker>,
+    merge_target: Controller<StorePicker>,
+    budget_store: Controller<StorePicker>,
 }
 
 #[derive(Debug)]
 enum Msg {
     SelectUnit(Unit),
+    PriceIsLineTotal(bool),
+    GroupItemsBy(GroupBy),
+    /// Writes the grouped items of the selected receipt with their subtotals to a CSV file
+    ExportItemGroups,
+    /// Sent by the timer scheduled when the item name changed, with the generation it was
+    /// scheduled for
+    LookupLastPrice(u64, GString),
+    /// Fills the item fields with the recent item at the index
+    PrefillItem(usize),
     AddStore(Store),
     ForceAddStore(Store),
+    ForceAddItem(NewItem),
+    /// Adds the item after its large amount was confirmed, it may still be a duplicate
+    ConfirmLargeAmount(NewItem),
+    /// Adds the item although its unit is not the currency of its receipt
+    ConfirmCurrency(NewItem),
+    /// Sets the amount of the unit, in major units, above which adding an item is confirmed.
+    /// 0 asks for no confirmation.
+    LargeAmount {
+        unit: Unit,
+        amount: f64,
+    },
+    /// Adds the quantity of the item to the existing item instead of adding it again
+    MergeItem {
+        existing_id: i64,
+        item: NewItem,
+    },
     AddReceipt(Receipt),
-    ForceAddReceipt(i64, GString),
+    ForceAddReceipt {
+        store_id: i64,
+        date: DateTime,
+        currency: Option<Unit>,
+        go_to_items: bool,
+    },
     AddItem(Item),
     OpenDbDialog,
     OpenCreateDbDialog,
     ConnectDb,
+    /// Connects the database that could not be opened on startup again
+    ReconnectDb,
+    /// Asks where the database that could not be opened on startup is now
+    LocateDb,
+    /// Reloads everything shown from the database
+    Refresh,
     CreateDb,
-    CapitalizeItem(bool),
-    ValidateStoreName(GString),
-    ValidateStoreLocation(GString),
-    ValidateItemName(GString),
+    ForceCreateDb,
+    ItemNameCasing(NameCasing),
+    WalMode(bool),
+    MajorUnits(bool),
+    WarnDuplicateItems(bool),
+    WarnDuplicateStores(bool),
+    WarnDuplicateReceipts(bool),
+    DateFormat(GString),
+    ReceiptSelection(ReceiptSelection),
+    Rounding(Rounding),
+    ExcludeRefunds(bool),
+    DecimalQuantities(bool),
+    /// How many inputs a query needs at least
+    RequiredInputs(usize),
+    /// How many header columns a query needs at least
+    RequiredOutputs(usize),
+    BaseUnit(Option<Unit>),
+    /// A rate of 0 removes the rate
+    SetRate {
+        unit: Unit,
+        base: Unit,
+        rate: f64,
+    },
+    /// Stores new receipt dates in the format and asks to convert the existing ones
+    StorageDateFormat(StorageFormat),
+    ForceConvertReceiptDates(StorageFormat),
+    StickyItemFields(bool),
+    CollapseNameWhitespace(bool),
+    PriceIncrements(f64, f64),
+    CheckIntegrity,
+    /// Shows the number of items without a receipt and asks to delete them, or to move them to
+    /// the selected receipt
+    CleanUpOrphanedItems {
+        reassign: bool,
+    },
+    ForceCleanUpOrphanedItems(OrphanCleanup),
+    Quit,
+    /// The divider of the analysis tab was moved to the position
+    InputPanelPosition(i32),
+    SwitchDb(Option<u32>),
+    /// The amount in major units, like the entered price
+    SetBudget {
+        amount: f64,
+        unit: Unit,
+    },
+    CloseIntegrityReport,
+    /// Saves the settings again after a failure
+    SaveSettings,
+    /// Saves the settings to another file after a failure
+    ChooseSettingsFile,
+    CloseSettingsError,
+    CloseError,
+    /// Writes the settings to a chosen file
+    ExportSettings,
+    /// Asks before the settings are reset
+    ResetSettings,
+    ForceResetSettings,
+    CsvDelimiter(csv::Delimiter),
+    ImportItems(Option<u32>),
+    /// The printed total in major units, like the entered price
+    SetPrintedTotal {
+        receipt_idx: Option<u32>,
+        printed_total: Option<(f64, Unit)>,
+    },
+    RescalePrices {
+        unit_idx: Option<u32>,
+        factor: i32,
+        divide: bool,
+    },
+    ForceRescalePrices {
+        unit: Unit,
+        factor: i32,
+        divide: bool,
+    },
+    /// Lists the items whose price changes and asks to confirm the update
+    UpdateItemPrices {
+        name: GString,
+        price: GString,
+        unit_idx: Option<u32>,
+        from: GString,
+        to: GString,
+    },
+    ForceUpdateItemPrices(price_update::PriceUpdate),
+    /// Reads a bank statement and asks to confirm the planned receipts
+    ImportStatement(Option<u32>),
+    ForceImportStatement(Vec<statement::PlannedReceipt>),
+    ValidateName(NameField, GString),
     ReceiptChanged(Option<u32>),
+    DeleteReceipts(Vec<i64>),
+    ForceDeleteReceipts(Vec<i64>),
+    MergeStores,
+    ForceMergeStores {
+        source_id: i64,
+        target_id: i64,
+    },
+    PageChanged(i32),
+    CyclePage(i32),
+    /// Selects the n-th visible tab
+    SelectPage(i32),
+    TabVisible(Tab, bool),
+    MoveTab(Tab, i32),
+    /// Shows the analysis in its own window, or back in its tab
+    PopOutAnalysis(bool),
+}
+
+/// Number of databases the switcher in the header bar offers
+const MAX_RECENT_DBS: usize = 10;
+/// Number of buttons for re-adding recent items
+const RECENT_ITEMS: usize = 8;
+/// The last price of an item is looked up once its name was not changed for this long.
+const LAST_PRICE_DELAY: Duration = Duration::from_millis(300);
+/// Planned statement imports or changed items listed in the confirmation dialog
+const MAX_LISTED_ACTIONS: usize = 20;
+/// How long SQLite retries a statement, with a short and growing delay, while another connection,
+/// e.g. another instance of the app, locks the database. Kept short, as it blocks the UI.
+const BUSY_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Opens the database and brings its schema up to date.
+fn open_db(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    schema::migrate(&conn)?;
+    Ok(conn)
+}
+
+/// Switches between a write-ahead log and the default rollback journal. The mode is stored in
+/// the database file. Returns the mode in effect, which stays unchanged while another
+/// connection uses the database.
+fn set_journal_mode(conn: &Connection, wal: bool) -> rusqlite::Result<String> {
+    let mode: String = conn.pragma_update_and_check(
+        None,
+        "journal_mode",
+        if wal { "WAL" } else { "DELETE" },
+        |row| row.get(0),
+    )?;
+    if mode.eq_ignore_ascii_case("wal") {
+        // a write-ahead log stays consistent with fewer syncs
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+    }
+    Ok(mode)
+}
+
+/// Moves the write-ahead log into the database file, if there is one, and closes the connection,
+/// so tools that open the file afterwards see every change.
+fn close_db(conn: Rc<Connection>) {
+    match conn.query_row("PRAGMA journal_mode;", [], |row| row.get::<_, String>(0)) {
+        Ok(mode) if mode.eq_ignore_ascii_case("wal") => {
+            if let Err(err) = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |_| Ok(())) {
+                eprintln!("[checkpoint]{err:#?}");
+            }
+        }
+        Ok(_) => {}
+        Err(err) => eprintln!("[journal mode]{err:#?}"),
+    }
+    // if another component still holds the connection, it is closed when that one is dropped
+    if let Ok(conn) = Rc::try_unwrap(conn) {
+        if let Err((_, err)) = conn.close() {
+            eprintln!("[close db]{err:#?}");
+        }
+    }
+}
+
+/// The title of the main window with the file name of the connected database, so windows of
+/// different databases can be told apart. Without a chosen database it is just "SQLBon".
+fn window_title(connected_db: Option<&str>, db_chosen: bool) -> String {
+    match connected_db {
+        Some(db) => {
+            let name = Path::new(db)
+                .file_name()
+                .map_or_else(|| db.into(), |name| name.to_string_lossy());
+            format!("SQLBon — {name}")
+        }
+        None if db_chosen => "SQLBon (disconnected)".to_string(),
+        None => "SQLBon".to_string(),
+    }
+}
+
+/// Moves the focus along the widgets with Tab and Shift+Tab, regardless of how they are nested.
+/// Widgets that can't take the focus, e.g. insensitive buttons, are left to the default order.
+fn set_focus_chain(chain: &[gtk::Widget]) {
+    for (i, widget) in chain.iter().enumerate() {
+        let previous = i.checked_sub(1).and_then(|i| chain.get(i)).cloned();
+        let next = chain.get(i + 1).cloned();
+        let keys = gtk::EventControllerKey::new();
+        // the inner widgets of entries and comboboxes would see the key first
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        keys.connect_key_pressed(move |_, key, _, modifier| {
+            let target = match key {
+                gtk::gdk::Key::ISO_Left_Tab => &previous,
+                gtk::gdk::Key::Tab if modifier.contains(gtk::gdk::ModifierType::SHIFT_MASK) => {
+                    &previous
+                }
+                gtk::gdk::Key::Tab => &next,
+                _ => return gtk::Inhibit(false),
+            };
+            gtk::Inhibit(target.as_ref().map_or(false, |target| target.grab_focus()))
+        });
+        widget.add_controller(&keys);
+    }
+}
+
+fn text_column(title: &str, idx: i32) -> gtk::TreeViewColumn {
+    let cell = gtk::CellRendererText::new();
+    let column = gtk::TreeViewColumn::new();
+    column.set_title(title);
+    column.pack_start(&cell, true);
+    column.set_attributes(&cell, &[("text", idx)]);
+    column.set_sort_column_id(idx);
+    column.set_resizable(true);
+    column
+}
+
+/// Runs `f` inside a transaction. It is committed if `f` succeeds and rolled back otherwise,
+/// so a failure halfway through doesn't leave dangling rows behind.
+fn with_transaction<T, E: From<rusqlite::Error>>(
+    conn: &Connection,
+    f: impl FnOnce(&Transaction) -> Result<T, E>,
+) -> Result<T, E> {
+    let tx = conn.unchecked_transaction()?;
+    let result = f(&tx)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Imports the items of a CSV file with a `name`, `quantity`, `price` and `unit` header into the receipt.
+/// Prices are in major units, e.g. `19.90`. Either all items are imported or none. Returns the
+/// number of imported items.
+fn import_items(
+    conn: &Connection,
+    receipt_id: i64,
+    text: &str,
+    delimiter: csv::Delimiter,
+    casing: NameCasing,
+) -> Result<usize, String> {
+    let header = text.lines().next().unwrap_or_default();
+    let mut records = csv::parse_records(text, delimiter.detect(header)).into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| "The file is empty.".to_string())?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("The header has no '{name}' column."))
+    };
+    let (name_col, quantity_col, price_col, unit_col) = (
+        column("name")?,
+        column("quantity")?,
+        column("price")?,
+        column("unit")?,
+    );
+
+    let mut items = Vec::new();
+    for (line, record) in records.enumerate() {
+        let field = |col: usize| {
+            record
+                .get(col)
+                .map(|field| field.trim())
+                .unwrap_or_default()
+        };
+        let invalid = |name: &str| format!("Record {} has an invalid {name}.", line + 1);
+        let name = field(name_col);
+        if name.is_empty() {
+            return Err(invalid("name"));
+        }
+        let name = casing.apply(name);
+        // weights may have a decimal comma
+        let quantity = field(quantity_col)
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|quantity| quantity.is_finite() && *quantity > 0.0)
+            .map(round_quantity)
+            .ok_or_else(|| invalid("quantity"))?;
+        let unit = Unit::from_str(field(unit_col)).map_err(|_| invalid("unit"))?;
+        // in major units, like every other entered price
+        let price = Money::parse(field(price_col), unit).map_err(|_| invalid("price"))?;
+        items.push((name, quantity, price));
+    }
+
+    with_transaction(conn, |tx| {
+        for (name, quantity, price) in &items {
+            tx.execute(
+                "INSERT INTO Item (name, quantity, price, unit, receipt, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                params![name, quantity, price.minor, price.unit.as_str(), receipt_id],
+            )?;
+        }
+        Ok(items.len())
+    })
+    .map_err(|err: rusqlite::Error| err.to_string())
+}
+
+/// Deletes the receipts and all of their items. Either everything is deleted or nothing.
+fn delete_receipts(conn: &Connection, receipt_ids: &[i64]) -> rusqlite::Result<()> {
+    with_transaction(conn, |tx| {
+        for receipt_id in receipt_ids {
+            tx.execute("DELETE FROM Item WHERE receipt == ?1;", params![receipt_id])?;
+            tx.execute("DELETE FROM Receipt WHERE id == ?1;", params![receipt_id])?;
+        }
+        Ok(())
+    })
+}
+
+/// Multiplies or divides the price of every item with the unit by the factor.
+/// Divided prices are rounded to the nearest integer.
+fn rescale_prices(
+    conn: &Connection,
+    unit: &Unit,
+    factor: i32,
+    divide: bool,
+) -> rusqlite::Result<()> {
+    let sql = if divide {
+        "UPDATE Item SET price = CAST(ROUND(price * 1.0 / ?1) AS INT), updated_at = CURRENT_TIMESTAMP WHERE unit == ?2;"
+    } else {
+        "UPDATE Item SET price = price * ?1, updated_at = CURRENT_TIMESTAMP WHERE unit == ?2;"
+    };
+    with_transaction(conn, |tx| {
+        tx.execute(sql, params![factor, unit.as_str()])?;
+        Ok(())
+    })
+}
+
+/// Rewrites the dates of all receipts in the format, returns the number of changed receipts.
+fn convert_receipt_dates(conn: &Connection, format: StorageFormat) -> rusqlite::Result<usize> {
+    with_transaction(conn, |tx| {
+        let dates = tx
+            .prepare("SELECT id, date FROM Receipt;")?
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut changed = 0;
+        for (id, stored) in dates {
+            match date::convert_date(&stored, format) {
+                Some(converted) if converted != stored => {
+                    tx.execute(
+                        "UPDATE Receipt SET date = ?1, updated_at = CURRENT_TIMESTAMP WHERE id == ?2;",
+                        params![converted, id],
+                    )?;
+                    changed += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(changed)
+    })
+}
+
+/// Moves all receipts of the source store to the target store and deletes the source store.
+fn merge_stores(conn: &Connection, source_id: i64, target_id: i64) -> rusqlite::Result<()> {
+    with_transaction(conn, |tx| {
+        tx.execute(
+            "UPDATE Receipt SET store = ?1, updated_at = CURRENT_TIMESTAMP WHERE store == ?2;",
+            params![target_id, source_id],
+        )?;
+        // the budget of the target store stays in place
+        tx.execute("DELETE FROM Budget WHERE store == ?1;", params![source_id])?;
+        tx.execute("DELETE FROM Store WHERE id == ?1;", params![source_id])?;
+        Ok(())
+    })
 }
 
 impl App {
@@ -243,70 +1296,420 @@ impl App {
                 .map(|rts| rts.0)
                 .or_else(|| new_stores.len().checked_sub(1))
                 .map(|idx| idx as u32);
+            self.receipt_store.emit(StorePickerMsg::Stores(
+                new_stores.clone(),
+                row_to_select.and_then(|idx| new_stores.get(idx as usize).map(|row| row.id)),
+            ));
+            for picker in [&self.merge_source, &self.merge_target, &self.budget_store] {
+                picker.emit(StorePickerMsg::Stores(new_stores.clone(), None));
+            }
             self.ui.set_stores((new_stores, row_to_select));
         }
     }
 
     fn load_receipts(&mut self) {
         if let Some(conn) = &self.conn {
-            let mut store_query = conn.prepare("SELECT Receipt.id, Receipt.date, Store.name FROM Receipt INNER JOIN Store ON Receipt.store = Store.id ORDER BY Receipt.id ASC;").unwrap();
+            let mut store_query = conn.prepare("SELECT Receipt.id, Receipt.date, Store.name, Receipt.currency FROM Receipt INNER JOIN Store ON Receipt.store = Store.id ORDER BY Receipt.id ASC;").unwrap();
             let new_receipts: Vec<_> = store_query
                 .query_map([], |row| {
                     Ok(ReceiptRow {
                         id: row.get(0)?,
                         date: row.get(1)?,
                         store_name: row.get(2)?,
+                        currency: row
+                            .get::<_, Option<String>>(3)?
+                            .and_then(|unit| Unit::from_str(&unit).ok()),
                     })
                 })
                 .unwrap()
                 .filter_map(|row| row.ok())
                 .collect();
-            let row_to_select = new_receipts
-                .iter()
-                .enumerate()
-                .find(|(_, row)| {
-                    self.ui
-                        .receipts
-                        .0
-                        .binary_search_by_key(&row.id, |old_row| old_row.id)
-                        .is_err()
-                })
-                .map(|rts| rts.0)
-                .or_else(|| new_receipts.len().checked_sub(1))
-                .map(|idx| idx as u32);
-            self.ui.set_receipts((new_receipts, row_to_select));
-        }
-    }
-
-    fn save_settings(&mut self) {
-        if let Ok(file) = File::options()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open("sqlbon_settings.json")
-        {
-            let settings = Settings {
-                db_file: self.ui.settings_db_path.trim().to_string(),
-                capitalize_item_names: self.ui.capitalize_item_names,
-            };
-            if serde_json::to_writer(file, &settings).is_ok() {
-                self.ui
-                    .set_settings_db_path_status("Successfully connected.".to_string());
-            } else {
-                self.ui.set_settings_db_path_status(
-                    "Could not write to sqlbon_settings.json".to_string(),
+            self.receipt_list.clear();
+            for row in &new_receipts {
+                self.receipt_list.insert_with_values(
+                    None,
+                    &[
+                        (0, &row.id as &dyn ToValue),
+                        (1, &row.date),
+                        (2, &row.store_name),
+                    ],
                 );
             }
-        } else {
-            self.ui
-                .set_settings_db_path_status("Could not write to sqlbon_settings.json".to_string());
+            let row_to_select = if self.ui.receipts.0.is_empty() {
+                // the receipts of a database that was just connected
+                let ids: Vec<_> = new_receipts.iter().map(|row| row.id).collect();
+                let last_used = self
+                    .ui
+                    .connected_db
+                    .as_ref()
+                    .and_then(|db| self.ui.last_receipts.get(db))
+                    .copied();
+                self.ui.receipt_selection.select(&ids, last_used)
+            } else {
+                new_receipts
+                    .iter()
+                    .enumerate()
+                    .find(|(_, row)| {
+                        self.ui
+                            .receipts
+                            .0
+                            .binary_search_by_key(&row.id, |old_row| old_row.id)
+                            .is_err()
+                    })
+                    .map(|rts| rts.0)
+                    .or_else(|| new_receipts.len().checked_sub(1))
+            }
+            .map(|idx| idx as u32);
+            self.ui.set_receipts((new_receipts, row_to_select));
         }
     }
+
+    fn load_item_names(&self) {
+        if let Some(conn) = &self.conn {
+            let mut item_query = conn
+                .prepare(
+                    "SELECT name, COUNT(*) c FROM Item GROUP BY name ORDER BY c DESC, name ASC;",
+                )
+                .unwrap();
+            let names: Vec<String> = item_query
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .filter_map(Result::ok)
+                .collect();
+            self.item_names.clear();
+            for name in &names {
+                self.item_names.insert_with_values(None, &[(0, name)]);
+            }
+        }
+    }
+
+    /// Asks to confirm an item whose line exceeds its amount of [`Ui::large_amounts`] before
+    /// it is added.
+    fn add_item_unless_large(&mut self, item: NewItem) {
+        match exceeded_large_amount(&item, &self.ui.large_amounts, self.ui.rounding) {
+            Some(threshold) => self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
+                add_duplicate_alert::WarningOrigin::LargeAmount { item, threshold },
+            )),
+            None => self.add_item_unless_duplicate(item),
+        }
+    }
+
+    /// Inserts the item, unless the receipt already has an item with the name and
+    /// [`Ui::warn_duplicate_items`] asks to confirm it first.
+    fn add_item_unless_duplicate(&mut self, item: NewItem) {
+        if let Some(conn) = &self.conn {
+            let existence_check_query = if self.ui.warn_duplicate_items {
+                conn.query_row(
+                    "SELECT id, price, unit FROM Item WHERE receipt == ?1 AND name == ?2 ORDER BY id ASC;",
+                    params![item.receipt_id, item.name],
+                    |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, i64>(1)?,
+                            row.get::<_, String>(2)?,
+                        ))
+                    },
+                )
+                .optional()
+            } else {
+                Ok(None)
+            };
+            match existence_check_query {
+                Ok(Some((existing_id, price, unit))) => {
+                    // only the quantity is merged, so the price has to match
+                    let mergeable = price == item.price.minor && unit == item.price.unit.as_str();
+                    self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
+                        add_duplicate_alert::WarningOrigin::Item {
+                            item,
+                            existing_id,
+                            mergeable,
+                        },
+                    ));
+                }
+                Ok(None) => self.insert_item(&item),
+                Err(err) => self.ui.report("add the item", err),
+            }
+        }
+    }
+
+    fn insert_item(&mut self, item: &NewItem) {
+        if let Some(conn) = &self.conn {
+            let insert_query = conn.execute(
+                "INSERT INTO Item (name, quantity, price, unit, receipt, category, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                params![item.name, item.quantity, item.price.minor, item.price.unit.as_str(), item.receipt_id, item.category],
+            );
+            if let Err(err) = insert_query {
+                self.ui.report("add the item", err);
+            } else {
+                self.ui.update_session_summary(|summary| summary.items += 1);
+                self.item_saved(item);
+            }
+        }
+    }
+
+    /// Clears the item fields and updates everything that depends on the items of the receipt.
+    fn item_saved(&mut self, item: &NewItem) {
+        self.ui.reset_item_fields = true;
+        self.load_item_names();
+        if let Some(conn) = &self.conn {
+            self.ui.load_items_today(conn);
+            self.ui.load_recent_items(conn);
+            match BudgetStatus::for_receipt(conn, item.receipt_id, item.price.unit.as_str()) {
+                Ok(budget_status) => self.ui.set_budget_status(budget_status),
+                Err(err) => self.ui.report("load the budget status", err),
+            }
+            self.ui.load_totals(conn, Some(item.receipt_id));
+        }
+        self.dashboard.emit(DashboardMsg::Refresh);
+    }
+
+    /// Reloads everything shown from the database, which another instance of the app or a tool
+    /// may have changed. The selected receipt stays selected if it still exists.
+    fn refresh(&mut self) {
+        let selected_id = self.ui.selected_receipt_id();
+        self.load_stores();
+        self.load_receipts();
+        if let Some(receipt_idx) =
+            selected_id.and_then(|id| self.ui.receipts.0.iter().position(|row| row.id == id))
+        {
+            self.ui.receipts.1 = Some(receipt_idx as u32);
+        }
+        self.load_item_names();
+        if let Some(conn) = &self.conn {
+            self.ui.load_items_today(conn);
+            self.ui.load_recent_items(conn);
+            self.ui.load_rates(conn);
+            let receipt_id = self.ui.selected_receipt_id();
+            self.ui.load_totals(conn, receipt_id);
+            self.ui
+                .set_printed_total(receipt_id.and_then(|id| printed_total_of_receipt(conn, id)));
+        }
+        self.dashboard.emit(DashboardMsg::Refresh);
+    }
+
+    /// Selects the new receipt and, if requested, shows the item tab to fill it.
+    fn receipt_added(&mut self, receipt_id: i64, go_to_items: bool) {
+        self.load_receipts();
+        self.dashboard.emit(DashboardMsg::Refresh);
+        self.ui
+            .update_session_summary(|summary| summary.receipts += 1);
+        let receipt_idx = self
+            .ui
+            .receipts
+            .0
+            .iter()
+            .position(|row| row.id == receipt_id)
+            .map(|idx| idx as u32);
+        if receipt_idx.is_some() && receipt_idx != self.ui.receipts.1 {
+            self.ui.update_receipts(|receipts| receipts.1 = receipt_idx);
+        }
+        if go_to_items && self.ui.tab_layout.is_visible(Tab::Item) {
+            self.ui
+                .set_page(self.ui.tab_layout.position(Tab::Item) as i32);
+        }
+    }
+
+    /// Creates (or truncates) the file at the create path and initializes the schema.
+    fn create_db(&mut self) {
+        let db_path = self.ui.settings_db_create_path.trim().to_string();
+        match schema::create_file(&db_path) {
+            Ok(conn) => {
+                if let Err(err) = set_journal_mode(&conn, self.ui.wal_mode) {
+                    eprintln!("[journal mode]{err:#?}");
+                }
+                self.ui.set_settings_db_path(db_path);
+                self.ui.set_settings_db_create_path_status(Ok(
+                    "Database created successfully.".to_string()
+                ));
+            }
+            Err(err) => {
+                eprintln!("[create db]{err:#?}");
+                self.ui.set_settings_db_create_path_status(Err(err));
+            }
+        }
+    }
+
+    /// Applies the journal mode setting to the connected database and shows the mode in effect.
+    fn apply_journal_mode(&mut self) {
+        if let Some(conn) = &self.conn {
+            match set_journal_mode(conn, self.ui.wal_mode) {
+                Ok(mode) => self.ui.set_journal_mode(format!("journal mode: {mode}")),
+                Err(err) => {
+                    eprintln!("[journal mode]{err:#?}");
+                    self.ui
+                        .set_journal_mode("Could not change the journal mode.".to_string());
+                }
+            }
+        }
+    }
+
+    /// Connects to the database at the settings path and makes it the most recent database.
+    fn connect_db(&mut self) {
+        let db_path = self.ui.settings_db_path.trim().to_string();
+        if db_path.is_empty() {
+            self.ui
+                .set_settings_db_path_status("No File Selected.".to_string());
+        } else if let Ok(conn) = open_db(&db_path) {
+            let conn = Rc::new(conn);
+            self.analysis
+                .emit(AnalysisMsg::ConnectDb(Rc::clone(&conn), db_path.clone()));
+            self.dashboard
+                .emit(DashboardMsg::ConnectDb(Rc::clone(&conn)));
+            if let Some(previous) = self.conn.replace(conn) {
+                close_db(previous);
+            }
+            self.apply_journal_mode();
+            self.ui.set_total(Total::new());
+            self.ui.set_net_total(Total::new());
+            self.ui.set_item_groups(Vec::new());
+            self.ui.set_printed_total(None);
+            self.ui.set_budget_status(None);
+            self.ui.set_connected_db(Some(db_path.clone()));
+            self.ui.receipts = (Vec::new(), None);
+            self.load_stores();
+            self.load_receipts();
+            self.load_item_names();
+            // a database chosen in the app replaces the one of the settings
+            self.ui.settings_db_file = None;
+            // a located database replaces the path it was moved from
+            let missing_db = self.ui.missing_db.clone();
+            self.ui.set_missing_db(None);
+            self.ui.remember_db(db_path, missing_db.as_ref());
+            self.save_settings();
+            self.ui.connect_names();
+            if let Some(conn) = &self.conn {
+                self.ui.load_items_today(conn);
+                self.ui.load_recent_items(conn);
+                self.ui.load_rates(conn);
+            }
+        } else {
+            self.ui
+                .set_settings_db_path_status("Selected File is not a valid Database.".to_string());
+        }
+    }
+
+    /// Writes the settings file. If that fails, a dialog offers to retry or to choose another
+    /// file.
+    fn save_settings(&mut self) {
+        match write_settings(&self.ui.settings_file, &self.settings()) {
+            Ok(()) => {
+                self.ui.unsaved_changes = false;
+                self.ui
+                    .set_settings_db_path_status("Successfully connected.".to_string());
+            }
+            Err(err) => {
+                eprintln!("[save settings]{err:#?}");
+                self.ui.set_settings_db_path_status(format!(
+                    "Could not write to {}",
+                    self.ui.settings_file
+                ));
+                self.ui.set_settings_error(Some(format!(
+                    "'{}' could not be written: {err}\n\nRetry, or choose another file. To read \
+                    the settings from that file on the next start, pass it with --settings or \
+                    set {SETTINGS_ENV}.",
+                    self.ui.settings_file
+                )));
+            }
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            db_file: self
+                .ui
+                .settings_db_file
+                .clone()
+                .unwrap_or_else(|| self.ui.settings_db_path.trim().to_string()),
+            capitalize_item_names: self.ui.item_name_casing == NameCasing::Upper,
+            item_name_casing: self.ui.item_name_casing,
+            csv_delimiter: self.ui.csv_delimiter,
+            sticky_item_fields: self.ui.sticky_item_fields,
+            collapse_name_whitespace: self.ui.collapse_name_whitespace,
+            price_increments: self.ui.price_increments,
+            recent_dbs: self.ui.recent_dbs.clone(),
+            input_panel_position: self.ui.input_panel_position,
+            wal_mode: self.ui.wal_mode,
+            major_units: self.ui.major_units,
+            warn_duplicate_items: self.ui.warn_duplicate_items,
+            warn_duplicate_stores: self.ui.warn_duplicate_stores,
+            warn_duplicate_receipts: self.ui.warn_duplicate_receipts,
+            date_format: self.ui.date_format.clone(),
+            storage_date_format: self.ui.storage_date_format,
+            large_amounts: self.ui.large_amounts.clone(),
+            tabs: self.ui.tabs.clone(),
+            base_unit: self.ui.base_unit.map(|unit| unit.as_str().to_string()),
+            receipt_selection: self.ui.receipt_selection,
+            rounding: self.ui.rounding,
+            exclude_refunds: self.ui.exclude_refunds,
+            decimal_quantities: self.ui.decimal_quantities,
+            required_rows: self.ui.required_rows,
+            last_receipts: self.ui.last_receipts.clone(),
+        }
+    }
+
+    /// Reloads the item total, its groups and the dashboard with or without refunds. Queries of
+    /// the analysis get the choice as `:exclude_refunds`.
+    fn exclude_refunds(&mut self, exclude: bool) {
+        self.ui.set_exclude_refunds(exclude);
+        self.dashboard.emit(DashboardMsg::ExcludeRefunds(exclude));
+        self.analysis.emit(AnalysisMsg::ExcludeRefunds(exclude));
+        if let Some(conn) = &self.conn {
+            let receipt_id = self.ui.selected_receipt_id();
+            self.ui.load_totals(conn, receipt_id);
+        }
+    }
+
+    /// Saves the required inputs and header columns of queries, if they changed.
+    fn update_required_rows(&mut self, required_rows: (usize, usize)) {
+        if required_rows != self.ui.required_rows {
+            self.ui.set_required_rows(required_rows);
+            let (inputs, outputs) = required_rows;
+            self.analysis
+                .emit(AnalysisMsg::RequiredRows(inputs, outputs));
+            self.save_settings();
+        }
+    }
+
+    /// Sets the settings back to the values of a first start and saves them. The database,
+    /// the recent databases and the settings that change the database file, the journal mode
+    /// and the format of stored dates, are kept.
+    fn reset_settings(&mut self) {
+        self.ui.set_item_name_casing(NameCasing::default());
+        self.ui.set_csv_delimiter(csv::Delimiter::default());
+        self.analysis
+            .emit(AnalysisMsg::CsvDelimiter(csv::Delimiter::default()));
+        self.ui.set_sticky_item_fields(false);
+        self.ui.set_collapse_name_whitespace(false);
+        self.ui.set_price_increments(default_price_increments());
+        // the divider keeps its position until the next start
+        self.ui.input_panel_position = None;
+        self.ui.set_major_units(false);
+        self.dashboard.emit(DashboardMsg::MajorUnits(false));
+        self.analysis.emit(AnalysisMsg::MajorUnits(false));
+        self.ui.set_warn_duplicate_items(true);
+        self.ui.set_warn_duplicate_stores(true);
+        self.ui.set_warn_duplicate_receipts(true);
+        self.ui.set_date_format(String::new());
+        self.ui.set_large_amounts(HashMap::new());
+        self.ui.set_tabs(TabLayout::default());
+        self.ui.set_base_unit(None);
+        self.ui.set_receipt_selection(ReceiptSelection::default());
+        self.ui.set_rounding(Rounding::default());
+        self.exclude_refunds(false);
+        self.ui.set_decimal_quantities(false);
+        self.ui.set_required_rows(default_required_rows());
+        let (inputs, outputs) = default_required_rows();
+        self.analysis
+            .emit(AnalysisMsg::RequiredRows(inputs, outputs));
+        self.save_settings();
+        self.ui
+            .set_settings_status("The settings were reset to their defaults.".to_string());
+    }
 }
 
 #[relm4::component]
 impl SimpleComponent for App {
-    type Init = ();
+    type Init = Args;
     type Input = Msg;
     type Output = ();
     type Widgets = AppWidgets;
@@ -319,6 +1722,10 @@ impl SimpleComponent for App {
         gtk::Label {
             set_label: "Receipt",
         },
+        #[name(tab_dashboard)]
+        gtk::Label {
+            set_label: "Dashboard",
+        },
         #[name(tab_item)]
         gtk::Label {
             set_label: "Item",
@@ -331,11 +1738,165 @@ impl SimpleComponent for App {
         gtk::Label {
             set_label: "Settings",
         },
+        #[name(about_dialog)]
+        gtk::AboutDialog {
+            set_program_name: Some("SQLBon"),
+            set_version: Some(env!("CARGO_PKG_VERSION")),
+            set_comments: Some("Insert receipts into a sqlite database and analyse them."),
+            set_license_type: gtk::License::Gpl30,
+            set_modal: true,
+            set_hide_on_close: true,
+        },
+        #[name(analysis_window)]
+        gtk::Window {
+            set_title: Some("SQLBon Analysis"),
+            set_default_width: 1000,
+            set_default_height: 700,
+            set_hide_on_close: true,
+            #[track(model.ui.changed(Ui::analysis_popped_out()))]
+            set_visible: model.ui.analysis_popped_out,
+            connect_close_request[sender] => move |_| {
+                sender.input(Msg::PopOutAnalysis(false));
+                gtk::Inhibit(false)
+            },
+        },
+        #[name(integrity_dialog)]
+        gtk::MessageDialog {
+            set_modal: true,
+            set_hide_on_close: true,
+            set_text: Some("Database check"),
+            #[track(model.ui.changed(Ui::integrity_report()))]
+            set_secondary_text: model.ui.integrity_report.as_deref(),
+            #[track(model.ui.changed(Ui::integrity_report()))]
+            set_visible: model.ui.integrity_report.is_some(),
+            add_button: ("Close", gtk::ResponseType::Close),
+            connect_response[sender] => move |_, _| {
+                sender.input(Msg::CloseIntegrityReport);
+            },
+        },
+        #[name(error_dialog)]
+        gtk::MessageDialog {
+            set_modal: true,
+            set_hide_on_close: true,
+            set_text: Some("Something went wrong"),
+            #[track(model.ui.changed(Ui::app_error()))]
+            set_secondary_text: model.ui.app_error.as_deref(),
+            #[track(model.ui.changed(Ui::app_error()))]
+            set_visible: model.ui.app_error.is_some(),
+            add_button: ("OK", gtk::ResponseType::Close),
+            connect_response[sender] => move |_, _| {
+                sender.input(Msg::CloseError);
+            },
+        },
+        #[name(settings_error_dialog)]
+        gtk::MessageDialog {
+            set_modal: true,
+            set_hide_on_close: true,
+            set_text: Some("The settings could not be saved"),
+            #[track(model.ui.changed(Ui::settings_error()))]
+            set_secondary_text: model.ui.settings_error.as_deref(),
+            #[track(model.ui.changed(Ui::settings_error()))]
+            set_visible: model.ui.settings_error.is_some(),
+            add_button: ("Cancel", gtk::ResponseType::Cancel),
+            add_button: ("Choose File", gtk::ResponseType::Apply),
+            add_button: ("Retry", gtk::ResponseType::Accept),
+            connect_response[sender] => move |_, response| {
+                sender.input(match response {
+                    gtk::ResponseType::Accept => Msg::SaveSettings,
+                    gtk::ResponseType::Apply => Msg::ChooseSettingsFile,
+                    _ => Msg::CloseSettingsError,
+                });
+            },
+        },
         #[root]
         #[name(main_window)]
         gtk::ApplicationWindow {
             set_default_width: 1300,
-            set_title: Some("SQLBon"),
+            #[track(model.ui.changed(Ui::connected_db()) || model.ui.changed(Ui::settings_db_path()))]
+            set_title: Some(&window_title(
+                model.ui.connected_db.as_deref(),
+                !model.ui.settings_db_path.trim().is_empty(),
+            )),
+            connect_close_request[sender] => move |_| {
+                sender.input(Msg::Quit);
+                gtk::Inhibit(true)
+            },
+
+            #[wrap(Some)]
+            set_titlebar = &gtk::HeaderBar {
+                pack_start = &gtk::ComboBoxText {
+                    set_tooltip_text: Some("Switch between recently connected databases"),
+                    #[track(model.ui.changed(Ui::recent_dbs()))]
+                    append_all_and_select: (model.ui.recent_dbs.iter().cloned(), Some(0)),
+                    connect_changed[sender] => move |db_switcher| {
+                        sender.input(Msg::SwitchDb(db_switcher.active()));
+                    },
+                },
+                pack_start = &gtk::Button {
+                    set_icon_name: "view-refresh-symbolic",
+                    set_tooltip_text: Some("Reload the database after it was changed elsewhere (F5)"),
+                    #[track(model.ui.changed(Ui::connected_db()))]
+                    set_sensitive: model.ui.connected_db.is_some(),
+                    connect_clicked[sender] => move |_| {
+                        sender.input(Msg::Refresh);
+                    },
+                },
+                pack_end = &gtk::MenuButton {
+                    set_icon_name: "open-menu-symbolic",
+                    #[wrap(Some)]
+                    set_popover: app_menu = &gtk::Popover {
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            gtk::Button {
+                                set_label: "About",
+                                connect_clicked[app_menu, about_dialog] => move |_| {
+                                    app_menu.popdown();
+                                    about_dialog.present();
+                                },
+                            },
+                            gtk::Button {
+                                set_label: "Quit",
+                                connect_clicked[main_window] => move |_| {
+                                    main_window.close();
+                                },
+                            },
+                        },
+                    },
+                },
+                pack_end = &gtk::Label {
+                    add_css_class: "dim-label",
+                    #[track(model.ui.changed(Ui::status()))]
+                    set_label: &model.ui.status,
+                },
+            },
+
+            add_controller = &gtk::EventControllerKey {
+                set_propagation_phase: gtk::PropagationPhase::Capture,
+                connect_key_pressed[sender] => move |_, key, _, modifier| {
+                    let msg = if modifier.contains(gtk::gdk::ModifierType::CONTROL_MASK) {
+                        match key {
+                            gtk::gdk::Key::Tab | gtk::gdk::Key::Page_Down => Some(Msg::CyclePage(1)),
+                            gtk::gdk::Key::ISO_Left_Tab | gtk::gdk::Key::Page_Up => Some(Msg::CyclePage(-1)),
+                            _ => None,
+                        }
+                    } else if modifier.contains(gtk::gdk::ModifierType::ALT_MASK) {
+                        key.to_unicode()
+                            .and_then(|c| c.to_digit(10))
+                            .filter(|digit| (1..=Tab::ALL.len() as u32).contains(digit))
+                            .map(|digit| Msg::SelectPage(digit as i32 - 1))
+                    } else if key == gtk::gdk::Key::F5 {
+                        Some(Msg::Refresh)
+                    } else {
+                        None
+                    };
+                    if let Some(msg) = msg {
+                        sender.input(msg);
+                        gtk::Inhibit(true)
+                    } else {
+                        gtk::Inhibit(false)
+                    }
+                },
+            },
 
             #[name(notebook)]
             gtk::Notebook {
@@ -345,7 +1906,11 @@ impl SimpleComponent for App {
                 set_halign: gtk::Align::Fill,
                 #[track(model.ui.changed(Ui::page()))]
                 set_page: model.ui.page,
+                connect_switch_page[sender] => move |_, _, page| {
+                    sender.input(Msg::PageChanged(page as i32));
+                },
 
+                append_page: (model.dashboard.widget(), Some(&tab_dashboard)),
                 append_page[Some(&tab_store)] = &gtk::Box {
                     set_vexpand: true,
                     set_hexpand: true,
@@ -373,7 +1938,7 @@ impl SimpleComponent for App {
                             #[track(model.ui.reset_store_fields)]
                             set_text: "",
                             connect_changed[sender] => move |store_name| {
-                                sender.input(Msg::ValidateStoreName(store_name.text()));
+                                sender.input(Msg::ValidateName(NameField::StoreName, store_name.text()));
                             },
                         },
                         gtk::Label {
@@ -386,16 +1951,41 @@ impl SimpleComponent for App {
                             #[track(model.ui.reset_store_fields)]
                             set_text: "",
                             connect_changed[sender] => move |store_location| {
-                                sender.input(Msg::ValidateStoreLocation(store_location.text()));
+                                sender.input(Msg::ValidateName(NameField::StoreLocation, store_location.text()));
                             },
                         },
+                        gtk::Label {
+                            set_label: "city:",
+                        },
+                        #[name(city_entry)]
+                        gtk::Entry {
+                            set_hexpand: true,
+                            set_halign: gtk::Align::Fill,
+                            set_placeholder_text: Some("optional"),
+                            set_tooltip_text: Some("Used to group the spending by city, e.g. on the dashboard or in queries."),
+                            #[track(model.ui.reset_store_fields)]
+                            set_text: "",
+                        },
+                        gtk::Label {
+                            set_label: "country:",
+                        },
+                        #[name(country_entry)]
+                        gtk::Entry {
+                            set_hexpand: true,
+                            set_halign: gtk::Align::Fill,
+                            set_placeholder_text: Some("optional"),
+                            #[track(model.ui.reset_store_fields)]
+                            set_text: "",
+                        },
                     },
                     gtk::Button {
                         set_label: "Add",
-                        connect_clicked[sender, store_name_entry, location_entry] => move |_| {
+                        connect_clicked[sender, store_name_entry, location_entry, city_entry, country_entry] => move |_| {
                             sender.input(Msg::AddStore(Store{
                                 name: store_name_entry.text(),
                                 location: location_entry.text(),
+                                city: city_entry.text(),
+                                country: country_entry.text(),
                             }));
                         },
                         #[track(
@@ -406,6 +1996,71 @@ impl SimpleComponent for App {
                             model.ui.store_name_valid == NameStatus::Valid &&
                             model.ui.store_location_valid == NameStatus::Valid,
                     },
+                    gtk::Separator {},
+                    gtk::Box {
+                        set_hexpand: true,
+                        set_halign: gtk::Align::Fill,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_margin_all: 5,
+                        set_spacing: 5,
+
+                        gtk::Label {
+                            set_label: "merge:",
+                        },
+                        append: model.merge_source.widget(),
+                        gtk::Label {
+                            set_label: "into:",
+                        },
+                        append: model.merge_target.widget(),
+                        gtk::Button {
+                            set_label: "Merge",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::MergeStores);
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                    },
+                    gtk::Box {
+                        set_hexpand: true,
+                        set_halign: gtk::Align::Fill,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_margin_all: 5,
+                        set_spacing: 5,
+
+                        gtk::Label {
+                            set_label: "monthly budget of:",
+                        },
+                        append: model.budget_store.widget(),
+                        #[name(budget_amount_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: Unit::ALL[0].digits(),
+                            set_range: (0.0, 1000000.0),
+                            set_increments: (1.0, 100.0),
+                        },
+                        #[name(budget_unit_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                            connect_changed[budget_amount_entry] => move |unit_entry| {
+                                if let Some(unit) = unit_entry.active().and_then(|idx| Unit::ALL.get(idx as usize)) {
+                                    budget_amount_entry.set_digits(unit.digits());
+                                }
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Set Budget",
+                            set_tooltip_text: Some("A budget of 0 removes the budget."),
+                            connect_clicked[sender, budget_amount_entry, budget_unit_entry] => move |_| {
+                                sender.input(Msg::SetBudget {
+                                    amount: budget_amount_entry.value(),
+                                    unit: budget_unit_entry.active().unwrap().try_into().unwrap(),
+                                });
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                    },
                 },
 
                 append_page[Some(&tab_receipt)] = &gtk::Box {
@@ -429,15 +2084,7 @@ impl SimpleComponent for App {
                             set_label: "store:",
                         },
 
-                        #[name(store_entry)]
-                        gtk::ComboBoxText {
-                            set_hexpand: true,
-                            set_vexpand: false,
-                            set_halign: gtk::Align::Fill,
-                            set_valign: gtk::Align::Center,
-                            #[track(model.ui.changed(Ui::stores()))]
-                            append_all_and_select: ( model.ui.stores.0.iter().map(|row| format!("{} ({}) #{}", row.name, row.location, row.id)), model.ui.stores.1),
-                        },
+                        append: model.receipt_store.widget(),
 
                         gtk::Label {
                             set_label: "date:",
@@ -445,18 +2092,93 @@ impl SimpleComponent for App {
 
                         #[name(date)]
                         gtk::Calendar {},
+
+                        gtk::Label {
+                            set_label: "currency:",
+                        },
+                        #[name(currency_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (ReceiptCurrency::labels(), Some(0)),
+                            set_tooltip_text: Some("The unit the items of the receipt are expected to have, items with another unit have to be confirmed."),
+                        },
+                    },
+                    gtk::Label {
+                        set_label: "Add a store first.",
+                        #[watch]
+                        set_visible: model.conn.is_some() && model.ui.stores.0.is_empty(),
                     },
                     gtk::Button {
                         set_label: "Add",
-                        connect_clicked[sender, date, store_entry] => move |_| {
+                        connect_clicked[sender, date, currency_entry] => move |_| {
+                            sender.input(Msg::AddReceipt(Receipt{
+                                date: date.date(),
+                                currency: ReceiptCurrency::from_idx(currency_entry.active()),
+                                go_to_items: false,
+                            }));
+                        },
+                        #[watch]
+                        set_sensitive: model.conn.is_some() && !model.ui.stores.0.is_empty(),
+                    },
+                    gtk::Button {
+                        set_label: "Add & Go to Items",
+                        connect_clicked[sender, date, currency_entry] => move |_| {
                             sender.input(Msg::AddReceipt(Receipt{
-                                store_idx: store_entry.active(),
                                 date: date.date(),
+                                currency: ReceiptCurrency::from_idx(currency_entry.active()),
+                                go_to_items: true,
                             }));
                         },
                         #[watch]
+                        set_sensitive: model.conn.is_some() && !model.ui.stores.0.is_empty(),
+                    },
+                    gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        set_min_content_height: 200,
+                        #[name(receipt_tree)]
+                        gtk::TreeView {
+                            set_hexpand: true,
+                            set_vexpand: true,
+                            set_model: Some(&model.receipt_list),
+                            append_column: &text_column("id", 0),
+                            append_column: &text_column("date", 1),
+                            append_column: &text_column("store", 2),
+                        },
+                    },
+                    gtk::Button {
+                        set_label: "Delete Selected",
+                        connect_clicked[sender, receipt_tree] => move |_| {
+                            let (paths, receipts) = receipt_tree.selection().selected_rows();
+                            let ids: Vec<i64> = paths
+                                .iter()
+                                .filter_map(|path| receipts.iter(path))
+                                .map(|iter| receipts.get(&iter, 0))
+                                .collect();
+                            if !ids.is_empty() {
+                                sender.input(Msg::DeleteReceipts(ids));
+                            }
+                        },
+                        #[watch]
                         set_sensitive: model.conn.is_some(),
                     },
+                    gtk::Box {
+                        set_halign: gtk::Align::Center,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+
+                        gtk::Button {
+                            set_label: "Import Statement",
+                            set_tooltip_text: Some("Creates a receipt with a single item for every row of a CSV file with a date, merchant and amount column. Merchants are matched to stores by name."),
+                            connect_clicked[sender, statement_unit_entry] => move |_| {
+                                sender.input(Msg::ImportStatement(statement_unit_entry.active()));
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                        #[name(statement_unit_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                        },
+                    },
                 },
                 append_page[Some(&tab_item)] = &gtk::Box {
                     set_vexpand: true,
@@ -485,8 +2207,11 @@ impl SimpleComponent for App {
                             set_halign: gtk::Align::Fill,
                             #[track(model.ui.reset_item_fields)]
                             set_text: "",
+                            #[track(model.ui.changed(Ui::prefill_item()))]
+                            set_text: model.ui.prefill_item.as_ref().map_or("", |item| item.name.as_str()),
+                            set_completion: Some(&item_name_completion),
                             connect_changed[sender] => move |item_name| {
-                                sender.input(Msg::ValidateItemName(item_name.text()));
+                                sender.input(Msg::ValidateName(NameField::ItemName, item_name.text()));
                             },
                         },
 
@@ -498,28 +2223,59 @@ impl SimpleComponent for App {
                             set_hexpand: true,
                             set_halign: gtk::Align::Fill,
                             set_numeric: true,
-                            set_digits: 0,
-                            set_snap_to_ticks: true,
-                            set_range: (1.0, 100.0),
+                            #[track(model.ui.changed(Ui::decimal_quantities()))]
+                            set_digits: if model.ui.decimal_quantities { QUANTITY_DIGITS } else { 0 },
+                            // ticks are whole units, weights are entered with decimals
+                            #[track(model.ui.changed(Ui::decimal_quantities()))]
+                            set_snap_to_ticks: !model.ui.decimal_quantities,
+                            #[track(model.ui.changed(Ui::decimal_quantities()))]
+                            set_range: (if model.ui.decimal_quantities { 0.1_f64.powi(QUANTITY_DIGITS as i32) } else { 1.0 }, 100.0),
                             set_increments: (1.0, 5.0),
-                            #[track(model.ui.reset_item_fields)]
+                            #[track(model.ui.reset_item_fields && !model.ui.sticky_item_fields)]
                             set_value: 1.0,
                         },
 
                         gtk::Label {
-                            #[track(model.ui.changed(Ui::selected_unit()))]
-                            set_label: &format!("price (×{})", model.ui.selected_unit.scale()),
+                            #[track(model.ui.changed(Ui::price_is_line_total()))]
+                            set_label: if model.ui.price_is_line_total { "line total:" } else { "price:" },
                         },
                         #[name(price_entry)]
                         gtk::SpinButton {
                             set_hexpand: true,
                             set_halign: gtk::Align::Fill,
                             set_numeric: true,
-                            set_digits: 0,
-                            set_range: (-1000000.0, 1000000.0),
-                            set_increments: (10.0, 500.0),
+                            #[track(model.ui.changed(Ui::selected_unit()))]
+                            set_digits: model.ui.selected_unit.digits(),
+                            set_range: (-10000.0, 10000.0),
+                            #[track(model.ui.changed(Ui::price_increments()))]
+                            set_increments: (model.ui.price_increments.0, model.ui.price_increments.1),
                             #[track(model.ui.reset_item_fields, )]
                             set_value: 1.0,
+                            #[track(model.ui.changed(Ui::prefill_item()))]
+                            set_value: model.ui.prefill_item.as_ref().map_or(1.0, |item| item.price.to_major()),
+                        },
+                        gtk::Label {
+                            #[track(model.ui.changed(Ui::selected_unit()))]
+                            set_label: model.ui.selected_unit.symbol(),
+                            #[track(model.ui.changed(Ui::selected_unit()))]
+                            set_tooltip_text: Some(model.ui.selected_unit.as_str()),
+                        },
+                        gtk::Label {
+                            add_css_class: "dim-label",
+                            #[track(model.ui.changed(Ui::last_price()) || model.ui.changed(Ui::date_format()) || model.ui.changed(Ui::major_units()))]
+                            set_label: &model.ui.last_price.as_ref().map(|last| last.label(&model.ui.date_format, model.ui.major_units)).unwrap_or_default(),
+                            #[track(model.ui.changed(Ui::last_price()))]
+                            set_visible: model.ui.last_price.is_some(),
+                        },
+                        #[name(line_total_check)]
+                        gtk::CheckButton {
+                            set_label: Some("for all"),
+                            set_tooltip_text: Some("The price is what all units together cost. The price per unit is stored, rounded to the smallest coin."),
+                            #[track(model.ui.changed(Ui::price_is_line_total()))]
+                            set_active: model.ui.price_is_line_total,
+                            connect_toggled[sender] => move |check| {
+                                sender.input(Msg::PriceIsLineTotal(check.is_active()));
+                            },
                         },
 
                         gtk::Label {
@@ -528,54 +2284,296 @@ impl SimpleComponent for App {
                         #[name(unit_entry)]
                         gtk::ComboBoxText {
                             append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                            #[track(model.ui.changed(Ui::prefill_item()))]
+                            set_active: model.ui.prefill_item.as_ref()
+                                .and_then(|item| Unit::ALL.iter().position(|unit| *unit == item.price.unit))
+                                .map(|idx| idx as u32)
+                                .or(Some(0)),
+                            #[track(model.ui.changed(Ui::receipt_currency()) && model.ui.receipt_currency.is_some())]
+                            set_active: model.ui.receipt_currency
+                                .and_then(|currency| Unit::ALL.iter().position(|unit| *unit == currency))
+                                .map(|idx| idx as u32),
                             connect_changed[sender] => move |ue| {
                                 sender.input(Msg::SelectUnit(ue.active().unwrap().try_into().unwrap()));
                             }
                         },
 
+                        gtk::Label {
+                            set_label: "category:",
+                        },
+                        #[name(category_entry)]
+                        gtk::Entry {
+                            set_placeholder_text: Some("optional"),
+                            set_tooltip_text: Some("The items of a receipt can be grouped by their category."),
+                            #[track(model.ui.reset_item_fields && !model.ui.sticky_item_fields)]
+                            set_text: "",
+                        },
+
                         gtk::Label {
                             set_label: "receipt:",
                         },
                         #[name(receipt_entry)]
                         gtk::ComboBoxText {
-                            #[track(model.ui.changed(Ui::receipts()))]
-                            append_all_and_select: (model.ui.receipts.0.iter().map(|row| format!("{} ({}) #{}", row.date, row.store_name, row.id)), model.ui.receipts.1),
+                            #[track(model.ui.changed(Ui::receipts()) || model.ui.changed(Ui::date_format()))]
+                            append_all_and_select_by: (
+                                &model.ui.receipts.0,
+                                |row| format!("{} ({}) #{}", date::display_date(&row.date, &model.ui.date_format), row.store_name, row.id),
+                                |row| row.id,
+                                model.ui.selected_receipt_id(),
+                            ),
                             connect_changed[sender] => move |receipt| {
                                 sender.input(Msg::ReceiptChanged(receipt.active()));
                             }
                         },
                     },
                     gtk::Label {
-                        #[track(model.ui.changed(Ui::total()))]
-                        set_label: &format!("{}", model.ui.total),
+                        #[track(model.ui.changed(Ui::rounding_note()))]
+                        set_label: &model.ui.rounding_note,
+                        #[track(model.ui.changed(Ui::rounding_note()))]
+                        set_visible: !model.ui.rounding_note.is_empty(),
                     },
-                    gtk::Button {
-                        set_label: "Add",
-                        connect_clicked[sender, item_name_entry, receipt_entry, quantity_entry, unit_entry, price_entry] => move |_| {
-                            sender.input(Msg::AddItem(Item{
-                                name: item_name_entry.text(),
-                                quantity: quantity_entry.value_as_int() as _,
-                                price: price_entry.value_as_int(),
-                                unit: unit_entry.active().unwrap().try_into().unwrap(),
-                                receipt_idx: receipt_entry.active(),
-                            }));
-                        },
-                        #[track(model.ui.changed(Ui::item_name_valid()))]
-                        set_sensitive: model.ui.item_name_valid == NameStatus::Valid,
+                    gtk::Label {
+                        add_css_class: "currency-mismatch",
+                        #[track(model.ui.changed(Ui::selected_unit()) || model.ui.changed(Ui::receipt_currency()))]
+                        set_label: &currency_warning(model.ui.selected_unit, model.ui.receipt_currency),
+                        #[track(model.ui.changed(Ui::selected_unit()) || model.ui.changed(Ui::receipt_currency()))]
+                        set_visible: !currency_warning(model.ui.selected_unit, model.ui.receipt_currency).is_empty(),
                     },
-                },
-                append_page: (model.analysis.widget(), Some(&tab_analysis)),
-                append_page[Some(&tab_settings)] = &gtk::Grid {
-                    set_hexpand: true,
-                    set_vexpand: true,
-                    set_halign: gtk::Align::Fill,
-                    set_valign: gtk::Align::Center,
-                    set_orientation: gtk::Orientation::Horizontal,
-
-                    attach[1, 1, 1, 1] = &gtk::Button {
-                        set_label: "Connect Database",
-                        connect_clicked[sender] => move |_| {
-                            sender.input(Msg::ConnectDb);
+                    gtk::FlowBox {
+                        set_selection_mode: gtk::SelectionMode::None,
+                        set_tooltip_text: Some("Recently added items, click to fill in their name, price and unit."),
+                        #[track(model.ui.changed(Ui::recent_items()) || model.ui.changed(Ui::major_units()))]
+                        show_recent_items: (&model.ui.recent_items, model.ui.major_units, &sender),
+                    },
+                    gtk::Label {
+                        #[track(model.ui.changed(Ui::total()) || model.ui.changed(Ui::major_units()))]
+                        set_label: &amount_text(&model.ui.total, model.ui.major_units),
+                    },
+                    gtk::Label {
+                        add_css_class: "dim-label",
+                        #[track(model.ui.changed(Ui::exclude_refunds()))]
+                        set_label: if model.ui.exclude_refunds {
+                            "gross, without refunds"
+                        } else {
+                            "net, refunds subtracted"
+                        },
+                    },
+                    gtk::Label {
+                        add_css_class: "dim-label",
+                        set_tooltip_text: Some("Approximate, converted with the rates in the settings."),
+                        #[track(model.ui.changed(Ui::total()) || model.ui.changed(Ui::base_unit()) || model.ui.changed(Ui::rates()) || model.ui.changed(Ui::rounding()) || model.ui.changed(Ui::major_units()))]
+                        set_label: &combined_total_text(&model.ui.total, model.ui.base_unit, &model.ui.rates, model.ui.rounding, model.ui.major_units),
+                        #[track(model.ui.changed(Ui::total()) || model.ui.changed(Ui::base_unit()))]
+                        set_visible: model.ui.base_unit.map_or(false, |base| model.ui.total.0.iter().any(|row| row.unit() != base.as_str())),
+                    },
+                    gtk::Expander {
+                        set_label: Some("items of the receipt"),
+                        #[wrap(Some)]
+                        set_child = &gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_spacing: 5,
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Horizontal,
+                                set_spacing: 5,
+                                gtk::Label {
+                                    set_label: "group by:",
+                                },
+                                gtk::ComboBoxText {
+                                    append_all: GroupBy::ALL.iter().map(ToString::to_string),
+                                    #[track(model.ui.changed(Ui::group_items_by()))]
+                                    set_active: Some(model.ui.group_items_by.into()),
+                                    connect_changed[sender] => move |cb| {
+                                        if let Some(group_by) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                            sender.input(Msg::GroupItemsBy(group_by));
+                                        }
+                                    }
+                                },
+                                gtk::Button {
+                                    set_label: "Export CSV",
+                                    set_tooltip_text: Some("Writes the grouped items with their subtotals to a CSV file."),
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(Msg::ExportItemGroups);
+                                    },
+                                    #[track(model.ui.changed(Ui::item_groups()))]
+                                    set_sensitive: !model.ui.item_groups.is_empty(),
+                                },
+                            },
+                            gtk::ScrolledWindow {
+                                set_min_content_height: 150,
+                                gtk::TreeView {
+                                    set_hexpand: true,
+                                    append_column: &text_column("item", 0),
+                                    append_column: &text_column("amount", 1),
+                                    #[track(model.ui.changed(Ui::item_groups()) || model.ui.changed(Ui::major_units()))]
+                                    show_item_groups: (&model.ui.item_groups, model.ui.major_units),
+                                },
+                            },
+                        },
+                    },
+                    gtk::Label {
+                        #[track(model.ui.changed(Ui::budget_status()))]
+                        set_visible: model.ui.budget_status.is_some(),
+                        #[track(model.ui.changed(Ui::budget_status()) || model.ui.changed(Ui::major_units()))]
+                        set_label: &model.ui.budget_status.as_ref().map(|status| amount_text(status, model.ui.major_units)).unwrap_or_default(),
+                        #[track(model.ui.changed(Ui::budget_status()))]
+                        set_css_classes: if model.ui.budget_status.as_ref().map_or(false, BudgetStatus::is_over) {
+                            &["over-budget"]
+                        } else {
+                            &[]
+                        },
+                    },
+                    gtk::Box {
+                        set_halign: gtk::Align::Center,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+
+                        gtk::Label {
+                            set_label: "printed total:",
+                        },
+                        #[name(printed_total_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            #[track(model.ui.changed(Ui::printed_total()))]
+                            set_digits: model.ui.printed_total.map_or(Unit::ALL[0], |printed| printed.unit).digits(),
+                            set_range: (-1000000.0, 1000000.0),
+                            set_increments: (0.1, 5.0),
+                            #[track(model.ui.changed(Ui::printed_total()))]
+                            set_value: model.ui.printed_total.as_ref().map_or(0.0, Money::to_major),
+                        },
+                        #[name(printed_total_unit_entry)]
+                        gtk::ComboBoxText {
+                            #[track(model.ui.changed(Ui::printed_total()))]
+                            append_all_and_select: (
+                                Unit::ALL.iter().map(|unit| unit.as_str().to_string()),
+                                model.ui.printed_total.as_ref()
+                                    .and_then(|printed| Unit::ALL.iter().position(|unit| *unit == printed.unit))
+                                    .map(|idx| idx as u32)
+                                    .or(Some(0)),
+                            ),
+                            connect_changed[printed_total_entry] => move |unit_entry| {
+                                if let Some(unit) = unit_entry.active().and_then(|idx| Unit::ALL.get(idx as usize)) {
+                                    printed_total_entry.set_digits(unit.digits());
+                                }
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Set",
+                            connect_clicked[sender, receipt_entry, printed_total_entry, printed_total_unit_entry] => move |_| {
+                                sender.input(Msg::SetPrintedTotal {
+                                    receipt_idx: receipt_entry.active(),
+                                    printed_total: Some((
+                                        printed_total_entry.value(),
+                                        printed_total_unit_entry.active().unwrap().try_into().unwrap(),
+                                    )),
+                                });
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                        gtk::Button {
+                            set_label: "Clear",
+                            connect_clicked[sender, receipt_entry] => move |_| {
+                                sender.input(Msg::SetPrintedTotal {
+                                    receipt_idx: receipt_entry.active(),
+                                    printed_total: None,
+                                });
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                        gtk::Label {
+                            #[track(model.ui.changed(Ui::net_total()) || model.ui.changed(Ui::printed_total()) || model.ui.changed(Ui::major_units()))]
+                            set_label: &reconciliation_text(&model.ui.net_total, &model.ui.printed_total, model.ui.major_units),
+                            #[track(model.ui.changed(Ui::net_total()) || model.ui.changed(Ui::printed_total()))]
+                            set_css_classes: if model.ui.printed_total.as_ref().map_or(false, |printed| model.ui.net_total.delta(printed) != 0) {
+                                &["total-mismatch"]
+                            } else {
+                                &[]
+                            },
+                        },
+                    },
+                    gtk::Label {
+                        set_label: "Add a receipt first.",
+                        #[watch]
+                        set_visible: model.conn.is_some() && model.ui.receipts.0.is_empty(),
+                    },
+                    #[name(item_add_button)]
+                    gtk::Button {
+                        set_label: "Add",
+                        connect_clicked[sender, item_name_entry, receipt_entry, quantity_entry, unit_entry, price_entry, line_total_check, category_entry] => move |_| {
+                            sender.input(Msg::AddItem(Item{
+                                name: item_name_entry.text(),
+                                quantity: round_quantity(quantity_entry.value()),
+                                price: price_entry.value(),
+                                unit: unit_entry.active().unwrap().try_into().unwrap(),
+                                line_total: line_total_check.is_active(),
+                                category: category_entry.text(),
+                                receipt_idx: receipt_entry.active(),
+                            }));
+                        },
+                        #[track(model.ui.changed(Ui::item_name_valid()) || model.ui.changed(Ui::receipts()))]
+                        set_sensitive: model.ui.item_name_valid == NameStatus::Valid && !model.ui.receipts.0.is_empty(),
+                        #[track(model.ui.changed(Ui::item_name_valid()))]
+                        set_tooltip_text: Some(&model.ui.item_name_valid.hint("item name")),
+                    },
+                    gtk::Button {
+                        set_label: "Import CSV",
+                        set_tooltip_text: Some("Adds the items of a CSV file with a name, quantity, price and unit column to the selected receipt."),
+                        connect_clicked[sender, receipt_entry] => move |_| {
+                            sender.input(Msg::ImportItems(receipt_entry.active()));
+                        },
+                        #[watch]
+                        set_sensitive: model.conn.is_some() && !model.ui.receipts.0.is_empty(),
+                    },
+                },
+                append_page[Some(&tab_analysis)] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 5,
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        set_margin_all: 5,
+                        gtk::Label {
+                            set_hexpand: true,
+                            set_xalign: 0.0,
+                            add_css_class: "dim-label",
+                            #[track(model.ui.changed(Ui::analysis_popped_out()))]
+                            set_label: if model.ui.analysis_popped_out {
+                                "The analysis is shown in its own window."
+                            } else {
+                                ""
+                            },
+                        },
+                        gtk::ToggleButton {
+                            set_label: "Pop Out",
+                            set_tooltip_text: Some("Shows the analysis in its own window, e.g. on a second monitor, while items are entered here."),
+                            #[track(model.ui.changed(Ui::analysis_popped_out()))]
+                            set_active: model.ui.analysis_popped_out,
+                            connect_toggled[sender] => move |toggle| {
+                                sender.input(Msg::PopOutAnalysis(toggle.is_active()));
+                            },
+                        },
+                    },
+                    #[name(analysis_page)]
+                    gtk::Box {
+                        set_vexpand: true,
+                        set_hexpand: true,
+                        append: model.analysis.widget(),
+                        #[track(model.ui.changed(Ui::analysis_popped_out()))]
+                        pop_out: (model.analysis.widget(), &analysis_window, model.ui.analysis_popped_out),
+                    },
+                },
+                append_page[Some(&tab_settings)] = &gtk::Grid {
+                    set_hexpand: true,
+                    set_vexpand: true,
+                    set_halign: gtk::Align::Fill,
+                    set_valign: gtk::Align::Center,
+                    set_orientation: gtk::Orientation::Horizontal,
+
+                    attach[1, 1, 1, 1] = &gtk::Button {
+                        set_label: "Connect Database",
+                        connect_clicked[sender] => move |_| {
+                            sender.input(Msg::ConnectDb);
                         },
                     },
                     attach[2, 1, 1, 1] = &gtk::Entry {
@@ -593,6 +2591,25 @@ impl SimpleComponent for App {
                         #[track(model.ui.changed(Ui::settings_db_path_status()))]
                         set_label: &model.ui.settings_db_path_status,
                     },
+                    attach[3, 2, 1, 1] = &gtk::Box {
+                        set_spacing: 5,
+                        #[track(model.ui.changed(Ui::missing_db()))]
+                        set_visible: model.ui.missing_db.is_some(),
+                        gtk::Button {
+                            set_label: "Reconnect",
+                            set_tooltip_text: Some("Tries to open the database of the settings again, e.g. after its drive was mounted."),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::ReconnectDb);
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Locate Database",
+                            add_css_class: "suggested-action",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::LocateDb);
+                            },
+                        },
+                    },
                     attach[1, 3, 1, 1] = &gtk::Button {
                         set_label: "Create Database",
                         connect_clicked[sender] => move |_| {
@@ -609,21 +2626,548 @@ impl SimpleComponent for App {
                         connect_clicked[sender] => move |_| {
                             sender.input(Msg::OpenCreateDbDialog);
                         },
-                    },
-                    attach[2, 4, 1, 1] = &gtk::Label {
-                        #[track(model.ui.changed(Ui::settings_db_create_path_status()))]
-                        set_label: &model.ui.settings_db_create_path_status,
-                    },
-                    attach[1, 5, 1, 1] = &gtk::Label {
-                        set_label: "Capitalize item names:",
-                    },
-                    attach[2, 5, 1, 1] = &gtk::CheckButton {
-                        set_label: Some("Capitalize"),
-                        #[track(model.ui.changed(Ui::capitalize_item_names()))]
-                        set_active: model.ui.capitalize_item_names,
-                        connect_toggled[sender] => move |cb| {
-                            sender.input(Msg::CapitalizeItem(cb.is_active()));
-                        }
+                    },
+                    attach[2, 4, 1, 1] = &gtk::Label {
+                        #[track(model.ui.changed(Ui::settings_db_create_path_status()))]
+                        set_label: match &model.ui.settings_db_create_path_status {
+                            Ok(status) | Err(status) => status,
+                        },
+                        #[track(model.ui.changed(Ui::settings_db_create_path_status()))]
+                        set_css_classes: if model.ui.settings_db_create_path_status.is_ok() {
+                            &["status-ok"]
+                        } else {
+                            &["status-error"]
+                        },
+                    },
+                    attach[1, 5, 1, 1] = &gtk::Label {
+                        set_label: "Item name casing:",
+                    },
+                    attach[2, 5, 1, 1] = &gtk::ComboBoxText {
+                        append_all: NameCasing::ALL.iter().map(ToString::to_string),
+                        #[track(model.ui.changed(Ui::item_name_casing()))]
+                        set_active: Some(model.ui.item_name_casing.into()),
+                        connect_changed[sender] => move |cb| {
+                            if let Some(casing) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                sender.input(Msg::ItemNameCasing(casing));
+                            }
+                        }
+                    },
+                    attach[1, 6, 1, 1] = &gtk::Label {
+                        set_label: "CSV delimiter:",
+                    },
+                    attach[2, 6, 1, 1] = &gtk::ComboBoxText {
+                        append_all: csv::Delimiter::ALL.iter().map(ToString::to_string),
+                        #[track(model.ui.changed(Ui::csv_delimiter()))]
+                        set_active: Some(model.ui.csv_delimiter.into()),
+                        connect_changed[sender] => move |cb| {
+                            if let Some(delimiter) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                sender.input(Msg::CsvDelimiter(delimiter));
+                            }
+                        }
+                    },
+                    attach[1, 7, 1, 1] = &gtk::Label {
+                        set_label: "Rescale prices:",
+                    },
+                    attach[2, 7, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        #[name(rescale_unit_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                        },
+                        #[name(rescale_operation_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (["multiply by", "divide by"].map(ToString::to_string), Some(0)),
+                        },
+                        #[name(rescale_factor_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 0,
+                            set_range: (2.0, 1000000.0),
+                            set_increments: (1.0, 10.0),
+                            set_value: 100.0,
+                        },
+                        gtk::Button {
+                            set_label: "Rescale",
+                            set_tooltip_text: Some("Fixes prices that were entered with the wrong scale."),
+                            connect_clicked[sender, rescale_unit_entry, rescale_operation_entry, rescale_factor_entry] => move |_| {
+                                sender.input(Msg::RescalePrices {
+                                    unit_idx: rescale_unit_entry.active(),
+                                    factor: rescale_factor_entry.value_as_int(),
+                                    divide: rescale_operation_entry.active() == Some(1),
+                                });
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                    },
+                    attach[1, 8, 1, 1] = &gtk::Label {
+                        set_label: "Sticky item fields:",
+                    },
+                    attach[2, 8, 1, 1] = &gtk::CheckButton {
+                        set_label: Some("Only clear name and price after adding an item"),
+                        #[track(model.ui.changed(Ui::sticky_item_fields()))]
+                        set_active: model.ui.sticky_item_fields,
+                        connect_toggled[sender] => move |cb| {
+                            sender.input(Msg::StickyItemFields(cb.is_active()));
+                        }
+                    },
+                    attach[1, 9, 1, 1] = &gtk::Label {
+                        set_label: "Price increments:",
+                    },
+                    attach[2, 9, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        gtk::Label {
+                            set_label: "step",
+                        },
+                        #[name(price_step_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 2,
+                            set_range: (0.01, 10000.0),
+                            set_increments: (1.0, 10.0),
+                            #[track(model.ui.changed(Ui::price_increments()))]
+                            set_value: model.ui.price_increments.0,
+                            connect_value_changed[sender, price_page_entry] => move |step| {
+                                sender.input(Msg::PriceIncrements(step.value(), price_page_entry.value()));
+                            },
+                        },
+                        gtk::Label {
+                            set_label: "page",
+                        },
+                        #[name(price_page_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 2,
+                            set_range: (0.01, 10000.0),
+                            set_increments: (1.0, 10.0),
+                            #[track(model.ui.changed(Ui::price_increments()))]
+                            set_value: model.ui.price_increments.1,
+                            connect_value_changed[sender, price_step_entry] => move |page| {
+                                sender.input(Msg::PriceIncrements(price_step_entry.value(), page.value()));
+                            },
+                        },
+                        gtk::Label {
+                            set_label: "in major units of the currency",
+                        },
+                    },
+                    attach[1, 10, 1, 1] = &gtk::Button {
+                        set_label: "Check Database",
+                        set_tooltip_text: Some("Looks for rows that reference missing stores or receipts, unknown units and negative totals."),
+                        connect_clicked[sender] => move |_| {
+                            sender.input(Msg::CheckIntegrity);
+                        },
+                        #[watch]
+                        set_sensitive: model.conn.is_some(),
+                    },
+                    attach[2, 10, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        set_tooltip_text: Some("Items whose receipt was deleted, e.g. by another tool. The number of items is shown before anything is changed."),
+                        gtk::Button {
+                            set_label: "Delete items without a receipt",
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::CleanUpOrphanedItems { reassign: false });
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Move them to the selected receipt",
+                            // the selection changes silently, see Msg::ReceiptChanged
+                            #[watch]
+                            set_sensitive: model.ui.selected_receipt_id().is_some(),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::CleanUpOrphanedItems { reassign: true });
+                            },
+                        },
+                    },
+                    attach[1, 11, 2, 1] = &gtk::Label {
+                        set_halign: gtk::Align::Start,
+                        #[track(model.ui.changed(Ui::session_summary()))]
+                        set_label: &model.ui.session_summary.to_string(),
+                    },
+                    attach[1, 12, 1, 1] = &gtk::Label {
+                        set_label: "Write-ahead log:",
+                    },
+                    attach[2, 12, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        gtk::CheckButton {
+                            set_label: Some("Allow other tools to use the database at the same time"),
+                            #[track(model.ui.changed(Ui::wal_mode()))]
+                            set_active: model.ui.wal_mode,
+                            connect_toggled[sender] => move |cb| {
+                                sender.input(Msg::WalMode(cb.is_active()));
+                            }
+                        },
+                        gtk::Label {
+                            #[track(model.ui.changed(Ui::journal_mode()))]
+                            set_label: &model.ui.journal_mode,
+                        },
+                    },
+                    attach[1, 13, 1, 1] = &gtk::Label {
+                        set_label: "Amounts:",
+                    },
+                    attach[2, 13, 1, 1] = &gtk::CheckButton {
+                        set_label: Some("Show prices, totals, budgets and money in analysis results in major units, e.g. 12.99 instead of 1299"),
+                        #[track(model.ui.changed(Ui::major_units()))]
+                        set_active: model.ui.major_units,
+                        connect_toggled[sender] => move |cb| {
+                            sender.input(Msg::MajorUnits(cb.is_active()));
+                        }
+                    },
+                    attach[1, 23, 1, 1] = &gtk::Label {
+                        set_label: "Refunds:",
+                    },
+                    attach[2, 23, 1, 1] = &gtk::CheckButton {
+                        set_label: Some("Leave lines with a negative amount, e.g. refunds and deposits, out of the item total, its groups and the dashboard. Queries can use it as :exclude_refunds."),
+                        #[track(model.ui.changed(Ui::exclude_refunds()))]
+                        set_active: model.ui.exclude_refunds,
+                        connect_toggled[sender] => move |cb| {
+                            sender.input(Msg::ExcludeRefunds(cb.is_active()));
+                        }
+                    },
+                    attach[1, 24, 1, 1] = &gtk::Label {
+                        set_label: "Quantities:",
+                    },
+                    attach[2, 24, 1, 1] = &gtk::CheckButton {
+                        set_label: Some("Allow decimal quantities for weighed goods, e.g. 0.75 kg"),
+                        #[track(model.ui.changed(Ui::decimal_quantities()))]
+                        set_active: model.ui.decimal_quantities,
+                        connect_toggled[sender] => move |cb| {
+                            sender.input(Msg::DecimalQuantities(cb.is_active()));
+                        }
+                    },
+                    attach[1, 25, 1, 1] = &gtk::Label {
+                        set_label: "Queries:",
+                    },
+                    attach[2, 25, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        set_tooltip_text: Some("A query can only be saved with at least this many inputs and header columns."),
+                        gtk::Label {
+                            set_label: "required inputs",
+                        },
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 0,
+                            set_range: (0.0, 20.0),
+                            set_increments: (1.0, 5.0),
+                            #[track(model.ui.changed(Ui::required_rows()))]
+                            set_value: model.ui.required_rows.0 as f64,
+                            connect_value_changed[sender] => move |inputs| {
+                                sender.input(Msg::RequiredInputs(inputs.value() as usize));
+                            },
+                        },
+                        gtk::Label {
+                            set_label: "required header columns",
+                        },
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 0,
+                            set_range: (0.0, 20.0),
+                            set_increments: (1.0, 5.0),
+                            #[track(model.ui.changed(Ui::required_rows()))]
+                            set_value: model.ui.required_rows.1 as f64,
+                            connect_value_changed[sender] => move |outputs| {
+                                sender.input(Msg::RequiredOutputs(outputs.value() as usize));
+                            },
+                        },
+                    },
+                    attach[1, 26, 1, 1] = &gtk::Label {
+                        set_label: "Update prices:",
+                    },
+                    attach[2, 26, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        #[name(update_price_name_entry)]
+                        gtk::Entry {
+                            set_placeholder_text: Some("item name"),
+                            set_completion: Some(&update_price_completion),
+                        },
+                        gtk::Label {
+                            set_label: "from",
+                        },
+                        #[name(update_price_from_entry)]
+                        gtk::Entry {
+                            set_width_chars: 10,
+                            set_text: "-30d",
+                        },
+                        gtk::Label {
+                            set_label: "to",
+                        },
+                        #[name(update_price_to_entry)]
+                        gtk::Entry {
+                            set_width_chars: 10,
+                            set_text: "today",
+                        },
+                        gtk::Label {
+                            set_label: "new price",
+                        },
+                        #[name(update_price_entry)]
+                        gtk::Entry {
+                            set_width_chars: 8,
+                        },
+                        #[name(update_price_unit_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                        },
+                        gtk::Button {
+                            set_label: "Update",
+                            set_tooltip_text: Some("Sets the price of the items with the name and unit on the receipts between both dates. Dates may be relative, e.g. -30d."),
+                            connect_clicked[sender, update_price_name_entry, update_price_entry, update_price_unit_entry, update_price_from_entry, update_price_to_entry] => move |_| {
+                                sender.input(Msg::UpdateItemPrices {
+                                    name: update_price_name_entry.text(),
+                                    price: update_price_entry.text(),
+                                    unit_idx: update_price_unit_entry.active(),
+                                    from: update_price_from_entry.text(),
+                                    to: update_price_to_entry.text(),
+                                });
+                            },
+                            #[watch]
+                            set_sensitive: model.conn.is_some(),
+                        },
+                    },
+                    attach[1, 27, 1, 1] = &gtk::Label {
+                        set_label: "Name whitespace:",
+                    },
+                    attach[2, 27, 1, 1] = &gtk::CheckButton {
+                        set_label: Some("Collapse repeated spaces in store and item names when adding them"),
+                        set_tooltip_text: Some("'Rema   1000' is added as 'Rema 1000', so it is recognized as a duplicate of it."),
+                        #[track(model.ui.changed(Ui::collapse_name_whitespace()))]
+                        set_active: model.ui.collapse_name_whitespace,
+                        connect_toggled[sender] => move |cb| {
+                            sender.input(Msg::CollapseNameWhitespace(cb.is_active()));
+                        }
+                    },
+                    attach[1, 14, 1, 1] = &gtk::Label {
+                        set_label: "Duplicates:",
+                    },
+                    attach[2, 14, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        gtk::CheckButton {
+                            set_label: Some("Ask before adding an item name twice to a receipt"),
+                            #[track(model.ui.changed(Ui::warn_duplicate_items()))]
+                            set_active: model.ui.warn_duplicate_items,
+                            connect_toggled[sender] => move |cb| {
+                                sender.input(Msg::WarnDuplicateItems(cb.is_active()));
+                            }
+                        },
+                        gtk::CheckButton {
+                            set_label: Some("Ask before adding a store with the name and location of another"),
+                            #[track(model.ui.changed(Ui::warn_duplicate_stores()))]
+                            set_active: model.ui.warn_duplicate_stores,
+                            connect_toggled[sender] => move |cb| {
+                                sender.input(Msg::WarnDuplicateStores(cb.is_active()));
+                            }
+                        },
+                        gtk::CheckButton {
+                            set_label: Some("Ask before adding a second receipt of a store on the same date"),
+                            #[track(model.ui.changed(Ui::warn_duplicate_receipts()))]
+                            set_active: model.ui.warn_duplicate_receipts,
+                            connect_toggled[sender] => move |cb| {
+                                sender.input(Msg::WarnDuplicateReceipts(cb.is_active()));
+                            }
+                        },
+                    },
+                    attach[1, 15, 1, 1] = &gtk::Label {
+                        set_label: "Date format:",
+                    },
+                    attach[2, 15, 1, 1] = &gtk::Entry {
+                        set_placeholder_text: Some("%F, e.g. %-d %B %Y for 3 May 2024"),
+                        set_tooltip_text: Some("How receipt dates are shown, applied with Enter. How they are stored is set below."),
+                        #[track(model.ui.changed(Ui::date_format()))]
+                        set_text: &model.ui.date_format,
+                        connect_activate[sender] => move |entry| {
+                            sender.input(Msg::DateFormat(entry.text()));
+                        },
+                    },
+                    attach[1, 16, 1, 1] = &gtk::Label {
+                        set_label: "Large amounts:",
+                    },
+                    attach[2, 16, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        #[name(large_amount_entry)]
+                        gtk::SpinButton {
+                            set_numeric: true,
+                            set_digits: 2,
+                            set_range: (0.0, 100000000.0),
+                            set_increments: (100.0, 1000.0),
+                        },
+                        #[name(large_amount_unit_entry)]
+                        gtk::ComboBoxText {
+                            append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                        },
+                        gtk::Button {
+                            set_label: "Set",
+                            set_tooltip_text: Some("Asks before adding an item whose price times quantity is more than this. 0 never asks."),
+                            connect_clicked[sender, large_amount_entry, large_amount_unit_entry] => move |_| {
+                                sender.input(Msg::LargeAmount {
+                                    unit: large_amount_unit_entry.active().unwrap().try_into().unwrap(),
+                                    amount: large_amount_entry.value(),
+                                });
+                            },
+                        },
+                        gtk::Label {
+                            #[track(model.ui.changed(Ui::large_amounts()) || model.ui.changed(Ui::rounding()))]
+                            set_label: &large_amounts_text(&model.ui.large_amounts, model.ui.rounding),
+                        },
+                    },
+                    attach[1, 17, 1, 1] = &gtk::Label {
+                        set_label: "Tabs:",
+                    },
+                    attach[2, 17, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            #[track(model.ui.changed(Ui::tabs()))]
+                            show_tab_layout: (&model.ui.tabs, &sender),
+                        },
+                        gtk::Label {
+                            set_label: "Changes are applied at the next start.",
+                            set_xalign: 0.0,
+                            add_css_class: "dim-label",
+                        },
+                    },
+                    attach[1, 18, 1, 1] = &gtk::Label {
+                        set_label: "Stored dates:",
+                    },
+                    attach[2, 18, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                        gtk::ComboBoxText {
+                            append_all: StorageFormat::ALL.iter().map(ToString::to_string),
+                            #[track(model.ui.changed(Ui::storage_date_format()))]
+                            set_active: Some(model.ui.storage_date_format.into()),
+                            connect_changed[sender] => move |cb| {
+                                if let Some(format) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                    sender.input(Msg::StorageDateFormat(format));
+                                }
+                            }
+                        },
+                        gtk::Label {
+                            set_label: "SQLite date functions in own queries only understand YYYY-MM-DD.",
+                            set_xalign: 0.0,
+                            add_css_class: "dim-label",
+                        },
+                    },
+                    attach[1, 20, 1, 1] = &gtk::Label {
+                        set_label: "Receipt on connect:",
+                    },
+                    attach[2, 20, 1, 1] = &gtk::ComboBoxText {
+                        set_tooltip_text: Some("Which receipt the item tab selects when a database is connected."),
+                        append_all: ReceiptSelection::ALL.iter().map(ToString::to_string),
+                        #[track(model.ui.changed(Ui::receipt_selection()))]
+                        set_active: Some(model.ui.receipt_selection.into()),
+                        connect_changed[sender] => move |cb| {
+                            if let Some(selection) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                sender.input(Msg::ReceiptSelection(selection));
+                            }
+                        }
+                    },
+                    attach[1, 21, 1, 1] = &gtk::Label {
+                        set_label: "Rounding:",
+                    },
+                    attach[2, 21, 1, 1] = &gtk::ComboBoxText {
+                        set_tooltip_text: Some("How amounts exactly between two cents are rounded, when entered amounts are stored, an entered line total is divided into the price per unit and totals are converted. The line totals of stored items are always rounded half away from zero, like in the totals of the database."),
+                        append_all: Rounding::ALL.iter().map(ToString::to_string),
+                        #[track(model.ui.changed(Ui::rounding()))]
+                        set_active: Some(model.ui.rounding.into()),
+                        connect_changed[sender] => move |cb| {
+                            if let Some(rounding) = cb.active().and_then(|idx| idx.try_into().ok()) {
+                                sender.input(Msg::Rounding(rounding));
+                            }
+                        }
+                    },
+                    attach[1, 22, 1, 1] = &gtk::Label {
+                        set_label: "Settings:",
+                    },
+                    attach[2, 22, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 5,
+                        gtk::Button {
+                            set_label: "Export",
+                            set_tooltip_text: Some("Writes the current settings to a file, e.g. to move them to another machine."),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::ExportSettings);
+                            },
+                        },
+                        gtk::Button {
+                            set_label: "Reset to Defaults",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(Msg::ResetSettings);
+                            },
+                        },
+                        gtk::Label {
+                            #[track(model.ui.changed(Ui::settings_status()))]
+                            set_label: &model.ui.settings_status,
+                        },
+                    },
+                    attach[1, 19, 1, 1] = &gtk::Label {
+                        set_label: "Combined total:",
+                    },
+                    attach[2, 19, 1, 1] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                        gtk::ComboBoxText {
+                            set_tooltip_text: Some("Also shows the item total converted to this unit."),
+                            append_all: std::iter::once("off".to_string()).chain(Unit::ALL.iter().map(|unit| unit.as_str().to_string())),
+                            #[track(model.ui.changed(Ui::base_unit()))]
+                            set_active: Some(model.ui.base_unit.and_then(|unit| Unit::ALL.iter().position(|other| *other == unit)).map_or(0, |idx| idx as u32 + 1)),
+                            connect_changed[sender] => move |cb| {
+                                if let Some(idx) = cb.active() {
+                                    sender.input(Msg::BaseUnit(idx.checked_sub(1).and_then(|idx| Unit::from_idx(idx).ok())));
+                                }
+                            }
+                        },
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_spacing: 5,
+                            gtk::Label {
+                                set_label: "1",
+                            },
+                            #[name(rate_unit_entry)]
+                            gtk::ComboBoxText {
+                                append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(1)),
+                            },
+                            gtk::Label {
+                                set_label: "=",
+                            },
+                            #[name(rate_entry)]
+                            gtk::SpinButton {
+                                set_numeric: true,
+                                set_digits: 4,
+                                set_range: (0.0, 1000000.0),
+                                set_increments: (0.01, 1.0),
+                            },
+                            #[name(rate_base_entry)]
+                            gtk::ComboBoxText {
+                                append_all_and_select: (Unit::ALL.iter().map(|unit| unit.as_str().to_string()), Some(0)),
+                            },
+                            gtk::Button {
+                                set_label: "Set Rate",
+                                set_tooltip_text: Some("A rate of 0 removes the rate. The rates are stored in the database."),
+                                connect_clicked[sender, rate_unit_entry, rate_entry, rate_base_entry] => move |_| {
+                                    sender.input(Msg::SetRate {
+                                        unit: rate_unit_entry.active().unwrap().try_into().unwrap(),
+                                        base: rate_base_entry.active().unwrap().try_into().unwrap(),
+                                        rate: rate_entry.value(),
+                                    });
+                                },
+                                #[watch]
+                                set_sensitive: model.conn.is_some(),
+                            },
+                        },
+                        gtk::Label {
+                            set_xalign: 0.0,
+                            add_css_class: "dim-label",
+                            #[track(model.ui.changed(Ui::rates()))]
+                            set_label: &rates::rates_text(&model.ui.rates),
+                        },
                     },
                 },
             },
@@ -631,12 +3175,14 @@ impl SimpleComponent for App {
     }
 
     fn init(
-        _init: Self::Init,
+        args: Self::Init,
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let provider = gtk::CssProvider::new();
-        provider.load_from_data(b"entry.duplicate-name { border: 2px solid red; }");
+        provider.load_from_data(
+            b"entry.duplicate-name { border: 2px solid red; } entry.invalid-name { border: 2px solid orange; } entry.unreferenced-name { border: 2px dashed orange; } label.total-mismatch { color: red; } label.over-budget { color: red; font-weight: bold; } label.status-error { color: red; } label.status-ok { color: green; } label.currency-mismatch { color: orange; }",
+        );
 
         gtk::StyleContext::add_provider_for_display(
             &gtk::gdk::Display::default().expect("Could not connect to a display."),
@@ -648,10 +3194,16 @@ impl SimpleComponent for App {
             .launch(root.clone().upcast())
             .forward(sender.input_sender(), identity);
 
+        let confirm_dialog = confirm_dialog::Dialog::builder()
+            .launch(root.clone().upcast())
+            .forward(sender.input_sender(), identity);
+
         let analysis = Analysis::builder()
             .launch(root.clone().upcast())
             .forward(sender.input_sender(), identity);
 
+        let store_picker = || StorePicker::builder().launch(()).detach();
+
         let mut model = App {
             conn: None,
             ui: Ui {
@@ -662,297 +3214,1258 @@ impl SimpleComponent for App {
                 reset_store_fields: false,
                 settings_db_path: String::new(),
                 settings_db_path_status: String::new(),
+                missing_db: None,
                 settings_db_create_path: String::new(),
-                settings_db_create_path_status: String::new(),
-                page: 4,
-                capitalize_item_names: false,
+                settings_db_create_path_status: Ok(String::new()),
+                page: TabLayout::default().position(Tab::Settings) as i32,
+                tab_layout: TabLayout::default(),
+                tabs: TabLayout::default(),
+                item_name_casing: NameCasing::default(),
+                sticky_item_fields: false,
+                collapse_name_whitespace: false,
+                price_increments: default_price_increments(),
+                csv_delimiter: csv::Delimiter::default(),
                 store_name_valid: NameStatus::Invalid,
                 store_location_valid: NameStatus::Invalid,
                 item_name_valid: NameStatus::Invalid,
                 total: Total::new(),
+                net_total: Total::new(),
+                exclude_refunds: false,
+                decimal_quantities: false,
+                required_rows: default_required_rows(),
+                base_unit: None,
+                rates: Vec::new(),
+                rounding: Rounding::default(),
+                printed_total: None,
+                integrity_report: None,
+                connected_db: None,
+                settings_db_file: None,
+                settings_file: args.settings_file(std::env::var(SETTINGS_ENV).ok()),
+                settings_error: None,
+                app_error: None,
+                analysis_popped_out: false,
+                settings_status: String::new(),
+                status: String::new(),
+                budget_status: None,
+                session_summary: SessionSummary::default(),
+                recent_items: Vec::new(),
+                prefill_item: None,
+                receipt_currency: None,
+                recent_dbs: Vec::new(),
+                input_panel_position: None,
+                unsaved_changes: false,
+                receipt_selection: ReceiptSelection::default(),
+                last_receipts: HashMap::new(),
+                wal_mode: false,
+                journal_mode: String::new(),
+                major_units: false,
+                warn_duplicate_items: true,
+                warn_duplicate_stores: true,
+                warn_duplicate_receipts: true,
+                date_format: String::new(),
+                storage_date_format: StorageFormat::default(),
+                price_is_line_total: false,
+                rounding_note: String::new(),
+                large_amounts: HashMap::new(),
+                group_items_by: GroupBy::default(),
+                item_groups: Vec::new(),
+                last_price: None,
+                last_price_generation: 0,
                 tracker: 0,
             },
+            receipt_list: gtk::ListStore::new(&[
+                gtk::glib::Type::I64,
+                gtk::glib::Type::STRING,
+                gtk::glib::Type::STRING,
+            ]),
+            item_names: gtk::ListStore::new(&[gtk::glib::Type::STRING]),
             dialog,
+            confirm_dialog,
             analysis,
+            dashboard: Dashboard::builder().launch(()).detach(),
+            receipt_store: store_picker(),
+            merge_source: store_picker(),
+            merge_target: store_picker(),
+            budget_store: store_picker(),
         };
 
-        if let Ok(file) = File::open("sqlbon_settings.json") {
+        if let Ok(file) = File::open(&model.ui.settings_file) {
             if let Ok(data) = serde_json::from_reader(file) {
                 let data: Settings = data;
-                if let Ok(conn) = Connection::open(&data.db_file) {
+                model.ui.set_recent_dbs(data.recent_dbs);
+                model.ui.input_panel_position = data.input_panel_position;
+                if let Some(position) = data.input_panel_position {
+                    model
+                        .analysis
+                        .emit(AnalysisMsg::InputPanelPosition(position));
+                }
+                model.ui.set_wal_mode(data.wal_mode);
+                model.ui.set_csv_delimiter(data.csv_delimiter);
+                model
+                    .analysis
+                    .emit(AnalysisMsg::CsvDelimiter(data.csv_delimiter));
+                model.ui.set_sticky_item_fields(data.sticky_item_fields);
+                model.ui.set_price_increments(data.price_increments);
+                // settings of older versions only have the flag
+                model.ui.set_item_name_casing(
+                    if data.capitalize_item_names && data.item_name_casing == NameCasing::Keep {
+                        NameCasing::Upper
+                    } else {
+                        data.item_name_casing
+                    },
+                );
+                model
+                    .ui
+                    .set_collapse_name_whitespace(data.collapse_name_whitespace);
+                model.ui.set_major_units(data.major_units);
+                model
+                    .dashboard
+                    .emit(DashboardMsg::MajorUnits(data.major_units));
+                model
+                    .analysis
+                    .emit(AnalysisMsg::MajorUnits(data.major_units));
+                model.ui.set_warn_duplicate_items(data.warn_duplicate_items);
+                model
+                    .ui
+                    .set_warn_duplicate_stores(data.warn_duplicate_stores);
+                model
+                    .ui
+                    .set_warn_duplicate_receipts(data.warn_duplicate_receipts);
+                model.ui.set_date_format(data.date_format);
+                model.ui.set_storage_date_format(data.storage_date_format);
+                model
+                    .analysis
+                    .emit(AnalysisMsg::StorageDateFormat(data.storage_date_format));
+                model.ui.set_large_amounts(data.large_amounts);
+                model.ui.set_receipt_selection(data.receipt_selection);
+                model.ui.set_rounding(data.rounding);
+                model.ui.set_exclude_refunds(data.exclude_refunds);
+                model.ui.set_decimal_quantities(data.decimal_quantities);
+                model.ui.set_required_rows(data.required_rows);
+                model.analysis.emit(AnalysisMsg::RequiredRows(
+                    data.required_rows.0,
+                    data.required_rows.1,
+                ));
+                model
+                    .dashboard
+                    .emit(DashboardMsg::ExcludeRefunds(data.exclude_refunds));
+                model
+                    .analysis
+                    .emit(AnalysisMsg::ExcludeRefunds(data.exclude_refunds));
+                model.ui.last_receipts = data.last_receipts;
+                model
+                    .ui
+                    .set_base_unit(data.base_unit.and_then(|unit| Unit::from_str(&unit).ok()));
+                let tabs = data.tabs.normalized();
+                model.ui.page = tabs.position(Tab::Settings) as i32;
+                model.ui.tab_layout = tabs.clone();
+                model.ui.set_tabs(tabs);
+                let db_file = match &args.db {
+                    Some(db) => {
+                        model.ui.settings_db_file = Some(data.db_file);
+                        db.clone()
+                    }
+                    None => data.db_file,
+                };
+                if let Ok(conn) = open_db(&db_file) {
                     let conn = Rc::new(conn);
                     model
                         .analysis
-                        .emit(AnalysisMsg::ConnectDb(Rc::clone(&conn)));
+                        .emit(AnalysisMsg::ConnectDb(Rc::clone(&conn), db_file.clone()));
+                    model
+                        .dashboard
+                        .emit(DashboardMsg::ConnectDb(Rc::clone(&conn)));
                     model.conn = Some(conn);
+                    model.apply_journal_mode();
+                    model.ui.set_connected_db(Some(db_file.clone()));
+                    // the switcher selects its first database
+                    model.ui.remember_db(db_file.clone(), None);
                     model.load_stores();
                     model.load_receipts();
-                    model.ui.set_settings_db_path(data.db_file);
-                    model
-                        .ui
-                        .set_capitalize_item_names(data.capitalize_item_names);
-                    model.ui.update_store_name_valid(NameStatus::connect);
-                    model.ui.update_store_location_valid(NameStatus::connect);
-                    model.ui.update_item_name_valid(NameStatus::connect);
+                    model.load_item_names();
+                    model.ui.set_settings_db_path(db_file);
+                    model.ui.connect_names();
+                    if let Some(conn) = &model.conn {
+                        model.ui.load_items_today(conn);
+                        model.ui.load_recent_items(conn);
+                        model.ui.load_rates(conn);
+                    }
                     model
                         .ui
                         .set_settings_db_path_status("Successfully connected.".to_string());
                 } else {
                     model.ui.set_settings_db_path_status(format!(
-                        "'{}' is not a database file.",
-                        data.db_file
+                        "'{db_file}' is not a database file."
                     ));
+                    model.ui.set_missing_db(Some(db_file));
                 }
             } else {
-                model.ui.set_settings_db_path_status(
-                    "'sqlbon_settings.json' file is not valid.".to_string(),
-                );
+                model.ui.set_settings_db_path_status(format!(
+                    "'{}' file is not valid.",
+                    model.ui.settings_file
+                ));
             }
         }
+        // without settings the database of the command line is connected like a chosen one
+        if let (None, Some(db)) = (&model.ui.settings_db_file, args.db) {
+            model.ui.set_settings_db_path(db);
+            model.connect_db();
+        }
+        if let Some(tab) = args.tab.filter(|tab| model.ui.tab_layout.is_visible(*tab)) {
+            model.ui.page = model.ui.tab_layout.position(tab) as i32;
+        }
+
+        // the completion keeps the order of the model, so the most frequent matches are shown first
+        let item_name_completion = gtk::EntryCompletion::new();
+        item_name_completion.set_model(Some(&model.item_names));
+        item_name_completion.set_text_column(0);
+        let update_price_completion = gtk::EntryCompletion::new();
+        update_price_completion.set_model(Some(&model.item_names));
+        update_price_completion.set_text_column(0);
 
         let widgets = view_output!();
+        model.ui.tab_layout.arrange(&widgets.notebook);
+        widgets
+            .notebook
+            .set_current_page(Some(model.ui.page as u32));
+        widgets.about_dialog.set_transient_for(Some(root));
+        widgets.integrity_dialog.set_transient_for(Some(root));
+        widgets.settings_error_dialog.set_transient_for(Some(root));
+        widgets.error_dialog.set_transient_for(Some(root));
+        widgets
+            .receipt_tree
+            .selection()
+            .set_mode(gtk::SelectionMode::Multiple);
+        // the item fields are spread over nested boxes and followed by other widgets,
+        // so the default focus order does not reach the add button next
+        set_focus_chain(&[
+            widgets.item_name_entry.clone().upcast(),
+            widgets.quantity_entry.clone().upcast(),
+            widgets.price_entry.clone().upcast(),
+            widgets.line_total_check.clone().upcast(),
+            widgets.unit_entry.clone().upcast(),
+            widgets.receipt_entry.clone().upcast(),
+            widgets.item_add_button.clone().upcast(),
+        ]);
+        let quantity_entry = widgets.quantity_entry.clone();
+        widgets.item_name_entry.connect_activate(move |_| {
+            quantity_entry.grab_focus();
+        });
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         self.ui.reset();
         self.ui.reset_item_fields = false;
         self.ui.reset_store_fields = false;
         match message {
             Msg::AddStore(store) => {
                 if let Some(conn) = &self.conn {
-                    let store_name = store.name.trim();
+                    let store_name = self.ui.normalize_name(&store.name);
                     let store_location = store.location.trim();
+                    let city = store.city.trim();
+                    let country = store.country.trim();
                     if !store_name.is_empty() && !store_location.is_empty() {
-                        let existence_check_query = conn
-                            .query_row(
+                        let existence_check_query = if self.ui.warn_duplicate_stores {
+                            conn.query_row(
                                 "SELECT id FROM Store WHERE name == ?1 AND location == ?2;",
                                 params![store_name, store_location],
                                 |row| {
                                     let id: i64 = row.get(0)?;
                                     Ok(id)
                                 },
-                            )
-                            .optional();
-                        match existence_check_query {
-                            Ok(Some(_)) => {
-                                self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
-                                    add_duplicate_alert::WarningOrigin::Store {
-                                        name: store_name.to_string(),
-                                        location: store_location.to_string(),
-                                    },
+                            )
+                            .optional()
+                        } else {
+                            Ok(None)
+                        };
+                        match existence_check_query {
+                            Ok(Some(_)) => {
+                                self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
+                                    add_duplicate_alert::WarningOrigin::Store {
+                                        name: store_name,
+                                        location: store_location.to_string(),
+                                        city: city.to_string(),
+                                        country: country.to_string(),
+                                    },
+                                ));
+                            }
+                            Ok(None) => {
+                                let insert_query = conn.execute(
+                                    "INSERT INTO Store (name, location, city, country) VALUES (?1, ?2, ?3, ?4);",
+                                    params![store_name, store_location, city, country],
+                                );
+                                if let Err(err) = insert_query {
+                                    self.ui.report("add the store", err);
+                                } else {
+                                    self.load_stores();
+                                    self.ui.reset_store_fields = true;
+                                }
+                            }
+                            Err(err) => self.ui.report("add the store", err),
+                        }
+                    }
+                }
+            }
+            Msg::ForceAddStore(store) => {
+                if let Some(conn) = &self.conn {
+                    let insert_query = conn.execute(
+                        "INSERT INTO Store (name, location, city, country) VALUES (?1, ?2, ?3, ?4);",
+                        params![
+                            store.name.as_str(),
+                            store.location.as_str(),
+                            store.city.as_str(),
+                            store.country.as_str()
+                        ],
+                    );
+                    if let Err(err) = insert_query {
+                        self.ui.report("add the store", err);
+                    } else {
+                        self.load_stores();
+                        self.ui.reset_store_fields = true;
+                    }
+                }
+            }
+            Msg::AddReceipt(receipt) => {
+                let store = self.receipt_store.state().get().model.chosen();
+                if let (Some(conn), Some(store)) = (&self.conn, store) {
+                    let receipt_date =
+                        date::to_stored_date(&receipt.date, self.ui.storage_date_format);
+                    let existence_check_query = if self.ui.warn_duplicate_receipts {
+                        conn.query_row(
+                            "SELECT id FROM Receipt WHERE store == ?1 AND date == ?2;",
+                            params![store.id, receipt_date.as_str()],
+                            |row| {
+                                let id: i64 = row.get(0)?;
+                                Ok(id)
+                            },
+                        )
+                        .optional()
+                    } else {
+                        Ok(None)
+                    };
+                    let checked = existence_check_query.and_then(|existing| {
+                        let currency = receipt.currency.resolve(conn, store.id)?;
+                        Ok((existing, currency))
+                    });
+                    match checked {
+                        Ok((Some(_), currency)) => {
+                            self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
+                                add_duplicate_alert::WarningOrigin::Receipt {
+                                    store: store.clone(),
+                                    date_label: date::display_date(
+                                        &receipt_date,
+                                        &self.ui.date_format,
+                                    ),
+                                    date: receipt.date,
+                                    currency,
+                                    go_to_items: receipt.go_to_items,
+                                },
+                            ));
+                        }
+                        Ok((None, currency)) => {
+                            let insert_query = conn.execute(
+                                "INSERT INTO Receipt (store, date, currency, created_at, updated_at) VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP);",
+                                params![store.id, receipt_date.as_str(), currency.map(|unit| unit.as_str().to_string())],
+                            );
+                            if let Err(err) = insert_query {
+                                self.ui.report("add the receipt", err);
+                            } else {
+                                let receipt_id = conn.last_insert_rowid();
+                                self.receipt_added(receipt_id, receipt.go_to_items);
+                            }
+                        }
+                        Err(err) => self.ui.report("add the receipt", err),
+                    }
+                }
+            }
+            Msg::ForceAddReceipt {
+                store_id,
+                date,
+                currency,
+                go_to_items,
+            } => {
+                if let Some(conn) = &self.conn {
+                    let date = date::to_stored_date(&date, self.ui.storage_date_format);
+                    let insert_query = conn.execute(
+                        "INSERT INTO Receipt (store, date, currency, created_at, updated_at) VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP);",
+                        params![store_id, date.as_str(), currency.map(|unit| unit.as_str().to_string())],
+                    );
+                    if let Err(err) = insert_query {
+                        self.ui.report("add the receipt", err);
+                    } else {
+                        let receipt_id = conn.last_insert_rowid();
+                        self.receipt_added(receipt_id, go_to_items);
+                    }
+                }
+            }
+            Msg::AddItem(item) => {
+                if let (Some(_), Some(receipt_idx)) = (&self.conn, item.receipt_idx) {
+                    let item_name = self.ui.normalize_name(&item.name);
+                    if !item_name.is_empty() {
+                        let name = self.ui.item_name_casing.apply(&item_name);
+                        let entered = Money::from_major(item.price, item.unit, self.ui.rounding);
+                        let price = if item.line_total {
+                            entered.per_unit(item.quantity, self.ui.rounding)
+                        } else {
+                            entered
+                        };
+                        self.ui.set_rounding_note(rounding_note(
+                            entered,
+                            price,
+                            item.quantity,
+                            item.line_total,
+                        ));
+                        let receipt = &self.ui.receipts.0[receipt_idx as usize];
+                        let currency = receipt.currency;
+                        let category = self.ui.normalize_name(&item.category);
+                        let item = NewItem {
+                            name,
+                            quantity: item.quantity,
+                            price,
+                            category: (!category.is_empty()).then_some(category),
+                            receipt_id: receipt.id,
+                        };
+                        match currency {
+                            Some(currency) if currency != item.price.unit => {
+                                self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
+                                    add_duplicate_alert::WarningOrigin::Currency { item, currency },
+                                ))
+                            }
+                            _ => self.add_item_unless_large(item),
+                        }
+                    }
+                }
+            }
+            Msg::ConfirmLargeAmount(item) => self.add_item_unless_duplicate(item),
+            Msg::ConfirmCurrency(item) => self.add_item_unless_large(item),
+            Msg::ForceAddItem(item) => self.insert_item(&item),
+            Msg::MergeItem { existing_id, item } => {
+                if let Some(conn) = &self.conn {
+                    let update_query = conn.execute(
+                        "UPDATE Item SET quantity = quantity + ?1, updated_at = CURRENT_TIMESTAMP WHERE id == ?2;",
+                        params![item.quantity, existing_id],
+                    );
+                    if let Err(err) = update_query {
+                        self.ui.report("merge the item", err);
+                    } else {
+                        self.item_saved(&item);
+                    }
+                }
+            }
+            Msg::SelectUnit(unit) => self.ui.set_selected_unit(unit),
+            Msg::PriceIsLineTotal(line_total) => self.ui.set_price_is_line_total(line_total),
+            Msg::GroupItemsBy(group_by) => {
+                self.ui.set_group_items_by(group_by);
+                if let Some(conn) = &self.conn {
+                    let receipt_id = self.ui.selected_receipt_id();
+                    self.ui.load_totals(conn, receipt_id);
+                }
+            }
+            Msg::ExportItemGroups => {
+                let path = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .show_save_single_file()
+                    .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                if let Some(path) = path {
+                    let text = item_groups::grouped_csv(
+                        &self.ui.item_groups,
+                        self.ui.csv_delimiter,
+                        self.ui.major_units,
+                    );
+                    if let Err(err) = std::fs::write(path, text) {
+                        self.ui.report("export the items", err);
+                    }
+                }
+            }
+            Msg::PrefillItem(idx) => {
+                if let Some(item) = self.ui.recent_items.get(idx).cloned() {
+                    self.ui.set_prefill_item(Some(item));
+                }
+            }
+            Msg::OpenDbDialog => {
+                let path = FileDialog::new()
+                    .show_open_single_file()
+                    .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                if let Some(path) = path {
+                    let path = path.to_string_lossy().to_string();
+                    self.ui.set_settings_db_path(path);
+                }
+            }
+            Msg::OpenCreateDbDialog => {
+                let path = FileDialog::new()
+                    .show_save_single_file()
+                    .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                if let Some(path) = path {
+                    let path = path.to_string_lossy().to_string();
+                    self.ui.set_settings_db_create_path(path);
+                }
+            }
+            Msg::ConnectDb => self.connect_db(),
+            Msg::ReconnectDb => {
+                if let Some(path) = self.ui.missing_db.clone() {
+                    self.ui.set_settings_db_path(path);
+                    self.connect_db();
+                }
+            }
+            Msg::LocateDb => {
+                if let Some(missing_db) = self.ui.missing_db.clone() {
+                    let missing_db = Path::new(&missing_db);
+                    let file_name = missing_db
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let mut dialog = FileDialog::new().set_filename(&file_name);
+                    // the folder may have been moved as well
+                    if let Some(folder) = missing_db.parent().filter(|folder| folder.is_dir()) {
+                        dialog = dialog.set_location(folder);
+                    }
+                    let path = dialog
+                        .show_open_single_file()
+                        .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                    if let Some(path) = path {
+                        self.ui
+                            .set_settings_db_path(path.to_string_lossy().to_string());
+                        self.connect_db();
+                    }
+                }
+            }
+            Msg::Refresh => self.refresh(),
+            Msg::SetBudget { amount, unit } => {
+                let store = self.budget_store.state().get().model.chosen();
+                let amount = Money::from_major(amount, unit, self.ui.rounding);
+                if let (Some(conn), Some(store)) = (&self.conn, store) {
+                    let query = if amount.minor > 0 {
+                        conn.execute(
+                            "INSERT OR REPLACE INTO Budget (store, unit, amount) VALUES (?1, ?2, ?3);",
+                            params![store.id, unit.as_str(), amount.minor],
+                        )
+                    } else {
+                        conn.execute(
+                            "DELETE FROM Budget WHERE store == ?1 AND unit == ?2;",
+                            params![store.id, unit.as_str()],
+                        )
+                    };
+                    if let Err(err) = query {
+                        self.ui.report("save the budget", err);
+                    }
+                }
+            }
+            Msg::SwitchDb(idx) => {
+                let path = idx.and_then(|idx| self.ui.recent_dbs.get(idx as usize).cloned());
+                if let Some(path) = path {
+                    // selecting the first entry after reordering the list would reconnect
+                    if self.ui.connected_db.as_ref() != Some(&path) {
+                        self.ui.set_settings_db_path(path);
+                        self.connect_db();
+                    }
+                }
+            }
+            Msg::CreateDb => {
+                let db_path = self.ui.settings_db_create_path.trim();
+                if !db_path.is_empty() {
+                    let is_non_empty_file = std::fs::metadata(db_path)
+                        .map(|metadata| metadata.len() > 0)
+                        .unwrap_or(false);
+                    if is_non_empty_file {
+                        self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                            text: format!("'{db_path}' already exists."),
+                            secondary_text: "Creating a database here deletes everything stored in this file. Do you really want to overwrite it?".to_string(),
+                            on_accept: Msg::ForceCreateDb,
+                        });
+                    } else {
+                        self.create_db();
+                    }
+                } else {
+                    self.ui
+                        .set_settings_db_create_path_status(Err("No File Selected.".to_string()));
+                }
+            }
+            Msg::ForceCreateDb => self.create_db(),
+            Msg::ItemNameCasing(casing) => {
+                self.ui.item_name_casing = casing;
+                self.save_settings();
+            }
+            Msg::DateFormat(format) => {
+                self.ui.set_date_format(format.trim().to_string());
+                self.save_settings();
+            }
+            Msg::ReceiptSelection(selection) => {
+                // the combobox already shows this selection
+                self.ui.receipt_selection = selection;
+                self.save_settings();
+            }
+            Msg::ExcludeRefunds(exclude) => {
+                if exclude != self.ui.exclude_refunds {
+                    self.exclude_refunds(exclude);
+                    self.save_settings();
+                }
+            }
+            Msg::DecimalQuantities(decimal) => {
+                self.ui.set_decimal_quantities(decimal);
+                self.save_settings();
+            }
+            Msg::RequiredInputs(inputs) => {
+                self.update_required_rows((inputs, self.ui.required_rows.1));
+            }
+            Msg::RequiredOutputs(outputs) => {
+                self.update_required_rows((self.ui.required_rows.0, outputs));
+            }
+            Msg::Rounding(rounding) => {
+                self.ui.set_rounding(rounding);
+                self.save_settings();
+            }
+            Msg::BaseUnit(base_unit) => {
+                self.ui.set_base_unit(base_unit);
+                self.save_settings();
+            }
+            Msg::SetRate { unit, base, rate } => {
+                if let Some(conn) = &self.conn {
+                    if unit == base {
+                        self.ui
+                            .report("save the rate", "A unit has no rate to itself.");
+                    } else if let Err(err) = rates::set_rate(conn, unit, base, rate) {
+                        self.ui.report("save the rate", err);
+                    } else {
+                        self.ui.load_rates(conn);
+                    }
+                }
+            }
+            Msg::StorageDateFormat(format) => {
+                // the combobox already shows this format
+                self.ui.storage_date_format = format;
+                self.analysis.emit(AnalysisMsg::StorageDateFormat(format));
+                self.save_settings();
+                if self.conn.is_some() {
+                    self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                        text: format!("Convert the existing receipt dates to {format}?"),
+                        secondary_text: format!(
+                            "New receipts are stored with {format} dates. Converting rewrites the dates of all receipts in '{}', otherwise both formats are mixed.",
+                            self.ui.settings_db_path.trim()
+                        ),
+                        on_accept: Msg::ForceConvertReceiptDates(format),
+                    });
+                }
+            }
+            Msg::ForceConvertReceiptDates(format) => {
+                if let Some(conn) = &self.conn {
+                    match convert_receipt_dates(conn, format) {
+                        Ok(count) => self
+                            .ui
+                            .set_status(format!("Converted {count} receipt date(s).")),
+                        Err(err) => self.ui.report("convert the receipt dates", err),
+                    }
+                    self.refresh();
+                }
+            }
+            Msg::LargeAmount { unit, amount } => {
+                self.ui.update_large_amounts(|large_amounts| {
+                    if amount > 0.0 {
+                        large_amounts.insert(unit.as_str().to_string(), amount);
+                    } else {
+                        large_amounts.remove(unit.as_str());
+                    }
+                });
+                self.save_settings();
+            }
+            Msg::WarnDuplicateItems(warn) => {
+                self.ui.warn_duplicate_items = warn;
+                self.save_settings();
+            }
+            Msg::WarnDuplicateStores(warn) => {
+                self.ui.warn_duplicate_stores = warn;
+                self.save_settings();
+            }
+            Msg::WarnDuplicateReceipts(warn) => {
+                self.ui.warn_duplicate_receipts = warn;
+                self.save_settings();
+            }
+            Msg::MajorUnits(major_units) => {
+                self.ui.set_major_units(major_units);
+                self.dashboard.emit(DashboardMsg::MajorUnits(major_units));
+                self.analysis.emit(AnalysisMsg::MajorUnits(major_units));
+                self.save_settings();
+            }
+            Msg::WalMode(wal) => {
+                self.ui.wal_mode = wal;
+                self.apply_journal_mode();
+                self.save_settings();
+            }
+            Msg::PriceIncrements(step, page) => {
+                if (step, page) != self.ui.price_increments {
+                    self.ui.set_price_increments((step, page));
+                    self.save_settings();
+                }
+            }
+            Msg::CheckIntegrity => {
+                if let Some(conn) = &self.conn {
+                    let report = match integrity::check_integrity(conn) {
+                        Ok(issues) if issues.is_empty() => "No issues found.".to_string(),
+                        Ok(issues) => {
+                            // keep the dialog on screen for databases with many issues
+                            const MAX_LISTED: usize = 30;
+                            let mut report = format!("{} issue(s) found:\n", issues.len());
+                            for issue in issues.iter().take(MAX_LISTED) {
+                                report.push_str(&format!("\n{issue}"));
+                            }
+                            if issues.len() > MAX_LISTED {
+                                report.push_str(&format!(
+                                    "\n… and {} more.",
+                                    issues.len() - MAX_LISTED
+                                ));
+                            }
+                            report
+                        }
+                        Err(err) => format!("The check could not be completed: {err}"),
+                    };
+                    self.ui.set_integrity_report(Some(report));
+                }
+            }
+            Msg::CleanUpOrphanedItems { reassign } => {
+                let cleanup = match (reassign, self.ui.selected_receipt_id()) {
+                    (false, _) => Some(OrphanCleanup::Delete),
+                    (true, receipt_id) => receipt_id.map(OrphanCleanup::Reassign),
+                };
+                if let (Some(conn), Some(cleanup)) = (&self.conn, cleanup) {
+                    match integrity::count_orphaned_items(conn) {
+                        Ok(0) => self.ui.set_integrity_report(Some(
+                            "No items without a receipt found.".to_string(),
+                        )),
+                        Ok(count) => {
+                            let text = match cleanup {
+                                OrphanCleanup::Delete => {
+                                    format!("Delete {count} item(s) without a receipt?")
+                                }
+                                OrphanCleanup::Reassign(receipt_id) => format!(
+                                    "Move {count} item(s) without a receipt to receipt #{receipt_id}?"
+                                ),
+                            };
+                            self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                                text,
+                                secondary_text: "Their receipt no longer exists, e.g. because another tool deleted it.".to_string(),
+                                on_accept: Msg::ForceCleanUpOrphanedItems(cleanup),
+                            });
+                        }
+                        Err(err) => self.ui.report("clean up the orphaned items", err),
+                    }
+                }
+            }
+            Msg::ForceCleanUpOrphanedItems(cleanup) => {
+                if let Some(conn) = &self.conn {
+                    match integrity::clean_up_orphaned_items(conn, cleanup) {
+                        Ok(count) => {
+                            self.ui.set_integrity_report(Some(match cleanup {
+                                OrphanCleanup::Delete => format!("Deleted {count} item(s)."),
+                                OrphanCleanup::Reassign(receipt_id) => {
+                                    format!("Moved {count} item(s) to receipt #{receipt_id}.")
+                                }
+                            }));
+                            self.refresh();
+                        }
+                        Err(err) => self.ui.report("clean up the orphaned items", err),
+                    }
+                }
+            }
+            Msg::CloseIntegrityReport => self.ui.set_integrity_report(None),
+            Msg::SaveSettings => {
+                self.ui.set_settings_error(None);
+                self.save_settings();
+            }
+            Msg::ChooseSettingsFile => {
+                self.ui.set_settings_error(None);
+                let path = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .show_save_single_file()
+                    .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                if let Some(path) = path {
+                    self.ui.settings_file = path.to_string_lossy().to_string();
+                }
+                self.save_settings();
+            }
+            Msg::CloseSettingsError => self.ui.set_settings_error(None),
+            Msg::CloseError => self.ui.set_app_error(None),
+            Msg::PopOutAnalysis(popped_out) => self.ui.set_analysis_popped_out(popped_out),
+            Msg::ExportSettings => {
+                let path = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .show_save_single_file()
+                    .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                if let Some(path) = path {
+                    let path = path.to_string_lossy().to_string();
+                    match write_settings(&path, &self.settings()) {
+                        Ok(()) => self.ui.set_settings_status(format!(
+                            "The settings were exported to '{path}'."
+                        )),
+                        Err(err) => {
+                            eprintln!("[export settings]{err:#?}");
+                            self.ui
+                                .set_settings_status(format!("Could not write to '{path}': {err}"));
+                        }
+                    }
+                }
+            }
+            Msg::ResetSettings => {
+                self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                    text: "Reset all settings?".to_string(),
+                    secondary_text: "Every setting is set back to its default. The connected database, the recent databases, the write-ahead log and the format of stored dates are kept.".to_string(),
+                    on_accept: Msg::ForceResetSettings,
+                });
+            }
+            Msg::ForceResetSettings => self.reset_settings(),
+            Msg::Quit => {
+                // the analysis tab saves its queries with a delay
+                self.analysis.state().get_mut().model.disconnect();
+                // the divider moves in many small steps, it is only saved once, like the last
+                // selected receipt
+                if self.ui.unsaved_changes {
+                    self.save_settings();
+                }
+                if let Some(conn) = self.conn.take() {
+                    close_db(conn);
+                }
+                relm4::main_application().quit();
+            }
+            Msg::InputPanelPosition(position) => {
+                self.ui.input_panel_position = Some(position);
+                self.ui.unsaved_changes = true;
+            }
+            Msg::StickyItemFields(sticky) => {
+                self.ui.sticky_item_fields = sticky;
+                self.save_settings();
+            }
+            Msg::CollapseNameWhitespace(collapse) => {
+                // the check button already shows the setting
+                self.ui.collapse_name_whitespace = collapse;
+                self.save_settings();
+            }
+            Msg::CsvDelimiter(delimiter) => {
+                // the combobox already shows this delimiter
+                self.ui.csv_delimiter = delimiter;
+                self.analysis.emit(AnalysisMsg::CsvDelimiter(delimiter));
+                self.save_settings();
+            }
+            Msg::ImportItems(receipt_idx) => {
+                if let (Some(conn), Some(receipt_idx)) = (&self.conn, receipt_idx) {
+                    let receipt_id = self.ui.receipts.0[receipt_idx as usize].id;
+                    let path = FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .show_open_single_file()
+                        .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                    if let Some(path) = path {
+                        let imported = std::fs::read_to_string(path)
+                            .map_err(AppError::from)
+                            .and_then(|text| {
+                                import_items(
+                                    conn,
+                                    receipt_id,
+                                    &text,
+                                    self.ui.csv_delimiter,
+                                    self.ui.item_name_casing,
+                                )
+                                .map_err(AppError::from)
+                            });
+                        match imported {
+                            Ok(count) => {
+                                self.ui.set_status(format!("Imported {count} item(s)."));
+                                self.load_item_names();
+                                self.ui
+                                    .update_session_summary(|summary| summary.items += count);
+                                self.ui.load_items_today(conn);
+                                self.ui.load_recent_items(conn);
+                            }
+                            Err(err) => self.ui.report("import the items", err),
+                        }
+                        self.ui.load_totals(conn, Some(receipt_id));
+                    }
+                }
+            }
+            Msg::RescalePrices {
+                unit_idx,
+                factor,
+                divide,
+            } => {
+                if let (Some(conn), Some(unit)) = (
+                    &self.conn,
+                    unit_idx.and_then(|idx| Unit::from_idx(idx).ok()),
+                ) {
+                    let item_count = conn.query_row(
+                        "SELECT COUNT(*) FROM Item WHERE unit == ?1;",
+                        params![unit.as_str()],
+                        |row| row.get::<_, i64>(0),
+                    );
+                    match item_count {
+                        Ok(item_count) => {
+                            let operation = if divide { "Divide" } else { "Multiply" };
+                            self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                                text: format!("{operation} all {unit} prices by {factor}?"),
+                                secondary_text: format!(
+                                    "This changes the price of {item_count} item(s) in '{}'.",
+                                    self.ui.settings_db_path.trim()
+                                ),
+                                on_accept: Msg::ForceRescalePrices {
+                                    unit,
+                                    factor,
+                                    divide,
+                                },
+                            });
+                        }
+                        Err(err) => self.ui.report("rescale the prices", err),
+                    }
+                }
+            }
+            Msg::ForceRescalePrices {
+                unit,
+                factor,
+                divide,
+            } => {
+                if let Some(conn) = &self.conn {
+                    if let Err(err) = rescale_prices(conn, &unit, factor, divide) {
+                        self.ui.report("rescale the prices", err);
+                    } else if let Some(receipt_id) = self.ui.selected_receipt_id() {
+                        self.ui.load_totals(conn, Some(receipt_id));
+                    }
+                }
+            }
+            Msg::UpdateItemPrices {
+                name,
+                price,
+                unit_idx,
+                from,
+                to,
+            } => {
+                if let (Some(conn), Some(unit)) = (
+                    &self.conn,
+                    unit_idx.and_then(|idx| Unit::from_idx(idx).ok()),
+                ) {
+                    let resolve = |input: &str| {
+                        date::resolve_date(input, StorageFormat::Iso)
+                            .map_err(|err| AppError::from(err.to_string()))
+                    };
+                    let planned = Money::parse(&price, unit)
+                        .map_err(AppError::from)
+                        .and_then(|price| Ok((price, resolve(&from)?, resolve(&to)?)))
+                        .and_then(|(price, from, to)| {
+                            price_update::plan_price_update(conn, name.trim(), price, &from, &to)
+                                .map_err(AppError::from)
+                        });
+                    match planned {
+                        Ok(update) if update.items.is_empty() => self.ui.report(
+                            "update the prices",
+                            format!(
+                                "No '{}' item in {unit} was bought between {} and {}.",
+                                name.trim(),
+                                from.trim(),
+                                to.trim()
+                            ),
+                        ),
+                        Ok(update) => {
+                            let mut items = update
+                                .items
+                                .iter()
+                                .take(MAX_LISTED_ACTIONS)
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>();
+                            if update.items.len() > MAX_LISTED_ACTIONS {
+                                items.push(format!(
+                                    "… and {} more",
+                                    update.items.len() - MAX_LISTED_ACTIONS
                                 ));
                             }
-                            Ok(None) => {
-                                let insert_query = conn.execute(
-                                    "INSERT INTO Store (name, location) VALUES (?1, ?2);",
-                                    params![store_name, store_location],
-                                );
-                                if let Err(err) = insert_query {
-                                    eprintln!("[add store]{err:#?}");
-                                } else {
-                                    self.load_stores();
-                                    self.ui.reset_store_fields = true;
-                                }
-                            }
-                            Err(err) => eprintln!("[add receipt]{err:#?}"),
+                            self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                                text: format!(
+                                    "Set the price of {} '{}' item(s) to {:#}?",
+                                    update.items.len(),
+                                    update.name,
+                                    update.price
+                                ),
+                                secondary_text: items.join("\n"),
+                                on_accept: Msg::ForceUpdateItemPrices(update),
+                            });
                         }
+                        Err(err) => self.ui.report("update the prices", err),
                     }
                 }
             }
-            Msg::ForceAddStore(store) => {
+            Msg::ForceUpdateItemPrices(update) => {
                 if let Some(conn) = &self.conn {
-                    let insert_query = conn.execute(
-                        "INSERT INTO Store (name, location) VALUES (?1, ?2);",
-                        params![store.name.as_str(), store.location.as_str()],
-                    );
-                    if let Err(err) = insert_query {
-                        eprintln!("[add store]{err:#?}");
-                    } else {
-                        self.load_stores();
-                        self.ui.reset_store_fields = true;
+                    match price_update::apply_price_update(conn, &update) {
+                        Ok(count) => {
+                            self.ui
+                                .set_status(format!("Changed the price of {count} item(s)."));
+                            self.dashboard.emit(DashboardMsg::Refresh);
+                            self.ui.load_items_today(conn);
+                            self.ui.load_recent_items(conn);
+                            if let Some(receipt_id) = self.ui.selected_receipt_id() {
+                                self.ui.load_totals(conn, Some(receipt_id));
+                            }
+                        }
+                        Err(err) => self.ui.report("update the prices", err),
                     }
                 }
             }
-            Msg::AddReceipt(receipt) => {
-                if let (Some(conn), Some(store_idx)) = (&self.conn, receipt.store_idx) {
-                    let store = &self.ui.stores.0[store_idx as usize];
-                    let receipt_date = receipt.date.format("%F").unwrap();
-                    let existence_check_query = conn
-                        .query_row(
-                            "SELECT id FROM Receipt WHERE store == ?1 AND date == ?2;",
-                            params![store.id, receipt_date.as_str()],
-                            |row| {
-                                let id: i64 = row.get(0)?;
-                                Ok(id)
-                            },
-                        )
-                        .optional();
-                    match existence_check_query {
-                        Ok(Some(_)) => {
-                            self.dialog.emit(add_duplicate_alert::DialogMsg::Show(
-                                add_duplicate_alert::WarningOrigin::Receipt {
-                                    store: store.clone(),
-                                    date: receipt.date,
-                                },
-                            ));
-                        }
-                        Ok(None) => {
-                            let insert_query = conn.execute(
-                                "INSERT INTO Receipt (store, date) VALUES (?1, ?2);",
-                                params![store.id, receipt_date.as_str()],
-                            );
-                            if let Err(err) = insert_query {
-                                eprintln!("[add receipt]{err:#?}");
-                            } else {
-                                self.load_receipts();
+            Msg::ImportStatement(unit_idx) => {
+                if let (Some(conn), Some(unit)) = (
+                    &self.conn,
+                    unit_idx.and_then(|idx| Unit::from_idx(idx).ok()),
+                ) {
+                    let path = FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .show_open_single_file()
+                        .unwrap_or_else(|err| self.ui.dialog_failed(err));
+                    if let Some(path) = path {
+                        let planned = std::fs::read_to_string(path)
+                            .map_err(AppError::from)
+                            .and_then(|text| {
+                                statement::parse_statement(&text, self.ui.csv_delimiter, unit)
+                                    .map_err(AppError::from)
+                            })
+                            .and_then(|rows| {
+                                statement::plan_import(conn, rows, self.ui.storage_date_format)
+                                    .map_err(AppError::from)
+                            });
+                        match planned {
+                            Ok(planned) if planned.is_empty() => self
+                                .ui
+                                .report("import the statement", "The statement has no rows."),
+                            Ok(planned) => {
+                                let mut actions = planned
+                                    .iter()
+                                    .take(MAX_LISTED_ACTIONS)
+                                    .map(ToString::to_string)
+                                    .collect::<Vec<_>>();
+                                if planned.len() > MAX_LISTED_ACTIONS {
+                                    actions.push(format!(
+                                        "… and {} more",
+                                        planned.len() - MAX_LISTED_ACTIONS
+                                    ));
+                                }
+                                self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                                    text: format!("Import {} statement row(s)?", planned.len()),
+                                    secondary_text: actions.join("\n"),
+                                    on_accept: Msg::ForceImportStatement(planned),
+                                });
                             }
+                            Err(err) => self.ui.report("import the statement", err),
                         }
-                        Err(err) => eprintln!("[add receipt]{err:#?}"),
                     }
                 }
             }
-            Msg::ForceAddReceipt(store_id, date) => {
+            Msg::ForceImportStatement(planned) => {
                 if let Some(conn) = &self.conn {
-                    let insert_query = conn.execute(
-                        "INSERT INTO Receipt (store, date) VALUES (?1, ?2);",
-                        params![store_id, date.as_str()],
-                    );
-                    if let Err(err) = insert_query {
-                        eprintln!("[add receipt]{err:#?}");
+                    match statement::apply_import(conn, &planned) {
+                        Ok(count) => {
+                            self.ui
+                                .set_status(format!("Imported {count} statement row(s)."));
+                            self.load_stores();
+                            self.load_receipts();
+                            self.load_item_names();
+                            self.ui
+                                .update_session_summary(|summary| summary.items += count);
+                            self.dashboard.emit(DashboardMsg::Refresh);
+                            if let Some(conn) = &self.conn {
+                                self.ui.load_items_today(conn);
+                                self.ui.load_recent_items(conn);
+                                if let Some(receipt_id) = self.ui.selected_receipt_id() {
+                                    self.ui.load_totals(conn, Some(receipt_id));
+                                }
+                            }
+                        }
+                        Err(err) => self.ui.report("import the statement", err),
+                    }
+                }
+            }
+            Msg::ValidateName(field, text) => match field {
+                NameField::StoreName => self.ui.update_store_name_valid(|s| s.validate(&text)),
+                NameField::StoreLocation => {
+                    self.ui.update_store_location_valid(|s| s.validate(&text))
+                }
+                NameField::ItemName => {
+                    self.ui.update_item_name_valid(|s| s.validate(&text));
+                    self.ui.last_price_generation += 1;
+                    if text.trim().is_empty() {
+                        self.ui.set_last_price(None);
                     } else {
-                        self.load_receipts();
+                        let generation = self.ui.last_price_generation;
+                        gtk::glib::timeout_add_local_once(LAST_PRICE_DELAY, move || {
+                            sender.input(Msg::LookupLastPrice(generation, text));
+                        });
+                    }
+                }
+            },
+            Msg::LookupLastPrice(generation, name) => {
+                if let (true, Some(conn)) =
+                    (generation == self.ui.last_price_generation, &self.conn)
+                {
+                    match LastPrice::load(conn, &name) {
+                        Ok(last_price) => self.ui.set_last_price(last_price),
+                        Err(err) => eprintln!("[last price]{err:#?}"),
                     }
                 }
             }
-            Msg::AddItem(item) => {
-                if let (Some(conn), Some(receipt_idx)) = (&self.conn, item.receipt_idx) {
-                    let item_name = item.name.trim();
-                    if !item_name.is_empty() {
-                        let receipt = &self.ui.receipts.0[receipt_idx as usize];
-                        let name = if self.ui.capitalize_item_names {
-                            item_name.to_uppercase()
-                        } else {
-                            item_name.to_string()
-                        };
-                        let insert_query = conn.execute(
-                            "INSERT INTO Item (name, quantity, price, unit, receipt) VALUES (?1, ?2, ?3, ?4, ?5)",
-                            params![name, item.quantity, item.price, item.unit.as_str(), receipt.id],
-                        );
-                        if let Err(err) = insert_query {
-                            eprintln!("[add item]{err:#?}");
-                        } else {
-                            self.ui.reset_item_fields = true;
+            Msg::ReceiptChanged(receipt_idx) => {
+                // keep the selection in sync without re-populating the combobox
+                self.ui.receipts.1 = receipt_idx;
+                self.ui.set_budget_status(None);
+                let currency = receipt_idx
+                    .and_then(|idx| self.ui.receipts.0.get(idx as usize))
+                    .and_then(|receipt| receipt.currency);
+                self.ui.set_receipt_currency(currency);
+                if let (Some(conn), Some(receipt_idx)) = (&self.conn, receipt_idx) {
+                    let receipt_id = self.ui.receipts.0[receipt_idx as usize].id;
+                    if let Some(db) = &self.ui.connected_db {
+                        if self.ui.last_receipts.insert(db.clone(), receipt_id) != Some(receipt_id)
+                        {
+                            self.ui.unsaved_changes = true;
                         }
-
-                        // update total
-                        self.ui.set_total(Total::for_receipt(conn, receipt.id));
                     }
+                    self.ui.load_totals(conn, Some(receipt_id));
+                    self.ui
+                        .set_printed_total(printed_total_of_receipt(conn, receipt_id));
                 }
             }
-            Msg::SelectUnit(unit) => self.ui.set_selected_unit(unit),
-            Msg::OpenDbDialog => {
-                let path = FileDialog::new().show_open_single_file().unwrap();
-                if let Some(path) = path {
-                    let path = path.to_string_lossy().to_string();
-                    self.ui.set_settings_db_path(path);
+            Msg::SetPrintedTotal {
+                receipt_idx,
+                printed_total,
+            } => {
+                if let (Some(conn), Some(receipt_idx)) = (&self.conn, receipt_idx) {
+                    let receipt = &self.ui.receipts.0[receipt_idx as usize];
+                    let rounding = self.ui.rounding;
+                    let (price, unit) = printed_total
+                        .map(|(price, unit)| {
+                            let price = Money::from_major(price, unit, rounding);
+                            (Some(price.minor), Some(unit.as_str().to_string()))
+                        })
+                        .unwrap_or_default();
+                    let update_query = conn.execute(
+                        "UPDATE Receipt SET printed_total = ?1, printed_total_unit = ?2, updated_at = CURRENT_TIMESTAMP WHERE id == ?3;",
+                        params![price, unit, receipt.id],
+                    );
+                    if let Err(err) = update_query {
+                        self.ui.report("save the printed total", err);
+                    }
+                    self.ui
+                        .set_printed_total(printed_total_of_receipt(conn, receipt.id));
                 }
             }
-            Msg::OpenCreateDbDialog => {
-                let path = FileDialog::new().show_save_single_file().unwrap();
-                if let Some(path) = path {
-                    let path = path.to_string_lossy().to_string();
-                    self.ui.set_settings_db_create_path(path);
+            Msg::DeleteReceipts(receipt_ids) => {
+                if let Some(conn) = &self.conn {
+                    let mut item_count = 0;
+                    for receipt_id in &receipt_ids {
+                        let count = conn.query_row(
+                            "SELECT COUNT(*) FROM Item WHERE receipt == ?1;",
+                            params![receipt_id],
+                            |row| row.get::<_, i64>(0),
+                        );
+                        match count {
+                            Ok(count) => item_count += count,
+                            Err(err) => self.ui.report("delete the receipts", err),
+                        }
+                    }
+                    self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                        text: format!("Delete {} receipt(s)?", receipt_ids.len()),
+                        secondary_text: format!(
+                            "This also deletes the {item_count} item(s) on these receipts."
+                        ),
+                        on_accept: Msg::ForceDeleteReceipts(receipt_ids),
+                    });
                 }
             }
-            Msg::ConnectDb => {
-                if !self.ui.settings_db_path.trim().is_empty() {
-                    if let Ok(conn) = Connection::open(self.ui.settings_db_path.trim()) {
-                        let conn = Rc::new(conn);
-                        self.analysis.emit(AnalysisMsg::ConnectDb(Rc::clone(&conn)));
-                        self.conn = Some(conn);
+            Msg::ForceDeleteReceipts(receipt_ids) => {
+                if let Some(conn) = &self.conn {
+                    if let Err(err) = delete_receipts(conn, &receipt_ids) {
+                        self.ui.report("delete the receipts", err);
+                    } else {
+                        let selected_id = self
+                            .ui
+                            .receipts
+                            .1
+                            .and_then(|idx| self.ui.receipts.0.get(idx as usize))
+                            .map(|row| row.id)
+                            .filter(|id| !receipt_ids.contains(id));
                         self.load_stores();
                         self.load_receipts();
-                        self.save_settings();
-                        self.ui.update_store_name_valid(NameStatus::connect);
-                        self.ui.update_store_location_valid(NameStatus::connect);
-                        self.ui.update_item_name_valid(NameStatus::connect);
-                    } else {
-                        self.ui.set_settings_db_path_status(
-                            "Selected File is not a valid Database.".to_string(),
+                        self.ui.receipts.1 = position_of_key(
+                            &self.ui.receipts.0,
+                            |row| row.id,
+                            selected_id.as_ref(),
                         );
-                    }
-                } else {
-                    self.ui
-                        .set_settings_db_path_status("No File Selected.".to_string());
-                }
-            }
-            Msg::CreateDb => {
-                let db_path = self.ui.settings_db_create_path.trim();
-                if !db_path.is_empty() {
-                    if File::create(db_path).is_ok() {
-                        if let Ok(conn) = Connection::open(db_path) {
-                            if conn.execute(schema::SCHEMA_STORE, []).is_ok()
-                                && conn.execute(schema::SCHEMA_RECEIPT, []).is_ok()
-                                && conn.execute(schema::SCHEMA_ITEM, []).is_ok()
-                            {
-                                let db_path = db_path.to_string();
-                                self.ui.set_settings_db_path(db_path);
-                                self.ui.set_settings_db_create_path_status(
-                                    "Database created successfully.".to_string(),
-                                );
-                            } else {
-                                let _ = std::fs::remove_file(db_path);
-                                self.ui.set_settings_db_create_path_status(
-                                    "Could not initialize the database.".to_string(),
-                                );
-                            }
+                        if let (Some(conn), Some(id)) = (&self.conn, selected_id) {
+                            self.ui.load_totals(conn, Some(id));
+                            self.ui
+                                .set_printed_total(printed_total_of_receipt(conn, id));
                         } else {
-                            self.ui.set_settings_db_create_path_status(
-                                "Could not open the database.".to_string(),
-                            );
+                            self.ui.set_total(Total::new());
+                            self.ui.set_net_total(Total::new());
+                            self.ui.set_item_groups(Vec::new());
+                            self.ui.set_printed_total(None);
                         }
-                    } else {
-                        self.ui.set_settings_db_create_path_status(
-                            "Could not create/truncate the file.".to_string(),
-                        );
                     }
-                } else {
-                    self.ui
-                        .set_settings_db_create_path_status("No File Selected.".to_string());
                 }
             }
-            Msg::CapitalizeItem(cap) => {
-                self.ui.capitalize_item_names = cap;
-                self.save_settings();
+            Msg::PageChanged(page) => {
+                // the notebook already shows this page, so don't trigger a view update
+                self.ui.page = page;
             }
-            Msg::ValidateStoreName(name) => {
-                if !name.trim().is_empty() {
-                    self.ui.update_store_name_valid(NameStatus::name_non_empty);
-                } else {
-                    self.ui.update_store_name_valid(NameStatus::name_empty);
+            Msg::CyclePage(delta) => self
+                .ui
+                .set_page(self.ui.tab_layout.cycle(self.ui.page as u32, delta) as i32),
+            Msg::SelectPage(nth) => {
+                if let Some(page) = self.ui.tab_layout.visible_positions().get(nth as usize) {
+                    self.ui.set_page(*page as i32);
                 }
             }
-            Msg::ValidateStoreLocation(location) => {
-                if !location.trim().is_empty() {
-                    self.ui
-                        .update_store_location_valid(NameStatus::name_non_empty);
-                } else {
-                    self.ui.update_store_location_valid(NameStatus::name_empty);
-                }
+            Msg::TabVisible(tab, visible) => {
+                // the check button already shows the change
+                self.ui.tabs.set_visible(tab, visible);
+                self.save_settings();
             }
-            Msg::ValidateItemName(name) => {
-                if !name.trim().is_empty() {
-                    self.ui.update_item_name_valid(NameStatus::name_non_empty);
-                } else {
-                    self.ui.update_item_name_valid(NameStatus::name_empty);
+            Msg::MoveTab(tab, delta) => {
+                self.ui.update_tabs(|tabs| tabs.move_tab(tab, delta));
+                self.save_settings();
+            }
+            Msg::MergeStores => {
+                let source = self.merge_source.state().get().model.chosen();
+                let target = self.merge_target.state().get().model.chosen();
+                if let (Some(conn), Some(source), Some(target)) = (&self.conn, source, target) {
+                    if source.id != target.id {
+                        let receipt_count = conn.query_row(
+                            "SELECT COUNT(*) FROM Receipt WHERE store == ?1;",
+                            params![source.id],
+                            |row| row.get::<_, i64>(0),
+                        );
+                        match receipt_count {
+                            Ok(receipt_count) => {
+                                self.confirm_dialog.emit(confirm_dialog::DialogMsg::Show {
+                                    text: format!(
+                                        "Merge {} ({}) into {} ({})?",
+                                        source.name, source.location, target.name, target.location
+                                    ),
+                                    secondary_text: format!(
+                                        "{receipt_count} receipt(s) will be moved and {source} will be deleted."
+                                    ),
+                                    on_accept: Msg::ForceMergeStores {
+                                        source_id: source.id,
+                                        target_id: target.id,
+                                    },
+                                });
+                            }
+                            Err(err) => self.ui.report("merge the stores", err),
+                        }
+                    }
                 }
             }
-            Msg::ReceiptChanged(receipt_idx) => {
-                if let (Some(conn), Some(receipt_idx)) = (&self.conn, receipt_idx) {
-                    let receipt = &self.ui.receipts.0[receipt_idx as usize];
-                    self.ui.set_total(Total::for_receipt(conn, receipt.id));
+            Msg::ForceMergeStores {
+                source_id,
+                target_id,
+            } => {
+                if let Some(conn) = &self.conn {
+                    if let Err(err) = merge_stores(conn, source_id, target_id) {
+                        self.ui.report("merge the stores", err);
+                    } else {
+                        self.load_stores();
+                        self.load_receipts();
+                    }
                 }
             }
         }
@@ -960,6 +4473,478 @@ impl SimpleComponent for App {
 }
 
 fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("[args]{err}\n{}", args::USAGE);
+            std::process::exit(2);
+        }
+    };
     let app = RelmApp::new("n4tus.sqlbon");
-    app.run::<App>(());
+    // the options are parsed above, GApplication would reject them as unknown
+    app.run_with_args::<App, &str>(args, &[]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = schema::testing::empty_db();
+        conn.execute_batch(
+            "INSERT INTO Store (id, name, location) VALUES (1, 'Shop', 'Town');
+            INSERT INTO Receipt (id, store, date) VALUES (1, 1, '2022-06-01');
+            INSERT INTO Item (name, price, unit, receipt) VALUES ('Milk', 1990, 'NOK', 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table};"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    fn total(rows: &[(&str, i64)]) -> Total {
+        Total(
+            rows.iter()
+                .map(|(unit, price)| TotalRow::new(unit.to_string(), *price))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn total_display_without_units_is_empty() {
+        assert_eq!(Total::new().to_string(), "");
+    }
+
+    #[test]
+    fn total_display_single_unit() {
+        assert_eq!(total(&[("NOK", 1990)]).to_string(), "1990 NOK");
+    }
+
+    #[test]
+    fn total_display_separates_units_by_comma() {
+        assert_eq!(
+            total(&[("EUR", -250), ("NOK", 1990), ("EUR", 0)]).to_string(),
+            "-250 EUR, 1990 NOK, 0 EUR"
+        );
+    }
+
+    #[test]
+    fn total_display_in_major_units() {
+        let total = total(&[("NOK", 1299), ("EUR", -250), ("XYZ", 7)]);
+        assert_eq!(amount_text(&total, true), "12.99 NOK, -2.50 EUR, 7 XYZ");
+        assert_eq!(amount_text(&total, false), "1299 NOK, -250 EUR, 7 XYZ");
+    }
+
+    #[test]
+    fn reconciliation_text_in_major_units() {
+        let printed = Some(Money::new(1000, Unit::NOK));
+        assert_eq!(
+            reconciliation_text(&total(&[("NOK", 1250)]), &printed, true),
+            "differs from the printed total of 10.00 NOK by +2.50 NOK"
+        );
+        assert_eq!(
+            reconciliation_text(&total(&[("NOK", 750)]), &printed, false),
+            "differs from the printed total of 1000 NOK by -250 NOK"
+        );
+    }
+
+    #[test]
+    fn budget_status_in_major_units() {
+        let status = BudgetStatus {
+            month: "2022-06".to_string(),
+            unit: "EUR".to_string(),
+            spent: 12050,
+            budget: 10000,
+        };
+        assert_eq!(
+            amount_text(&status, true),
+            "120.50 of the 100.00 EUR budget spent in 2022-06, 20.50 EUR over budget"
+        );
+    }
+
+    #[test]
+    fn receipts_default_to_the_usual_unit_of_their_store() {
+        let conn = test_db();
+        assert_eq!(store_default_unit(&conn, 1).unwrap(), Some(Unit::NOK));
+        conn.execute_batch(
+            "INSERT INTO Store (id, name, location) VALUES (2, 'Kiosk', 'Port');
+            INSERT INTO Receipt (id, store, date) VALUES (2, 1, '2022-06-02');
+            INSERT INTO Item (name, price, unit, receipt) VALUES ('Coffee', 450, 'EUR', 2);",
+        )
+        .unwrap();
+        // a tie goes to the unit used last
+        assert_eq!(store_default_unit(&conn, 1).unwrap(), Some(Unit::EUR));
+        assert_eq!(store_default_unit(&conn, 2).unwrap(), None);
+
+        let resolve = |idx| {
+            ReceiptCurrency::from_idx(Some(idx))
+                .resolve(&conn, 2)
+                .unwrap()
+        };
+        assert_eq!(resolve(1), None);
+        assert_eq!(resolve(2), Some(Unit::NOK));
+        assert_eq!(resolve(3), Some(Unit::EUR));
+
+        assert_eq!(
+            currency_warning(Unit::EUR, Some(Unit::NOK)),
+            "The items of this receipt are in NOK."
+        );
+        assert!(currency_warning(Unit::NOK, Some(Unit::NOK)).is_empty());
+        assert!(currency_warning(Unit::EUR, None).is_empty());
+    }
+
+    #[test]
+    fn total_for_receipt_groups_by_unit() {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO Receipt (id, store, date) VALUES (2, 1, '2022-06-02');
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Milk', 3, 1990, 'NOK', 1);
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Deposit', 2, -200, 'NOK', 1);
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Coffee', 2, 450, 'EUR', 1);
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Bread', 1, 3000, 'NOK', 2);",
+        )
+        .unwrap();
+        let mut rows = Total::for_receipt(&conn, 1, false)
+            .unwrap()
+            .0
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        // the order of groups is not specified
+        rows.sort();
+        assert_eq!(rows, ["7560 NOK", "900 EUR"]);
+
+        assert_eq!(Total::for_receipt(&conn, 3, false).unwrap().to_string(), "");
+    }
+
+    #[test]
+    fn total_fails_without_an_item_column() {
+        // a database of an older tool without units
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE Item (id INTEGER PRIMARY KEY, name VARCHAR, quantity INT, price INT, receipt INT);
+            INSERT INTO Item (name, quantity, price, receipt) VALUES ('Milk', 1, 1990, 1);",
+        )
+        .unwrap();
+        assert!(Total::for_receipt(&conn, 1, false).is_err());
+    }
+
+    #[test]
+    fn decimal_prices_are_stored_in_minor_units() {
+        let conn = test_db();
+        conn.execute(
+            "INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Cheese', 2, ?1, 'NOK', 1);",
+            params![Unit::NOK.to_minor(12.99, Rounding::HalfUp)],
+        )
+        .unwrap();
+        // 1990 of the milk and 2 × 1299
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "4588 NOK"
+        );
+    }
+
+    #[test]
+    fn total_for_sample_receipts() {
+        let conn = schema::testing::sample_db();
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "6980 NOK"
+        );
+        assert_eq!(
+            Total::for_receipt(&conn, 2, false).unwrap().to_string(),
+            "1950 EUR"
+        );
+        assert_eq!(
+            Total::for_receipt(&conn, 3, false).unwrap().to_string(),
+            "1990 NOK"
+        );
+        // without the deposit
+        assert_eq!(
+            Total::for_receipt(&conn, 3, true).unwrap().to_string(),
+            "2190 NOK"
+        );
+        assert_eq!(
+            Total::for_receipt(&conn, 1, true).unwrap().to_string(),
+            "6980 NOK"
+        );
+    }
+
+    #[test]
+    fn with_transaction_rolls_back_on_failure() {
+        let conn = test_db();
+        let result: rusqlite::Result<()> = with_transaction(&conn, |tx| {
+            tx.execute("DELETE FROM Item;", [])?;
+            tx.execute("DELETE FROM NoSuchTable;", [])?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(count(&conn, "Item"), 1);
+    }
+
+    #[test]
+    fn with_transaction_commits_on_success() {
+        let conn = test_db();
+        delete_receipts(&conn, &[1]).unwrap();
+        assert_eq!(count(&conn, "Item"), 0);
+        assert_eq!(count(&conn, "Receipt"), 0);
+    }
+
+    #[test]
+    fn import_items_is_all_or_nothing() {
+        let conn = test_db();
+        let text = "name;quantity;price;unit\nBread;1;3000;NOK\nCheese;x;5000;NOK\n";
+        assert!(import_items(&conn, 1, text, csv::Delimiter::Comma, NameCasing::Keep).is_err());
+        assert_eq!(count(&conn, "Item"), 1);
+
+        let text = "name;quantity;price;unit\nBread;1;3000;NOK\nCheese;2;5000;EUR\n";
+        assert_eq!(
+            import_items(&conn, 1, text, csv::Delimiter::Comma, NameCasing::Keep),
+            Ok(2)
+        );
+        assert_eq!(count(&conn, "Item"), 3);
+
+        let text = "name;quantity;price;unit\nApples;0,75;1990;NOK\nPears;0;1990;NOK\n";
+        assert!(import_items(&conn, 1, text, csv::Delimiter::Comma, NameCasing::Keep).is_err());
+        let text = "name;quantity;price;unit\nApples;0,75;19,90;NOK\n";
+        assert_eq!(
+            import_items(&conn, 1, text, csv::Delimiter::Comma, NameCasing::Keep),
+            Ok(1)
+        );
+        let price: i64 = conn
+            .query_row(
+                "SELECT price FROM Item WHERE name == 'Apples';",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(price, 1990);
+        // more decimals than the unit has are no price
+        let text = "name;quantity;price;unit\nPears;1;1.999;NOK\n";
+        assert!(import_items(&conn, 1, text, csv::Delimiter::Comma, NameCasing::Keep).is_err());
+    }
+
+    #[test]
+    fn close_db_checkpoints_the_wal() {
+        let path = schema::testing::temp_path("close.db");
+        let wal = path.with_extension("db-wal");
+        let conn = Rc::new(Connection::open(&path).unwrap());
+        schema::init(&conn).unwrap();
+        // a second holder keeps the connection open, so only the checkpoint empties the log
+        let holder = Rc::clone(&conn);
+        conn.query_row("PRAGMA journal_mode=WAL;", [], |_| Ok(()))
+            .unwrap();
+        conn.execute_batch("INSERT INTO Store (name, location) VALUES ('Shop', 'Town');")
+            .unwrap();
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+        close_db(conn);
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+        drop(holder);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn set_journal_mode_switches_between_wal_and_rollback() {
+        let path = schema::testing::temp_path("wal.db");
+        let conn = Connection::open(&path).unwrap();
+        schema::init(&conn).unwrap();
+        assert_eq!(set_journal_mode(&conn, true).unwrap(), "wal");
+        let synchronous: i64 = conn
+            .query_row("PRAGMA synchronous;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1);
+        assert_eq!(set_journal_mode(&conn, false).unwrap(), "delete");
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn large_amounts_compare_the_line_in_major_units() {
+        let item = |minor: i64, quantity: f64| NewItem {
+            name: "TV".to_string(),
+            quantity,
+            price: Money::new(minor, Unit::NOK),
+            category: None,
+            receipt_id: 1,
+        };
+        let thresholds = HashMap::from([("NOK".to_string(), 1000.0), ("EUR".to_string(), 0.0)]);
+        let exceeded = |item: &NewItem| exceeded_large_amount(item, &thresholds, Rounding::HalfUp);
+        assert_eq!(exceeded(&item(100000, 1.0)), None);
+        assert_eq!(
+            exceeded(&item(100001, 1.0)),
+            Some(Money::new(100000, Unit::NOK))
+        );
+        assert!(exceeded(&item(40000, 3.0)).is_some());
+        assert!(exceeded(&item(-200000, 1.0)).is_some());
+        assert!(exceeded(&item(200000, 0.5)).is_none());
+        let eur = NewItem {
+            price: Money::new(9999999, Unit::EUR),
+            ..item(0, 1.0)
+        };
+        assert_eq!(exceeded(&eur), None);
+        assert_eq!(
+            large_amounts_text(&thresholds, Rounding::HalfUp),
+            "Confirm lines over 1000.00 NOK."
+        );
+    }
+
+    #[test]
+    fn exported_settings_are_read_with_their_defaults() {
+        // settings of a first start, only the database is required
+        let settings: Settings = serde_json::from_str(r#"{"db_file": "receipts.db"}"#).unwrap();
+        assert!(settings.warn_duplicate_items);
+        assert_eq!(settings.price_increments, default_price_increments());
+        assert_eq!(settings.required_rows, (0, 1));
+        assert!(!settings.collapse_name_whitespace);
+        assert_eq!(settings.rounding, Rounding::HalfUp);
+
+        let path = schema::testing::temp_path("settings.json")
+            .to_string_lossy()
+            .to_string();
+        let settings = Settings {
+            major_units: true,
+            date_format: "%d.%m.%Y".to_string(),
+            rounding: Rounding::HalfEven,
+            ..settings
+        };
+        write_settings(&path, &settings).unwrap();
+        let exported: Settings = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        assert_eq!(exported.db_file, "receipts.db");
+        assert!(exported.major_units);
+        assert_eq!(exported.date_format, "%d.%m.%Y");
+        assert_eq!(exported.rounding, Rounding::HalfEven);
+        std::fs::remove_file(&path).unwrap();
+        assert!(write_settings("/nonexistent/sqlbon_settings.json", &settings).is_err());
+    }
+
+    #[test]
+    fn receipt_dates_are_converted_between_formats() {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO Receipt (id, store, date) VALUES (2, 1, '15.06.2022');
+            INSERT INTO Item (name, price, unit, receipt) VALUES ('Bread', 3000, 'NOK', 2);
+            INSERT INTO Budget (store, unit, amount) VALUES (1, 'NOK', 10000);",
+        )
+        .unwrap();
+        let dates = |conn: &Connection| {
+            conn.prepare("SELECT date FROM Receipt ORDER BY id;")
+                .unwrap()
+                .query_map([], |row| row.get::<_, String>(0))
+                .unwrap()
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .unwrap()
+        };
+        // mixed formats still count towards the same month
+        let status = BudgetStatus::for_receipt(&conn, 2, "NOK").unwrap().unwrap();
+        assert_eq!((status.month.as_str(), status.spent), ("2022-06", 4990));
+
+        assert_eq!(
+            convert_receipt_dates(&conn, StorageFormat::DayMonthYear),
+            Ok(1)
+        );
+        assert_eq!(dates(&conn), ["01.06.2022", "15.06.2022"]);
+        assert_eq!(convert_receipt_dates(&conn, StorageFormat::Iso), Ok(2));
+        assert_eq!(dates(&conn), ["2022-06-01", "2022-06-15"]);
+    }
+
+    #[test]
+    fn totals_multiply_prices_entered_per_unit_or_per_line() {
+        let conn = test_db();
+        let insert = |price: i64, quantity: f64| {
+            conn.execute(
+                "INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Egg', ?1, ?2, 'NOK', 1);",
+                params![quantity, price],
+            )
+            .unwrap();
+        };
+        let nok = |minor: i64| Money::new(minor, Unit::NOK);
+        // per unit: 3 × 2.00 is stored as entered
+        insert(200, 3.0);
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "2590 NOK"
+        );
+        assert_eq!(rounding_note(nok(200), nok(200), 3.0, false), "");
+        // line total: 6.00 for 4 is stored as 1.50 per unit
+        insert(nok(600).per_unit(4.0, Rounding::HalfUp).minor, 4.0);
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "3190 NOK"
+        );
+        assert_eq!(rounding_note(nok(600), nok(150), 4.0, true), "");
+        // line total: 10.00 for 3 does not divide, 3.33 per unit is stored
+        let price = nok(1000).per_unit(3.0, Rounding::HalfUp);
+        insert(price.minor, 3.0);
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "4189 NOK"
+        );
+        assert_eq!(
+            rounding_note(nok(1000), price, 3.0, true),
+            "Stored as 3 × 3.33 NOK, 0.01 NOK less than the entered line total."
+        );
+    }
+
+    #[test]
+    fn weighed_items_round_their_line_to_a_minor_unit() {
+        let conn = test_db();
+        let insert = |price: i64, quantity: f64| {
+            conn.execute(
+                "INSERT INTO Item (name, quantity, price, unit, receipt) VALUES ('Apples', ?1, ?2, 'NOK', 1);",
+                params![quantity, price],
+            )
+            .unwrap();
+        };
+        let nok = |minor: i64| Money::new(minor, Unit::NOK);
+        // 0.75 kg at 19.90 is 14.925, the half is rounded away from zero
+        insert(1990, 0.75);
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "3483 NOK"
+        );
+        assert_eq!(nok(1990).times(0.75), nok(1493));
+        // -0.333 × 3.00 is -0.999
+        insert(-300, round_quantity(1.0 / 3.0));
+        assert_eq!(
+            Total::for_receipt(&conn, 1, false).unwrap().to_string(),
+            "3383 NOK"
+        );
+        // whole quantities stay integers in the column of the quantity
+        let types: Vec<String> = conn
+            .prepare("SELECT typeof(quantity) FROM Item WHERE name == 'Apples' ORDER BY id;")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(types, ["real", "real"]);
+        insert(1990, 2.0);
+        let whole: String = conn
+            .query_row(
+                "SELECT typeof(quantity) FROM Item WHERE name == 'Apples' AND quantity == 2;",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(whole, "integer");
+        // a line total of 10.00 for 0.75 kg is 13.33 per kg, which gives 10.00 again
+        let price = nok(1000).per_unit(0.75, Rounding::HalfUp);
+        assert_eq!(rounding_note(nok(1000), price, 0.75, true), "");
+    }
+
+    #[test]
+    fn window_title_names_the_database_file() {
+        assert_eq!(
+            window_title(Some("/home/me/groceries.db"), true),
+            "SQLBon — groceries.db"
+        );
+        assert_eq!(window_title(Some("bon.db"), true), "SQLBon — bon.db");
+        assert_eq!(window_title(None, true), "SQLBon (disconnected)");
+        assert_eq!(window_title(None, false), "SQLBon");
+    }
 }
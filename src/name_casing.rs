@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How item names are cased when they are added.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum NameCasing {
+    /// As entered
+    #[default]
+    Keep,
+    /// `APPLE JUICE`
+    Upper,
+    /// `Apple juice`, the rest of the name is kept as entered
+    Sentence,
+}
+
+impl NameCasing {
+    pub(crate) const ALL: [NameCasing; 3] =
+        [NameCasing::Keep, NameCasing::Upper, NameCasing::Sentence];
+
+    pub(crate) fn apply(&self, name: &str) -> String {
+        match self {
+            NameCasing::Keep => name.to_string(),
+            NameCasing::Upper => name.to_uppercase(),
+            NameCasing::Sentence => {
+                let start = name.len() - name.trim_start().len();
+                let mut rest = name[start..].chars();
+                match rest.next() {
+                    // the uppercase of a character may be several characters, e.g. ß is SS
+                    Some(first) => format!(
+                        "{}{}{}",
+                        &name[..start],
+                        first.to_uppercase(),
+                        rest.as_str()
+                    ),
+                    None => name.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// Trims the name and replaces every run of whitespace within it by a single space, e.g.
+/// `Rema   1000` by `Rema 1000`.
+pub(crate) fn collapse_whitespace(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl TryFrom<u32> for NameCasing {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        NameCasing::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<NameCasing> for u32 {
+    fn from(casing: NameCasing) -> Self {
+        match casing {
+            NameCasing::Keep => 0,
+            NameCasing::Upper => 1,
+            NameCasing::Sentence => 2,
+        }
+    }
+}
+
+impl fmt::Display for NameCasing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameCasing::Keep => f.write_str("as entered"),
+            NameCasing::Upper => f.write_str("UPPERCASE"),
+            NameCasing::Sentence => f.write_str("Sentence case"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn casings_of_item_names() {
+        assert_eq!(NameCasing::Keep.apply("apple Juice"), "apple Juice");
+        assert_eq!(NameCasing::Upper.apply("apple juice"), "APPLE JUICE");
+        assert_eq!(NameCasing::Sentence.apply("apple juice"), "Apple juice");
+        assert_eq!(NameCasing::Sentence.apply("apple JUICE"), "Apple JUICE");
+    }
+
+    #[test]
+    fn sentence_case_starts_at_the_first_letter() {
+        assert_eq!(NameCasing::Sentence.apply(""), "");
+        assert_eq!(NameCasing::Sentence.apply("   "), "   ");
+        assert_eq!(NameCasing::Sentence.apply("  milk"), "  Milk");
+        assert_eq!(NameCasing::Sentence.apply("ærfugl"), "Ærfugl");
+        assert_eq!(NameCasing::Sentence.apply("ßcheese"), "SScheese");
+        assert_eq!(NameCasing::Sentence.apply("7up"), "7up");
+    }
+
+    #[test]
+    fn whitespace_is_collapsed() {
+        assert_eq!(collapse_whitespace("  Rema   1000 "), "Rema 1000");
+        assert_eq!(collapse_whitespace("apple\t juice"), "apple juice");
+        assert_eq!(collapse_whitespace("   "), "");
+    }
+
+    #[test]
+    fn index_round_trips() {
+        for casing in NameCasing::ALL {
+            assert_eq!(NameCasing::try_from(u32::from(casing)), Ok(casing));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/// Validity of a text field that needs a connected database and a non-empty text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum NameStatus {
+    Valid,
+    NonEmpty,
+    /// Connected, but the text is empty
+    Connected,
+    Invalid,
+}
+
+impl NameStatus {
+    pub(crate) fn connect(&mut self) {
+        match *self {
+            NameStatus::Valid => {}
+            NameStatus::NonEmpty => *self = NameStatus::Valid,
+            NameStatus::Connected => {}
+            NameStatus::Invalid => *self = NameStatus::Connected,
+        }
+    }
+
+    // we never disconnect from a database, only override a connection if it was successful established
+
+    pub(crate) fn name_non_empty(&mut self) {
+        match *self {
+            NameStatus::Valid => {}
+            NameStatus::NonEmpty => {}
+            NameStatus::Connected => *self = NameStatus::Valid,
+            NameStatus::Invalid => *self = NameStatus::NonEmpty,
+        }
+    }
+
+    pub(crate) fn name_empty(&mut self) {
+        match *self {
+            NameStatus::Valid => *self = NameStatus::Connected,
+            NameStatus::NonEmpty => *self = NameStatus::Invalid,
+            NameStatus::Connected => {}
+            NameStatus::Invalid => {}
+        }
+    }
+
+    /// Applies the text of the field, whitespace only counts as empty.
+    pub(crate) fn validate(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            self.name_empty();
+        } else {
+            self.name_non_empty();
+        }
+    }
+
+    /// What is missing before the field is valid.
+    pub(crate) fn hint(&self, field: &str) -> String {
+        match self {
+            NameStatus::Valid => String::new(),
+            NameStatus::NonEmpty => "Connect to a database first.".to_string(),
+            NameStatus::Connected => format!("Enter a {field}."),
+            NameStatus::Invalid => format!("Connect to a database and enter a {field}."),
+        }
+    }
+}
+
+/// The text fields validated with a [`NameStatus`]
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum NameField {
+    StoreName,
+    StoreLocation,
+    ItemName,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(mut status: NameStatus, f: fn(&mut NameStatus)) -> NameStatus {
+        f(&mut status);
+        status
+    }
+
+    #[test]
+    fn connect() {
+        assert_eq!(
+            after(NameStatus::Valid, NameStatus::connect),
+            NameStatus::Valid
+        );
+        assert_eq!(
+            after(NameStatus::NonEmpty, NameStatus::connect),
+            NameStatus::Valid
+        );
+        assert_eq!(
+            after(NameStatus::Connected, NameStatus::connect),
+            NameStatus::Connected
+        );
+        assert_eq!(
+            after(NameStatus::Invalid, NameStatus::connect),
+            NameStatus::Connected
+        );
+    }
+
+    #[test]
+    fn name_non_empty() {
+        let f = NameStatus::name_non_empty;
+        assert_eq!(after(NameStatus::Valid, f), NameStatus::Valid);
+        assert_eq!(after(NameStatus::NonEmpty, f), NameStatus::NonEmpty);
+        assert_eq!(after(NameStatus::Connected, f), NameStatus::Valid);
+        assert_eq!(after(NameStatus::Invalid, f), NameStatus::NonEmpty);
+    }
+
+    #[test]
+    fn name_empty() {
+        let f = NameStatus::name_empty;
+        assert_eq!(after(NameStatus::Valid, f), NameStatus::Connected);
+        assert_eq!(after(NameStatus::NonEmpty, f), NameStatus::Invalid);
+        assert_eq!(after(NameStatus::Connected, f), NameStatus::Connected);
+        assert_eq!(after(NameStatus::Invalid, f), NameStatus::Invalid);
+    }
+
+    #[test]
+    fn validate_treats_whitespace_as_empty() {
+        let mut status = NameStatus::Valid;
+        status.validate("  ");
+        assert_eq!(status, NameStatus::Connected);
+        status.validate(" Milk ");
+        assert_eq!(status, NameStatus::Valid);
+    }
+}
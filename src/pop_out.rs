@@ -0,0 +1,21 @@
+use relm4::gtk::{self, prelude::*};
+
+pub(crate) trait PopOut {
+    /// Moves the child into the window, or back from the window to the end of this box. The
+    /// child is the same widget in both places, so it keeps its state and its component.
+    fn pop_out(&self, child: &impl IsA<gtk::Widget>, window: &gtk::Window, popped_out: bool);
+}
+
+impl PopOut for gtk::Box {
+    fn pop_out(&self, child: &impl IsA<gtk::Widget>, window: &gtk::Window, popped_out: bool) {
+        let child = child.upcast_ref::<gtk::Widget>();
+        let parent = child.parent();
+        if popped_out && parent.as_ref() == Some(self.upcast_ref()) {
+            self.remove(child);
+            window.set_child(Some(child));
+        } else if !popped_out && parent.as_ref() == Some(window.upcast_ref()) {
+            window.set_child(None::<&gtk::Widget>);
+            self.append(child);
+        }
+    }
+}
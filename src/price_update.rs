@@ -0,0 +1,153 @@
+use crate::date;
+use crate::unit::Money;
+use crate::with_transaction;
+use rusqlite::{params, Connection};
+use std::fmt;
+
+/// An item that gets the new price, listed before the update is confirmed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AffectedItem {
+    receipt: i64,
+    /// As stored
+    date: String,
+    store: String,
+    quantity: f64,
+    price: Money,
+}
+
+impl fmt::Display for AffectedItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let AffectedItem {
+            receipt,
+            date,
+            store,
+            quantity,
+            price,
+        } = self;
+        write!(
+            f,
+            "{date}: {quantity} × {price:#} at '{store}', receipt #{receipt}"
+        )
+    }
+}
+
+/// Setting the price of all items of a name and unit on the receipts of a date range.
+/// Created by [`plan_price_update`], applied by [`apply_price_update`].
+#[derive(Clone, Debug)]
+pub(crate) struct PriceUpdate {
+    pub(crate) name: String,
+    pub(crate) price: Money,
+    pub(crate) items: Vec<AffectedItem>,
+}
+
+/// Finds the items with the name and the unit of the price on receipts from `from` to `to`,
+/// both `YYYY-MM-DD` and included. Receipt dates are compared in either storage format.
+pub(crate) fn plan_price_update(
+    conn: &Connection,
+    name: &str,
+    price: Money,
+    from: &str,
+    to: &str,
+) -> rusqlite::Result<PriceUpdate> {
+    let items = conn
+        .prepare(&format!(
+            "SELECT Receipt.id, Receipt.date, Store.name, Item.quantity, Item.price FROM Item
+            INNER JOIN Receipt ON Item.receipt == Receipt.id
+            INNER JOIN Store ON Receipt.store == Store.id
+            WHERE Item.name == ?1 AND Item.unit == ?2 AND date({0}) BETWEEN ?3 AND ?4
+            ORDER BY {0} ASC, Item.id ASC;",
+            date::iso_date_sql("Receipt.date")
+        ))?
+        .query_map(params![name, price.unit.as_str(), from, to], |row| {
+            Ok(AffectedItem {
+                receipt: row.get(0)?,
+                date: row.get(1)?,
+                store: row.get(2)?,
+                quantity: row.get(3)?,
+                price: Money::new(row.get(4)?, price.unit),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(PriceUpdate {
+        name: name.to_string(),
+        price,
+        items,
+    })
+}
+
+/// Sets the new price of the planned items in one transaction, returns the number of changed
+/// items. Items added to the receipts since the plan was made get the price as well.
+pub(crate) fn apply_price_update(
+    conn: &Connection,
+    update: &PriceUpdate,
+) -> rusqlite::Result<usize> {
+    let mut receipts: Vec<_> = update.items.iter().map(|item| item.receipt).collect();
+    receipts.sort_unstable();
+    receipts.dedup();
+    let placeholders = (0..receipts.len())
+        .map(|idx| format!("?{}", idx + 4))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "UPDATE Item SET price = ?1, updated_at = CURRENT_TIMESTAMP
+        WHERE name == ?2 AND unit == ?3 AND receipt IN ({placeholders});"
+    );
+    let unit = update.price.unit.as_str().to_string();
+    let mut values: Vec<&dyn rusqlite::ToSql> = vec![&update.price.minor, &update.name, &unit];
+    values.extend(receipts.iter().map(|id| id as &dyn rusqlite::ToSql));
+    with_transaction(conn, |tx| tx.execute(&sql, values.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+    use crate::unit::Unit;
+
+    fn milk_prices(conn: &Connection) -> Vec<(i64, i64, String)> {
+        conn.prepare("SELECT receipt, price, unit FROM Item WHERE name == 'Milk' ORDER BY id;")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn updates_the_named_items_within_the_dates() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "INSERT INTO Receipt (id, store, date) VALUES (4, 1, '10.07.2022');
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES
+                ('Milk', 1, 2000, 'NOK', 4),
+                ('Milk', 1, 250, 'EUR', 2);",
+        )
+        .unwrap();
+
+        let price = Money::new(2490, Unit::NOK);
+        let update = plan_price_update(&conn, "Milk", price, "2022-07-01", "2022-07-31").unwrap();
+        let items: Vec<_> = update.items.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            items,
+            [
+                "10.07.2022: 1 × 20.00 NOK at 'Shop', receipt #4",
+                "2022-07-20: 1 × 21.90 NOK at 'Kiosk', receipt #3",
+            ]
+        );
+
+        assert_eq!(apply_price_update(&conn, &update).unwrap(), 2);
+        assert_eq!(
+            milk_prices(&conn),
+            [
+                (1, 1990, "NOK".to_string()),
+                (3, 2490, "NOK".to_string()),
+                (4, 2490, "NOK".to_string()),
+                (2, 250, "EUR".to_string()),
+            ]
+        );
+
+        let none = plan_price_update(&conn, "Milk", price, "2023-01-01", "2023-12-31").unwrap();
+        assert!(none.items.is_empty());
+        assert_eq!(apply_price_update(&conn, &none).unwrap(), 0);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::unit::{format_amount, Rounding, Unit};
+use crate::{Total, TotalRow};
+use rusqlite::{params, Connection};
+use std::fmt;
+
+/// One major unit of `unit` is worth `rate` major units of `base`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Rate {
+    unit: String,
+    base: String,
+    rate: f64,
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "1 {} = {} {}", self.unit, self.rate, self.base)
+    }
+}
+
+pub(crate) fn load_rates(conn: &Connection) -> rusqlite::Result<Vec<Rate>> {
+    conn.prepare("SELECT unit, base, rate FROM Rate ORDER BY unit, base;")?
+        .query_map([], |row| {
+            Ok(Rate {
+                unit: row.get(0)?,
+                base: row.get(1)?,
+                rate: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+/// A rate of 0 or less removes the rate.
+pub(crate) fn set_rate(
+    conn: &Connection,
+    unit: Unit,
+    base: Unit,
+    rate: f64,
+) -> rusqlite::Result<()> {
+    if rate > 0.0 {
+        conn.execute(
+            "INSERT OR REPLACE INTO Rate (unit, base, rate) VALUES (?1, ?2, ?3);",
+            params![unit.as_str(), base.as_str(), rate],
+        )?;
+    } else {
+        conn.execute(
+            "DELETE FROM Rate WHERE unit == ?1 AND base == ?2;",
+            params![unit.as_str(), base.as_str()],
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn rates_text(rates: &[Rate]) -> String {
+    if rates.is_empty() {
+        "No rates are saved.".to_string()
+    } else {
+        rates
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The rate from `unit` to `base`, the inverse of a saved rate from `base` to `unit` is used too.
+fn find_rate(rates: &[Rate], unit: &str, base: &str) -> Option<f64> {
+    rates
+        .iter()
+        .find(|rate| rate.unit == unit && rate.base == base)
+        .map(|rate| rate.rate)
+        .or_else(|| {
+            rates
+                .iter()
+                .find(|rate| rate.unit == base && rate.base == unit)
+                .map(|rate| 1.0 / rate.rate)
+        })
+}
+
+/// The units of a [`Total`] converted to a base unit and summed.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CombinedTotal {
+    base: Unit,
+    /// In minor units of the base
+    amount: i64,
+    /// Units without a rate to the base, they are not part of the amount
+    missing: Vec<String>,
+}
+
+impl CombinedTotal {
+    /// `None` if every unit of the total already is the base. Converted amounts are rounded to
+    /// a minor unit of the base each.
+    pub(crate) fn new(
+        total: &Total,
+        base: Unit,
+        rates: &[Rate],
+        rounding: Rounding,
+    ) -> Option<Self> {
+        if total.0.iter().all(|row| row.unit() == base.as_str()) {
+            return None;
+        }
+        let mut combined = CombinedTotal {
+            base,
+            amount: 0,
+            missing: Vec::new(),
+        };
+        for row in &total.0 {
+            if row.unit() == base.as_str() {
+                combined.amount += row.amount();
+                continue;
+            }
+            let converted = match row {
+                TotalRow::Money(money) => find_rate(rates, money.unit.as_str(), base.as_str())
+                    .map(|rate| base.to_minor(money.to_major() * rate, rounding)),
+                TotalRow::Unknown { .. } => None,
+            };
+            match converted {
+                Some(amount) => combined.amount += amount,
+                None => combined.missing.push(row.unit().to_string()),
+            }
+        }
+        Some(combined)
+    }
+}
+
+/// The alternate flag, `{:#}`, shows the amount in major units.
+impl fmt::Display for CombinedTotal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = self.base.as_str();
+        write!(
+            f,
+            "≈ {} {base} at the saved rates",
+            format_amount(self.amount, base, f.alternate())
+        )?;
+        if !self.missing.is_empty() {
+            write!(
+                f,
+                ", without {} (no rate to {base})",
+                self.missing.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::empty_db;
+
+    fn total(rows: &[(&str, i64)]) -> Total {
+        Total(
+            rows.iter()
+                .map(|(unit, price)| TotalRow::new(unit.to_string(), *price))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn totals_are_combined_with_the_saved_rates() {
+        let conn = empty_db();
+        set_rate(&conn, Unit::EUR, Unit::NOK, 11.5).unwrap();
+        let rates = load_rates(&conn).unwrap();
+        assert_eq!(rates_text(&rates), "1 EUR = 11.5 NOK");
+
+        let mixed = total(&[("NOK", 10000), ("EUR", 1000)]);
+        let combine =
+            |total: &Total, base| CombinedTotal::new(total, base, &rates, Rounding::HalfUp);
+        let combined = combine(&mixed, Unit::NOK).unwrap();
+        assert_eq!(format!("{combined:#}"), "≈ 215.00 NOK at the saved rates");
+        // the inverse rate is used to convert to EUR
+        let combined = combine(&mixed, Unit::EUR).unwrap();
+        assert_eq!(combined.amount, 1870);
+
+        assert_eq!(combine(&total(&[("NOK", 100)]), Unit::NOK), None);
+        let unknown = total(&[("NOK", 100), ("SEK", 500)]);
+        assert_eq!(
+            combine(&unknown, Unit::NOK).unwrap().to_string(),
+            "≈ 100 NOK at the saved rates, without SEK (no rate to NOK)"
+        );
+        // 0.03 EUR are 0.345 NOK, exactly between two minor units
+        let half = total(&[("NOK", 100), ("EUR", 3)]);
+        assert_eq!(combine(&half, Unit::NOK).unwrap().amount, 135);
+        let half_even = CombinedTotal::new(&half, Unit::NOK, &rates, Rounding::HalfEven);
+        assert_eq!(half_even.unwrap().amount, 134);
+
+        set_rate(&conn, Unit::EUR, Unit::NOK, 0.0).unwrap();
+        assert_eq!(
+            rates_text(&load_rates(&conn).unwrap()),
+            "No rates are saved."
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which receipt the item tab selects when a database is connected.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ReceiptSelection {
+    /// The receipt added last
+    #[default]
+    Newest,
+    /// The receipt that was selected when the database was used the last time
+    LastUsed,
+    Nothing,
+}
+
+impl ReceiptSelection {
+    pub(crate) const ALL: [ReceiptSelection; 3] = [
+        ReceiptSelection::Newest,
+        ReceiptSelection::LastUsed,
+        ReceiptSelection::Nothing,
+    ];
+
+    /// Position of the receipt to select, `ids` are the receipts in the order of the list.
+    /// Without the last used receipt the newest one is selected.
+    pub(crate) fn select(self, ids: &[i64], last_used: Option<i64>) -> Option<usize> {
+        let newest = || ids.len().checked_sub(1);
+        match self {
+            ReceiptSelection::Newest => newest(),
+            ReceiptSelection::LastUsed => last_used
+                .and_then(|last_used| ids.iter().position(|id| *id == last_used))
+                .or_else(newest),
+            ReceiptSelection::Nothing => None,
+        }
+    }
+}
+
+impl TryFrom<u32> for ReceiptSelection {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        ReceiptSelection::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<ReceiptSelection> for u32 {
+    fn from(selection: ReceiptSelection) -> Self {
+        match selection {
+            ReceiptSelection::Newest => 0,
+            ReceiptSelection::LastUsed => 1,
+            ReceiptSelection::Nothing => 2,
+        }
+    }
+}
+
+impl fmt::Display for ReceiptSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiptSelection::Newest => f.write_str("newest"),
+            ReceiptSelection::LastUsed => f.write_str("last used"),
+            ReceiptSelection::Nothing => f.write_str("none"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_used_receipt_falls_back_to_the_newest() {
+        let ids = [3, 5, 8];
+        assert_eq!(ReceiptSelection::Newest.select(&ids, Some(5)), Some(2));
+        assert_eq!(ReceiptSelection::LastUsed.select(&ids, Some(5)), Some(1));
+        // the last used receipt was deleted in the meantime
+        assert_eq!(ReceiptSelection::LastUsed.select(&ids, Some(4)), Some(2));
+        assert_eq!(ReceiptSelection::LastUsed.select(&ids, None), Some(2));
+        assert_eq!(ReceiptSelection::Nothing.select(&ids, Some(5)), None);
+        assert_eq!(ReceiptSelection::Newest.select(&[], None), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::date;
+use crate::unit::{format_amount, Money, Unit};
+use crate::{amount_text, Msg};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use relm4::ComponentSender;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// The last price an item was added with
+#[derive(Clone, Debug)]
+pub(crate) struct RecentItem {
+    pub(crate) name: String,
+    pub(crate) price: Money,
+}
+
+impl RecentItem {
+    /// The most recently added distinct items, newest first. Items with unknown units are skipped.
+    pub(crate) fn load(conn: &Connection, limit: usize) -> rusqlite::Result<Vec<Self>> {
+        let mut query = conn.prepare(
+            "SELECT name, price, unit FROM Item AS i
+            WHERE id == (SELECT id FROM Item WHERE name == i.name ORDER BY created_at DESC, id DESC LIMIT 1)
+            ORDER BY created_at DESC, id DESC LIMIT ?1;",
+        )?;
+        let rows = query.query_map(params![limit as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut items = Vec::new();
+        for row in rows {
+            let (name, price, unit) = row?;
+            if let Ok(unit) = Unit::from_str(&unit) {
+                items.push(RecentItem {
+                    name,
+                    price: Money::new(price, unit),
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    pub(crate) fn label(&self, major_units: bool) -> String {
+        format!("{} {}", self.name, amount_text(&self.price, major_units))
+    }
+}
+
+/// The most recent purchase of an item, shown while its name is entered.
+#[derive(Clone, Debug)]
+pub(crate) struct LastPrice {
+    /// In minor units of `unit`, which may be unknown to the app
+    price: i64,
+    unit: String,
+    /// `YYYY-MM-DD`
+    date: String,
+    store: String,
+}
+
+impl LastPrice {
+    /// Names are compared ignoring ASCII case, as they may have been entered with another casing.
+    pub(crate) fn load(conn: &Connection, name: &str) -> rusqlite::Result<Option<Self>> {
+        conn.query_row(
+            &format!(
+                "SELECT Item.price, Item.unit, Receipt.date, Store.name FROM Item
+                INNER JOIN Receipt ON Item.receipt = Receipt.id
+                INNER JOIN Store ON Receipt.store = Store.id
+                WHERE Item.name == ?1 COLLATE NOCASE
+                ORDER BY {} DESC, Item.id DESC LIMIT 1;",
+                date::iso_date_sql("Receipt.date")
+            ),
+            params![name.trim()],
+            |row| {
+                Ok(LastPrice {
+                    price: row.get(0)?,
+                    unit: row.get(1)?,
+                    date: row.get(2)?,
+                    store: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+    }
+
+    pub(crate) fn label(&self, date_format: &str, major_units: bool) -> String {
+        format!(
+            "last: {} {} at {} on {}",
+            format_amount(self.price, &self.unit, major_units),
+            self.unit,
+            self.store,
+            date::display_date(&self.date, date_format)
+        )
+    }
+}
+
+pub(crate) trait ShowRecentItems {
+    /// Replaces the children with a button per item that prefills the item fields.
+    fn show_recent_items(
+        &self,
+        items: &[RecentItem],
+        major_units: bool,
+        sender: &ComponentSender<crate::App>,
+    );
+}
+
+impl ShowRecentItems for gtk::FlowBox {
+    fn show_recent_items(
+        &self,
+        items: &[RecentItem],
+        major_units: bool,
+        sender: &ComponentSender<crate::App>,
+    ) {
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+        for (idx, item) in items.iter().enumerate() {
+            let button = gtk::Button::with_label(&item.label(major_units));
+            let sender = sender.clone();
+            button.connect_clicked(move |_| sender.input(Msg::PrefillItem(idx)));
+            self.insert(&button, -1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    #[test]
+    fn newest_distinct_items_first() {
+        let conn = sample_db();
+        conn.execute_batch(
+            "UPDATE Item SET created_at = '2022-07-20 10:00:00' WHERE receipt == 3;
+            UPDATE Item SET created_at = '2022-06-01 10:00:00' WHERE receipt == 1;",
+        )
+        .unwrap();
+        let items = RecentItem::load(&conn, 3).unwrap();
+        let labels: Vec<_> = items.iter().map(|item| item.label(true)).collect();
+        // items without a timestamp of the first migration are the oldest
+        assert_eq!(
+            labels,
+            ["Deposit -2.00 NOK", "Milk 21.90 NOK", "Bread 30.00 NOK"]
+        );
+        assert_eq!(items[1].label(false), "Milk 2190 NOK");
+    }
+
+    #[test]
+    fn last_price_is_of_the_latest_receipt() {
+        let conn = sample_db();
+        let last = LastPrice::load(&conn, "milk ").unwrap().unwrap();
+        assert_eq!(
+            last.label("", true),
+            "last: 21.90 NOK at Kiosk on 2022-07-20"
+        );
+        assert_eq!(
+            last.label("", false),
+            "last: 2190 NOK at Kiosk on 2022-07-20"
+        );
+        assert!(LastPrice::load(&conn, "Tea").unwrap().is_none());
+    }
+}
@@ -1,3 +1,212 @@
+use rusqlite::Connection;
+use std::fs::File;
+use std::path::Path;
+
 pub static SCHEMA_STORE: &str = include_str!("sqlbon-schema-store.sql");
 pub static SCHEMA_RECEIPT: &str = include_str!("sqlbon-schema-receipt.sql");
 pub static SCHEMA_ITEM: &str = include_str!("sqlbon-schema-item.sql");
+
+/// Applied in order on top of the initial schema. `PRAGMA user_version` stores how many have been applied.
+pub static MIGRATIONS: &[&str] = &[
+    include_str!("sqlbon-migration-1-timestamps.sql"),
+    include_str!("sqlbon-migration-2-printed-total.sql"),
+    include_str!("sqlbon-migration-3-budget.sql"),
+    include_str!("sqlbon-migration-4-attachment.sql"),
+    include_str!("sqlbon-migration-5-rate.sql"),
+    include_str!("sqlbon-migration-6-store-place.sql"),
+    include_str!("sqlbon-migration-7-receipt-currency.sql"),
+    include_str!("sqlbon-migration-8-item-category.sql"),
+];
+
+pub fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Creates the tables of a new database and applies all migrations. Either all of them are
+/// created or, on an error, none.
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    init_with(conn, MIGRATIONS)
+}
+
+fn init_with(conn: &Connection, migrations: &[&str]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(SCHEMA_STORE, [])?;
+    tx.execute(SCHEMA_RECEIPT, [])?;
+    tx.execute(SCHEMA_ITEM, [])?;
+    for migration in migrations {
+        tx.execute_batch(migration)?;
+    }
+    tx.pragma_update(None, "user_version", migrations.len() as i64)?;
+    tx.commit()
+}
+
+/// Creates or truncates the file and initializes a database in it. If that fails, the file is
+/// removed again, so no half initialized database is left behind.
+pub fn create_file(path: &str) -> Result<Connection, String> {
+    create_file_with(path, MIGRATIONS)
+}
+
+/// The folder of a new database file has to exist and be writable. Whether a file can be created
+/// in it is tried out, the permissions of the folder do not tell, e.g. on Unix or for ACLs.
+fn check_folder(path: &str) -> Result<(), String> {
+    let folder = match Path::new(path).parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder,
+        _ => Path::new("."),
+    };
+    let metadata = std::fs::metadata(folder)
+        .map_err(|_| format!("The folder '{}' does not exist.", folder.display()))?;
+    if !metadata.is_dir() {
+        Err(format!("'{}' is not a folder.", folder.display()))
+    } else {
+        let probe = folder.join(format!(".sqlbon_write_check_{}", std::process::id()));
+        File::options()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+            .and_then(|_| std::fs::remove_file(&probe))
+            .map_err(|err| format!("The folder '{}' is not writable: {err}", folder.display()))
+    }
+}
+
+fn create_file_with(path: &str, migrations: &[&str]) -> Result<Connection, String> {
+    check_folder(path)?;
+    File::create(path).map_err(|err| format!("Could not create/truncate the file: {err}"))?;
+    let created = Connection::open(path)
+        .map_err(|err| format!("Could not open the database: {err}"))
+        .and_then(|conn| {
+            init_with(&conn, migrations)
+                .map_err(|err| format!("Could not initialize the database: {err}"))?;
+            Ok(conn)
+        });
+    if created.is_err() {
+        // the connection is closed at this point, so the file can be removed on every platform
+        let _ = std::fs::remove_file(path);
+    }
+    created
+}
+
+/// In-memory databases for tests.
+#[cfg(test)]
+pub mod testing {
+    use super::init;
+    use rusqlite::Connection;
+    use std::path::PathBuf;
+
+    /// A path in the temp folder for a file of a test, e.g. `export.csv`. The process id keeps
+    /// test runs at the same time apart, the name the tests of one run.
+    pub fn temp_path(file_name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sqlbon_{}_{file_name}", std::process::id()))
+    }
+
+    /// A database with the current schema and no rows.
+    pub fn empty_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init(&conn).unwrap();
+        conn
+    }
+
+    /// A database with two stores, three receipts and items in both units:
+    ///
+    /// | receipt | store        | date       | items                             |
+    /// |---------|--------------|------------|-----------------------------------|
+    /// | 1       | Shop (Town)  | 2022-06-01 | 2 × Milk 1990 NOK, Bread 3000 NOK |
+    /// | 2       | Shop (Town)  | 2022-07-15 | Coffee 450 EUR, 3 × Apple 500 EUR |
+    /// | 3       | Kiosk (Port) | 2022-07-20 | Milk 2190 NOK, Deposit -200 NOK   |
+    pub fn sample_db() -> Connection {
+        let conn = empty_db();
+        conn.execute_batch(
+            "INSERT INTO Store (id, name, location) VALUES (1, 'Shop', 'Town'), (2, 'Kiosk', 'Port');
+            INSERT INTO Receipt (id, store, date) VALUES
+                (1, 1, '2022-06-01'),
+                (2, 1, '2022-07-15'),
+                (3, 2, '2022-07-20');
+            INSERT INTO Item (name, quantity, price, unit, receipt) VALUES
+                ('Milk', 2, 1990, 'NOK', 1),
+                ('Bread', 1, 3000, 'NOK', 1),
+                ('Coffee', 1, 450, 'EUR', 2),
+                ('Apple', 3, 500, 'EUR', 2),
+                ('Milk', 1, 2190, 'NOK', 3),
+                ('Deposit', 1, -200, 'NOK', 3);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn empty_db_is_migrated() {
+        let conn = empty_db();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, super::MIGRATIONS.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::temp_path;
+
+    #[test]
+    fn failed_creation_leaves_no_file() {
+        let path = temp_path("create_failed.db");
+        let path = path.to_str().unwrap();
+        let migrations = [MIGRATIONS[0], "CREATE TABLE Broken (;"];
+        assert!(create_file_with(path, &migrations)
+            .unwrap_err()
+            .starts_with("Could not initialize the database"));
+        assert!(!Path::new(path).exists());
+
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(init_with(&conn, &migrations).is_err());
+        // nothing of the failed creation was committed
+        let tables: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn missing_folder_is_reported_before_creating() {
+        let folder = temp_path("missing");
+        let path = folder.join("receipts.db").to_string_lossy().to_string();
+        assert_eq!(
+            create_file(&path).unwrap_err(),
+            format!("The folder '{}' does not exist.", folder.display())
+        );
+        let file = temp_path("not_a_folder.db");
+        File::create(&file).unwrap();
+        assert!(check_folder(&file.join("receipts.db").to_string_lossy())
+            .unwrap_err()
+            .ends_with("is not a folder."));
+        std::fs::remove_file(&file).unwrap();
+        // a file name without a folder is created in the working directory
+        assert_eq!(check_folder("receipts.db"), Ok(()));
+        // the file that tries out writing is removed again
+        assert_eq!(
+            check_folder(&temp_path("probe.db").to_string_lossy()),
+            Ok(())
+        );
+        let probe = format!(".sqlbon_write_check_{}", std::process::id());
+        assert!(!std::env::temp_dir().join(probe).exists());
+    }
+
+    #[test]
+    fn created_file_has_the_current_version() {
+        let path = temp_path("create.db");
+        let conn = create_file(path.to_str().unwrap()).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+-- rows created before this migration get the sentinel '1970-01-01 00:00:00', new rows set both columns on insert
+ALTER TABLE Receipt ADD COLUMN created_at DATETIME NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE Receipt ADD COLUMN updated_at DATETIME NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE Item ADD COLUMN created_at DATETIME NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE Item ADD COLUMN updated_at DATETIME NOT NULL DEFAULT '1970-01-01 00:00:00';
@@ -0,0 +1,3 @@
+-- the grand total printed on the receipt in minor units, NULL if it was not entered
+ALTER TABLE Receipt ADD COLUMN printed_total INT;
+ALTER TABLE Receipt ADD COLUMN printed_total_unit VARCHAR(3);
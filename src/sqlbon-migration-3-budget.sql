@@ -0,0 +1,8 @@
+-- monthly spending budget of a store in minor units of the unit
+CREATE TABLE Budget (
+    store INT NOT NULL,
+    unit VARCHAR(3) NOT NULL,
+    amount INT NOT NULL,
+    PRIMARY KEY(store, unit),
+    FOREIGN KEY(store) REFERENCES Store(id)
+);
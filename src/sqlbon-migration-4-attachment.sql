@@ -0,0 +1,5 @@
+-- other databases attached to the connection for analysis queries
+CREATE TABLE Attachment (
+    alias VARCHAR PRIMARY KEY,
+    path VARCHAR NOT NULL
+);
@@ -0,0 +1,7 @@
+-- exchange rate for the combined total, one major unit of unit is worth rate major units of base
+CREATE TABLE Rate (
+    unit VARCHAR(3) NOT NULL,
+    base VARCHAR(3) NOT NULL,
+    rate REAL NOT NULL,
+    PRIMARY KEY(unit, base)
+);
@@ -0,0 +1,3 @@
+-- structured place of a store, the free-text location is kept for display
+ALTER TABLE Store ADD COLUMN city VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE Store ADD COLUMN country VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+-- the unit all items of the receipt are expected to have, NULL if they may have any unit
+ALTER TABLE Receipt ADD COLUMN currency VARCHAR(3);
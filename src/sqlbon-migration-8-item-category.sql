@@ -0,0 +1,2 @@
+-- the category of an item, e.g. groceries, NULL if it has none
+ALTER TABLE Item ADD COLUMN category VARCHAR;
@@ -0,0 +1,290 @@
+use crate::csv;
+use crate::date::{self, StorageFormat};
+use crate::name_casing::collapse_whitespace;
+use crate::unit::{Money, Unit};
+use crate::with_transaction;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Location of stores created for unknown merchants, statements do not contain one.
+const IMPORTED_LOCATION: &str = "imported";
+/// Name of the single item added for each statement row.
+const ITEM_NAME: &str = "Statement";
+
+/// A card transaction of a bank statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StatementRow {
+    /// `YYYY-MM-DD` when parsed, in the storage format once planned
+    date: String,
+    merchant: String,
+    amount: Money,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum StoreAction {
+    Existing { id: i64, name: String },
+    Create,
+}
+
+/// What importing a statement row does. Created by [`plan_import`], applied by [`apply_import`].
+#[derive(Clone, Debug)]
+pub(crate) struct PlannedReceipt {
+    row: StatementRow,
+    store: StoreAction,
+    /// An existing receipt of the store at the date, the item is added to it instead of a new one
+    receipt: Option<i64>,
+}
+
+impl fmt::Display for PlannedReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let StatementRow {
+            date,
+            merchant,
+            amount,
+        } = &self.row;
+        write!(f, "{date}: {amount:#} at ")?;
+        match &self.store {
+            StoreAction::Existing { id, name } => write!(f, "'{name}' #{id}")?,
+            StoreAction::Create => write!(f, "'{merchant}' (new store)")?,
+        }
+        match self.receipt {
+            Some(id) => write!(f, ", added to receipt #{id}"),
+            None => write!(f, ", new receipt"),
+        }
+    }
+}
+
+/// Parses a statement with a `date`, `merchant` and `amount` column. Dates are `YYYY-MM-DD` or
+/// `DD.MM.YYYY` and have to exist. Amounts are in major units
+/// and may use a decimal comma. Card payments are often listed as negative amounts, so the
+/// absolute amount is imported.
+pub(crate) fn parse_statement(
+    text: &str,
+    delimiter: csv::Delimiter,
+    unit: Unit,
+) -> Result<Vec<StatementRow>, String> {
+    let header = text.lines().next().unwrap_or_default();
+    let mut records = csv::parse_records(text, delimiter.detect(header)).into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| "The file is empty.".to_string())?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("The header has no '{name}' column."))
+    };
+    let (date_col, merchant_col, amount_col) =
+        (column("date")?, column("merchant")?, column("amount")?);
+
+    let mut rows = Vec::new();
+    for (line, record) in records.enumerate() {
+        let field = |col: usize| {
+            record
+                .get(col)
+                .map(|field| field.trim())
+                .unwrap_or_default()
+        };
+        let invalid = |name: &str| format!("Record {} has an invalid {name}.", line + 1);
+        let date = date::convert_date(field(date_col), StorageFormat::Iso)
+            .ok_or_else(|| invalid("date"))?;
+        let merchant = field(merchant_col);
+        if merchant.is_empty() {
+            return Err(invalid("merchant"));
+        }
+        let amount = Money::parse(field(amount_col), unit).map_err(|_| invalid("amount"))?;
+        rows.push(StatementRow {
+            date,
+            merchant: merchant.to_string(),
+            amount: Money::new(amount.minor.abs(), unit),
+        });
+    }
+    Ok(rows)
+}
+
+/// Merchants are matched to stores ignoring case and repeated whitespace.
+fn store_key(name: &str) -> String {
+    collapse_whitespace(name).to_lowercase()
+}
+
+/// Matches the merchants to existing stores and the dates to existing receipts of those stores,
+/// in either storage format. The dates are converted to the format new receipt dates are stored
+/// in.
+pub(crate) fn plan_import(
+    conn: &Connection,
+    rows: Vec<StatementRow>,
+    date_format: StorageFormat,
+) -> rusqlite::Result<Vec<PlannedReceipt>> {
+    let mut stores = HashMap::new();
+    let mut query = conn.prepare("SELECT id, name FROM Store ORDER BY id ASC;")?;
+    for store in query.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (id, name) = store?;
+        // the oldest store wins if several have the same name
+        stores.entry(store_key(&name)).or_insert((id, name));
+    }
+
+    let receipt_sql = format!(
+        "SELECT id FROM Receipt WHERE store == ?1 AND date({}) == ?2 ORDER BY id ASC;",
+        date::iso_date_sql("date")
+    );
+    let mut planned = Vec::new();
+    for mut row in rows {
+        let (store, receipt) = match stores.get(&store_key(&row.merchant)) {
+            Some((id, name)) => {
+                let receipt = conn
+                    .query_row(&receipt_sql, params![id, row.date], |row| row.get(0))
+                    .optional()?;
+                let store = StoreAction::Existing {
+                    id: *id,
+                    name: name.clone(),
+                };
+                (store, receipt)
+            }
+            None => (StoreAction::Create, None),
+        };
+        if let Some(date) = date::convert_date(&row.date, date_format) {
+            row.date = date;
+        }
+        planned.push(PlannedReceipt {
+            row,
+            store,
+            receipt,
+        });
+    }
+    Ok(planned)
+}
+
+/// Creates the planned stores, receipts and items. Either everything is imported or nothing.
+/// Returns the number of imported rows.
+pub(crate) fn apply_import(
+    conn: &Connection,
+    planned: &[PlannedReceipt],
+) -> rusqlite::Result<usize> {
+    with_transaction(conn, |tx| {
+        // several rows of the same new merchant share one store, and one receipt per date
+        let mut created_stores = HashMap::new();
+        let mut created_receipts = HashMap::new();
+        for PlannedReceipt {
+            row,
+            store,
+            receipt,
+        } in planned
+        {
+            let store_id = match store {
+                StoreAction::Existing { id, .. } => *id,
+                StoreAction::Create => match created_stores.get(&store_key(&row.merchant)) {
+                    Some(id) => *id,
+                    None => {
+                        tx.execute(
+                            "INSERT INTO Store (name, location) VALUES (?1, ?2);",
+                            params![row.merchant, IMPORTED_LOCATION],
+                        )?;
+                        let id = tx.last_insert_rowid();
+                        created_stores.insert(store_key(&row.merchant), id);
+                        id
+                    }
+                },
+            };
+            let receipt_id = match (receipt, created_receipts.get(&(store_id, &row.date))) {
+                (Some(id), _) | (None, Some(id)) => *id,
+                (None, None) => {
+                    tx.execute(
+                        "INSERT INTO Receipt (store, date, created_at, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP);",
+                        params![store_id, row.date],
+                    )?;
+                    let id = tx.last_insert_rowid();
+                    created_receipts.insert((store_id, &row.date), id);
+                    id
+                }
+            };
+            tx.execute(
+                "INSERT INTO Item (name, quantity, price, unit, receipt, created_at, updated_at) VALUES (?1, 1, ?2, ?3, ?4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+                params![ITEM_NAME, row.amount.minor, row.amount.unit.as_str(), receipt_id],
+            )?;
+        }
+        Ok(planned.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::sample_db;
+
+    const STATEMENT: &str = "Date;Merchant;Amount
+01.06.2022;SHOP;-12,50
+2022-06-03;Bakery  North;-4.20
+2022-06-04;bakery north;-3.00
+";
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table};"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_dates_and_decimal_commas() {
+        let rows = parse_statement(STATEMENT, csv::Delimiter::Comma, Unit::NOK).unwrap();
+        assert_eq!(
+            rows[0],
+            StatementRow {
+                date: "2022-06-01".to_string(),
+                merchant: "SHOP".to_string(),
+                amount: Money::new(1250, Unit::NOK),
+            }
+        );
+        assert_eq!(rows[1].date, "2022-06-03");
+        assert_eq!(rows[1].amount.minor, 420);
+
+        for date in ["2022-13-01", "31.02.2022", "2022-06"] {
+            let invalid = format!("date,merchant,amount\n{date},Shop,1\n");
+            assert!(
+                parse_statement(&invalid, csv::Delimiter::Comma, Unit::NOK).is_err(),
+                "{date}"
+            );
+        }
+    }
+
+    #[test]
+    fn reuses_matching_stores_and_receipts() {
+        let conn = sample_db();
+        let rows = parse_statement(STATEMENT, csv::Delimiter::Comma, Unit::NOK).unwrap();
+        let planned = plan_import(&conn, rows, StorageFormat::Iso).unwrap();
+        let actions: Vec<_> = planned.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            actions,
+            [
+                "2022-06-01: 12.50 NOK at 'Shop' #1, added to receipt #1",
+                "2022-06-03: 4.20 NOK at 'Bakery  North' (new store), new receipt",
+                "2022-06-04: 3.00 NOK at 'bakery north' (new store), new receipt",
+            ]
+        );
+
+        assert_eq!(apply_import(&conn, &planned).unwrap(), 3);
+        assert_eq!(count(&conn, "Store"), 3);
+        assert_eq!(count(&conn, "Receipt"), 5);
+        assert_eq!(count(&conn, "Item"), 9);
+    }
+
+    #[test]
+    fn matches_receipts_stored_in_either_format() {
+        let conn = sample_db();
+        conn.execute(
+            "INSERT INTO Receipt (id, store, date) VALUES (4, 2, '03.06.2022');",
+            [],
+        )
+        .unwrap();
+        let statement = "date,merchant,amount\n2022-06-03,Kiosk,-1.00\n";
+        let rows = parse_statement(statement, csv::Delimiter::Comma, Unit::NOK).unwrap();
+        let planned = plan_import(&conn, rows, StorageFormat::DayMonthYear).unwrap();
+        assert_eq!(
+            planned[0].to_string(),
+            "03.06.2022: 1.00 NOK at 'Kiosk' #2, added to receipt #4"
+        );
+    }
+}
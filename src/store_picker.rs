@@ -0,0 +1,87 @@
+use crate::combobox::AppendAll;
+use crate::StoreRow;
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+
+/// A combobox of all stores. Every tab that needs a store uses one, so they are shown alike.
+#[tracker::track]
+pub(crate) struct StorePicker {
+    #[tracker::no_eq]
+    stores: Vec<StoreRow>,
+    /// Id of the chosen store. Only read when the stores are replaced, the combobox already
+    /// shows the choice
+    #[tracker::do_not_track]
+    chosen: Option<i64>,
+}
+
+#[derive(Debug)]
+pub(crate) enum StorePickerMsg {
+    /// Replaces the stores. Without the id of a store to choose, the chosen store stays chosen
+    /// if it is still one of them.
+    Stores(Vec<StoreRow>, Option<i64>),
+    Choose(Option<u32>),
+}
+
+impl StorePicker {
+    pub(crate) fn chosen(&self) -> Option<StoreRow> {
+        self.chosen
+            .and_then(|id| self.stores.iter().find(|store| store.id == id))
+            .cloned()
+    }
+}
+
+#[relm4::component(pub(crate))]
+impl SimpleComponent for StorePicker {
+    type Input = StorePickerMsg;
+    type Output = ();
+    type Init = ();
+    type Widgets = StorePickerWidgets;
+
+    view! {
+        #[root]
+        gtk::ComboBoxText {
+            set_hexpand: true,
+            set_vexpand: false,
+            set_halign: gtk::Align::Fill,
+            set_valign: gtk::Align::Center,
+            #[track(model.changed(StorePicker::stores()))]
+            append_all_and_select_by: (&model.stores, ToString::to_string, |store| store.id, model.chosen),
+            connect_changed[sender] => move |cb| {
+                sender.input(StorePickerMsg::Choose(cb.active()));
+            },
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        self.reset();
+        match message {
+            StorePickerMsg::Stores(stores, to_select) => {
+                let previous = self.chosen;
+                self.chosen = to_select
+                    .or_else(|| previous.filter(|id| stores.iter().any(|store| store.id == *id)));
+                self.set_stores(stores);
+            }
+            StorePickerMsg::Choose(chosen) => {
+                self.chosen = chosen
+                    .and_then(|idx| self.stores.get(idx as usize))
+                    .map(|store| store.id);
+            }
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = StorePicker {
+            stores: Vec::new(),
+            chosen: None,
+            tracker: 0,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+}
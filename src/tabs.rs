@@ -0,0 +1,217 @@
+use crate::Msg;
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use relm4::ComponentSender;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A page of the notebook
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Tab {
+    Dashboard,
+    Store,
+    Receipt,
+    Item,
+    Analysis,
+    /// Always visible, otherwise hidden tabs could not be shown again
+    Settings,
+}
+
+impl Tab {
+    /// In the order the pages are appended to the notebook
+    pub(crate) const ALL: [Tab; 6] = [
+        Tab::Dashboard,
+        Tab::Store,
+        Tab::Receipt,
+        Tab::Item,
+        Tab::Analysis,
+        Tab::Settings,
+    ];
+
+    /// The tab whose label is the name, ignoring ASCII case.
+    pub(crate) fn from_name(name: &str) -> Option<Tab> {
+        Tab::ALL
+            .into_iter()
+            .find(|tab| tab.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tab::Dashboard => f.write_str("Dashboard"),
+            Tab::Store => f.write_str("Store"),
+            Tab::Receipt => f.write_str("Receipt"),
+            Tab::Item => f.write_str("Item"),
+            Tab::Analysis => f.write_str("Analysis"),
+            Tab::Settings => f.write_str("Settings"),
+        }
+    }
+}
+
+/// Order and visibility of the tabs. Hidden tabs keep their position, so the position of a tab
+/// is also the number of its page in the notebook.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TabLayout(Vec<(Tab, bool)>);
+
+impl Default for TabLayout {
+    fn default() -> Self {
+        TabLayout(Tab::ALL.iter().map(|tab| (*tab, true)).collect())
+    }
+}
+
+impl TabLayout {
+    /// Drops repeated tabs and adds missing ones at the end, e.g. if the settings were edited
+    /// by hand. The settings tab is always visible.
+    pub(crate) fn normalized(self) -> Self {
+        let mut tabs: Vec<(Tab, bool)> = Vec::new();
+        for (tab, visible) in self.0.into_iter().chain(TabLayout::default().0) {
+            if tabs.iter().all(|(other, _)| *other != tab) {
+                tabs.push((tab, visible || tab == Tab::Settings));
+            }
+        }
+        TabLayout(tabs)
+    }
+
+    pub(crate) fn position(&self, tab: Tab) -> u32 {
+        self.0
+            .iter()
+            .position(|(other, _)| *other == tab)
+            .unwrap_or_default() as u32
+    }
+
+    pub(crate) fn is_visible(&self, tab: Tab) -> bool {
+        self.0
+            .iter()
+            .any(|(other, visible)| *other == tab && *visible)
+    }
+
+    /// Positions of the visible tabs
+    pub(crate) fn visible_positions(&self) -> Vec<u32> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, visible))| *visible)
+            .map(|(position, _)| position as u32)
+            .collect()
+    }
+
+    /// The position of the visible tab `delta` visible tabs after the one at `position`,
+    /// wrapping around at the ends.
+    pub(crate) fn cycle(&self, position: u32, delta: i32) -> u32 {
+        let visible = self.visible_positions();
+        let current = visible
+            .iter()
+            .position(|visible| *visible == position)
+            .unwrap_or_default() as i32;
+        visible[(current + delta).rem_euclid(visible.len() as i32) as usize]
+    }
+
+    pub(crate) fn set_visible(&mut self, tab: Tab, visible: bool) {
+        for (other, other_visible) in &mut self.0 {
+            if *other == tab {
+                *other_visible = visible || tab == Tab::Settings;
+            }
+        }
+    }
+
+    /// Swaps the tab with its neighbour before (`-1`) or after (`1`) it.
+    pub(crate) fn move_tab(&mut self, tab: Tab, delta: i32) {
+        let position = self.position(tab) as i32;
+        let target = position + delta;
+        if (0..self.0.len() as i32).contains(&target) {
+            self.0.swap(position as usize, target as usize);
+        }
+    }
+
+    /// Moves the pages of the notebook, appended in the order of [`Tab::ALL`], to their
+    /// positions and hides the pages of hidden tabs.
+    pub(crate) fn arrange(&self, notebook: &gtk::Notebook) {
+        let pages: Vec<_> = (0..Tab::ALL.len() as u32)
+            .filter_map(|page| notebook.nth_page(Some(page)))
+            .collect();
+        for (position, (tab, visible)) in self.0.iter().enumerate() {
+            let index = Tab::ALL.iter().position(|other| other == tab);
+            if let Some(page) = index.and_then(|index| pages.get(index)) {
+                notebook.reorder_child(page, Some(position as u32));
+                page.set_visible(*visible);
+            }
+        }
+    }
+}
+
+pub(crate) trait ShowTabLayout {
+    /// Replaces the children with a row per tab to show or hide it and to move it.
+    fn show_tab_layout(&self, layout: &TabLayout, sender: &ComponentSender<crate::App>);
+}
+
+impl ShowTabLayout for gtk::Box {
+    fn show_tab_layout(&self, layout: &TabLayout, sender: &ComponentSender<crate::App>) {
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+        for (position, (tab, visible)) in layout.0.iter().copied().enumerate() {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+            let check = gtk::CheckButton::with_label(&tab.to_string());
+            check.set_hexpand(true);
+            check.set_active(visible);
+            check.set_sensitive(tab != Tab::Settings);
+            let check_sender = sender.clone();
+            check.connect_toggled(move |check| {
+                check_sender.input(Msg::TabVisible(tab, check.is_active()));
+            });
+            row.append(&check);
+            for (label, delta, sensitive) in [
+                ("up", -1, position > 0),
+                ("down", 1, position + 1 < layout.0.len()),
+            ] {
+                let button = gtk::Button::with_label(label);
+                button.set_sensitive(sensitive);
+                let sender = sender.clone();
+                button.connect_clicked(move |_| sender.input(Msg::MoveTab(tab, delta)));
+                row.append(&button);
+            }
+            self.append(&row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_keep_every_tab_once() {
+        let layout = TabLayout(vec![
+            (Tab::Settings, false),
+            (Tab::Item, true),
+            (Tab::Analysis, false),
+            (Tab::Item, false),
+        ])
+        .normalized();
+        assert_eq!(
+            layout,
+            TabLayout(vec![
+                (Tab::Settings, true),
+                (Tab::Item, true),
+                (Tab::Analysis, false),
+                (Tab::Dashboard, true),
+                (Tab::Store, true),
+                (Tab::Receipt, true),
+            ])
+        );
+        assert_eq!(layout.visible_positions(), [0, 1, 3, 4, 5]);
+        // the hidden analysis tab is skipped
+        assert_eq!(layout.cycle(1, 1), 3);
+        assert_eq!(layout.cycle(0, -1), 5);
+
+        let mut layout = layout;
+        layout.move_tab(Tab::Settings, -1);
+        layout.move_tab(Tab::Receipt, 1);
+        layout.move_tab(Tab::Settings, 1);
+        layout.set_visible(Tab::Settings, false);
+        assert_eq!(layout.position(Tab::Settings), 1);
+        assert_eq!(layout.position(Tab::Item), 0);
+        assert!(layout.is_visible(Tab::Settings));
+    }
+}
@@ -1,8 +1,10 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Formatter;
 use std::str::FromStr;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum Unit {
     NOK,
@@ -33,10 +35,35 @@ impl Unit {
         }
     }
 
+    /// Decimal places of an amount in major units, e.g. 2 for a scale of 100.
+    pub fn digits(&self) -> u32 {
+        match self {
+            Unit::NOK => 2,
+            Unit::EUR => 2,
+        }
+    }
+
+    /// Converts an amount in major units, e.g. 12.99, to the stored minor units, e.g. 1299.
+    pub fn to_minor(&self, major: f64, rounding: Rounding) -> i64 {
+        rounding.round(major * self.scale() as f64) as i64
+    }
+
+    pub fn to_major(&self, minor: i64) -> f64 {
+        minor as f64 / self.scale() as f64
+    }
+
     pub fn as_str(&self) -> &str {
         self.into()
     }
 
+    /// Shown next to entered amounts, e.g. `kr` for NOK.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::NOK => "kr",
+            Unit::EUR => "€",
+        }
+    }
+
     pub const ALL: [Unit; 2] = [Unit::NOK, Unit::EUR];
 }
 
@@ -79,3 +106,383 @@ impl fmt::Display for Unit {
         f.write_str(self.into())
     }
 }
+
+/// How an amount exactly halfway between two minor units is rounded, when an amount in major
+/// units is converted or a line total is divided.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Rounding {
+    /// Away from zero, 0.125 becomes 0.13 and -0.125 becomes -0.13
+    #[default]
+    HalfUp,
+    /// To the even minor unit, 0.125 becomes 0.12 and 0.135 becomes 0.14
+    HalfEven,
+}
+
+impl Rounding {
+    pub const ALL: [Rounding; 2] = [Rounding::HalfUp, Rounding::HalfEven];
+
+    /// Rounds to a whole number.
+    pub fn round(self, value: f64) -> f64 {
+        match self {
+            Rounding::HalfEven if (value - value.trunc()).abs() == 0.5 => {
+                2.0 * (value / 2.0).round()
+            }
+            _ => value.round(),
+        }
+    }
+
+    /// Divides by a positive `divisor` and rounds the quotient to a whole number, without going
+    /// through floating point.
+    pub fn div(self, dividend: i64, divisor: i64) -> i64 {
+        let quotient = dividend / divisor;
+        let away = quotient + dividend.signum();
+        match ((dividend % divisor).abs() * 2).cmp(&divisor) {
+            Ordering::Less => quotient,
+            Ordering::Greater => away,
+            Ordering::Equal => match self {
+                Rounding::HalfEven if quotient % 2 == 0 => quotient,
+                _ => away,
+            },
+        }
+    }
+}
+
+impl TryFrom<u32> for Rounding {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Rounding::ALL.get(value as usize).copied().ok_or(())
+    }
+}
+
+impl From<Rounding> for u32 {
+    fn from(rounding: Rounding) -> Self {
+        match rounding {
+            Rounding::HalfUp => 0,
+            Rounding::HalfEven => 1,
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::HalfUp => f.write_str("half up"),
+            Rounding::HalfEven => f.write_str("half to even (banker's)"),
+        }
+    }
+}
+
+/// Decimals of a quantity when decimal quantities are entered, e.g. grams of a weight in
+/// kilograms.
+pub const QUANTITY_DIGITS: u32 = 3;
+
+/// Rounds an entered quantity to [`QUANTITY_DIGITS`] decimals. Quantities are stored as
+/// floats in the integer column of the item, so whole quantities stay integers and fractional
+/// ones become `REAL` values.
+pub fn round_quantity(quantity: f64) -> f64 {
+    let scale = 10_f64.powi(QUANTITY_DIGITS as i32);
+    (quantity * scale).round() / scale
+}
+
+/// An amount of money as it is stored, in minor units of its unit, so sums stay exact. Major
+/// units, e.g. `12.99`, only appear when an amount is entered or shown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Money {
+    pub minor: i64,
+    pub unit: Unit,
+}
+
+impl Money {
+    pub fn new(minor: i64, unit: Unit) -> Self {
+        Money { minor, unit }
+    }
+
+    /// Rounds to the nearest minor unit, e.g. the value of a spin button.
+    pub fn from_major(major: f64, unit: Unit, rounding: Rounding) -> Self {
+        Money::new(unit.to_minor(major, rounding), unit)
+    }
+
+    /// Parses an amount in major units like `12.99`, `12,99`, `-3` or `.5` without going through
+    /// floating point. More decimals than the unit has are an error, not rounded away.
+    pub fn parse(text: &str, unit: Unit) -> Result<Self, String> {
+        let invalid = || format!("'{text}' is not an amount of {unit}.");
+        let trimmed = text.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (whole, fraction) = digits.split_once(['.', ',']).unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() && fraction.is_empty() || !all_digits(whole) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > unit.digits() as usize {
+            return Err(format!(
+                "'{text}' has more than {} decimals for {unit}.",
+                unit.digits()
+            ));
+        }
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid())?
+        };
+        let fraction: i64 = format!("{fraction:0<digits$}", digits = unit.digits() as usize)
+            .parse()
+            .unwrap_or(0);
+        let minor = whole
+            .checked_mul(unit.scale() as i64)
+            .and_then(|minor| minor.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        Ok(Money::new(if negative { -minor } else { minor }, unit))
+    }
+
+    pub fn to_major(&self) -> f64 {
+        self.unit.to_major(self.minor)
+    }
+
+    /// The amount without the unit, in major units with the decimals of the unit, e.g. `12.99`,
+    /// or as the stored integer, e.g. `1299`.
+    pub fn amount_text(&self, major: bool) -> String {
+        if !major {
+            return self.minor.to_string();
+        }
+        let scale = self.unit.scale() as u64;
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let abs = self.minor.unsigned_abs();
+        format!(
+            "{sign}{}.{:0digits$}",
+            abs / scale,
+            abs % scale,
+            digits = self.unit.digits() as usize
+        )
+    }
+
+    /// The price of a line of `quantity` units of this price. Line totals of fractional
+    /// quantities are rounded half away from zero to a minor unit, like
+    /// `CAST(ROUND(price * quantity) AS INTEGER)` in the totals computed by the database.
+    pub fn times(self, quantity: f64) -> Self {
+        Money::new((self.minor as f64 * quantity).round() as i64, self.unit)
+    }
+
+    /// The price per unit of a line that cost this for `quantity` units, rounded to a minor
+    /// unit. Prices are always stored per unit and totals multiply them with the quantity again,
+    /// so a line total that does not divide evenly is stored off by up to half a minor unit per
+    /// unit.
+    pub fn per_unit(self, quantity: f64, rounding: Rounding) -> Self {
+        let minor = if quantity <= 0.0 {
+            self.minor
+        } else if quantity.fract() == 0.0 {
+            rounding.div(self.minor, quantity as i64)
+        } else {
+            rounding.round(self.minor as f64 / quantity) as i64
+        };
+        Money::new(minor, self.unit)
+    }
+
+    /// `None` if the units differ, amounts of different units are never summed.
+    pub fn checked_add(self, other: Money) -> Option<Self> {
+        if self.unit != other.unit {
+            return None;
+        }
+        Some(Money::new(self.minor.checked_add(other.minor)?, self.unit))
+    }
+
+    /// `None` if the units differ, see [`Money::checked_add`].
+    pub fn checked_sub(self, other: Money) -> Option<Self> {
+        self.checked_add(Money::new(other.minor.checked_neg()?, other.unit))
+    }
+}
+
+/// The alternate flag, `{:#}`, shows the amount in major units, e.g. `12.99 NOK` instead of
+/// `1299 NOK`.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount_text(f.alternate()), self.unit)
+    }
+}
+
+/// Formats a stored amount of the unit like [`Money::amount_text`]. Amounts of unknown units
+/// are always shown as stored.
+pub fn format_amount(amount: i64, unit: &str, major: bool) -> String {
+    match Unit::from_str(unit) {
+        Ok(unit) => Money::new(amount, unit).amount_text(major),
+        Err(()) => amount.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_minor_scales_and_rounds() {
+        assert_eq!(Unit::NOK.to_minor(12.99, Rounding::HalfUp), 1299);
+        assert_eq!(Unit::EUR.to_minor(0.1 + 0.2, Rounding::HalfUp), 30);
+        assert_eq!(Unit::NOK.to_minor(-2.0, Rounding::HalfEven), -200);
+    }
+
+    #[test]
+    fn rounding_modes_differ_at_half() {
+        // values that are exactly halfway as floats
+        let halves = [
+            (0.125, 13, 12),
+            (0.375, 38, 38),
+            (-0.125, -13, -12),
+            (-0.375, -38, -38),
+            (0.005, 1, 0),
+        ];
+        for (major, half_up, half_even) in halves {
+            assert_eq!(
+                Unit::NOK.to_minor(major, Rounding::HalfUp),
+                half_up,
+                "{major}"
+            );
+            assert_eq!(
+                Unit::NOK.to_minor(major, Rounding::HalfEven),
+                half_even,
+                "{major}"
+            );
+        }
+        // only halves are rounded differently
+        for rounding in Rounding::ALL {
+            assert_eq!(Unit::NOK.to_minor(0.126, rounding), 13);
+            assert_eq!(Unit::NOK.to_minor(-0.124, rounding), -12);
+        }
+
+        for (dividend, divisor, half_up, half_even) in [
+            (5, 2, 3, 2),
+            (7, 2, 4, 4),
+            (-5, 2, -3, -2),
+            (-7, 2, -4, -4),
+            (250, 100, 3, 2),
+            (10, 4, 3, 2),
+        ] {
+            assert_eq!(Rounding::HalfUp.div(dividend, divisor), half_up);
+            assert_eq!(Rounding::HalfEven.div(dividend, divisor), half_even);
+        }
+        for rounding in Rounding::ALL {
+            assert_eq!(rounding.div(1000, 3), 333);
+            assert_eq!(rounding.div(500, 3), 167);
+            assert_eq!(rounding.div(-500, 3), -167);
+            assert_eq!(rounding.div(600, 4), 150);
+            assert_eq!(rounding.div(0, 7), 0);
+        }
+        assert_eq!(nok(5).per_unit(2.0, Rounding::HalfEven), nok(2));
+        assert_eq!(
+            Money::from_major(-0.005, Unit::NOK, Rounding::HalfEven),
+            nok(0)
+        );
+    }
+
+    #[test]
+    fn format_amount_in_major_units() {
+        assert_eq!(format_amount(1299, "NOK", true), "12.99");
+        assert_eq!(format_amount(-250, "EUR", true), "-2.50");
+        assert_eq!(format_amount(5, "EUR", true), "0.05");
+        assert_eq!(format_amount(1299, "NOK", false), "1299");
+        assert_eq!(format_amount(1299, "XYZ", true), "1299");
+    }
+
+    fn nok(minor: i64) -> Money {
+        Money::new(minor, Unit::NOK)
+    }
+
+    #[test]
+    fn per_unit_divides_the_line_total() {
+        let per_unit = |money: Money, quantity| money.per_unit(quantity, Rounding::HalfUp);
+        assert_eq!(per_unit(nok(1000), 4.0), nok(250));
+        assert_eq!(per_unit(nok(1000), 3.0), nok(333));
+        assert_eq!(per_unit(nok(500), 3.0), nok(167));
+        assert_eq!(per_unit(nok(-500), 3.0), nok(-167));
+        assert_eq!(per_unit(nok(5), 2.0), nok(3));
+        assert_eq!(per_unit(nok(1299), 1.0), nok(1299));
+        assert_eq!(per_unit(nok(1299), 0.0), nok(1299));
+        assert_eq!(per_unit(nok(1000), 0.75), nok(1333));
+        assert_eq!(per_unit(nok(100), 0.5), nok(200));
+    }
+
+    #[test]
+    fn fractional_quantities_round_the_line_total() {
+        assert_eq!(round_quantity(0.7504), 0.75);
+        assert_eq!(round_quantity(1.0 / 3.0), 0.333);
+        assert_eq!(round_quantity(2.0), 2.0);
+        assert_eq!(nok(1990).times(0.75), nok(1493));
+        assert_eq!(nok(199).times(0.75), nok(149));
+        assert_eq!(nok(-199).times(0.75), nok(-149));
+        // halves are rounded away from zero
+        assert_eq!(nok(3).times(0.5), nok(2));
+        assert_eq!(nok(-3).times(0.5), nok(-2));
+        // a line total divided by the quantity is close to the entered one again
+        let price = nok(1000).per_unit(0.75, Rounding::HalfUp);
+        assert_eq!(price.times(0.75), nok(1000));
+    }
+
+    #[test]
+    fn money_parses_major_units_exactly() {
+        assert_eq!(Money::parse("12.99", Unit::NOK), Ok(nok(1299)));
+        assert_eq!(Money::parse(" 12,9 ", Unit::NOK), Ok(nok(1290)));
+        assert_eq!(Money::parse("-3", Unit::NOK), Ok(nok(-300)));
+        assert_eq!(Money::parse("+.5", Unit::NOK), Ok(nok(50)));
+        assert_eq!(Money::parse("7.", Unit::NOK), Ok(nok(700)));
+        assert_eq!(Money::parse("-0.01", Unit::NOK), Ok(nok(-1)));
+        // 0.1 + 0.2 is not exactly 0.3 as a float, the text is
+        assert_eq!(
+            Money::parse("0.3", Unit::EUR),
+            Ok(Money::new(30, Unit::EUR))
+        );
+        for invalid in [
+            "", "-", ".", "1.2.3", "1e3", "12.999", "abc", "1 000", "--1",
+        ] {
+            assert!(Money::parse(invalid, Unit::NOK).is_err(), "{invalid}");
+        }
+        assert!(Money::parse("99999999999999999999", Unit::NOK).is_err());
+    }
+
+    #[test]
+    fn money_round_trips_through_major_units() {
+        for minor in [0, 1, 5, 99, 100, 1299, -1, -250, 123456789] {
+            let money = nok(minor);
+            for rounding in Rounding::ALL {
+                assert_eq!(
+                    Money::from_major(money.to_major(), Unit::NOK, rounding),
+                    money
+                );
+            }
+            assert_eq!(Money::parse(&money.amount_text(true), Unit::NOK), Ok(money));
+        }
+        assert_eq!(
+            Money::from_major(12.345, Unit::NOK, Rounding::HalfUp),
+            nok(1235)
+        );
+        assert_eq!(
+            Money::from_major(-0.005, Unit::NOK, Rounding::HalfUp),
+            nok(-1)
+        );
+    }
+
+    #[test]
+    fn money_is_shown_in_minor_or_major_units() {
+        assert_eq!(nok(1299).to_string(), "1299 NOK");
+        assert_eq!(format!("{:#}", nok(1299)), "12.99 NOK");
+        assert_eq!(format!("{:#}", nok(-5)), "-0.05 NOK");
+        assert_eq!(format!("{:#}", nok(700)), "7.00 NOK");
+    }
+
+    #[test]
+    fn money_of_different_units_is_not_summed() {
+        assert_eq!(nok(1990).times(3.0), nok(5970));
+        assert_eq!(nok(100).checked_add(nok(-250)), Some(nok(-150)));
+        assert_eq!(nok(100).checked_sub(nok(250)), Some(nok(-150)));
+        assert_eq!(nok(100).checked_add(Money::new(100, Unit::EUR)), None);
+        assert_eq!(nok(i64::MAX).checked_add(nok(1)), None);
+    }
+
+    #[test]
+    fn digits_match_scale() {
+        for unit in Unit::ALL {
+            assert_eq!(10u32.pow(unit.digits()), unit.scale());
+        }
+    }
+}